@@ -0,0 +1,371 @@
+#[cfg(test)]
+mod test;
+
+pub mod bsdfs {
+    use std::fmt::Debug;
+
+    use cgmath::{InnerSpace, Vector3, Zero};
+
+    use crate::material::materials::{fresnel_conductor, fresnel_dielectric, refract};
+    use crate::sampling::samplers::{cosine_sample_hemisphere, to_world, Sampler};
+    use crate::{float_consts::PI, Float};
+
+    // below this GGX turns into a mirror the sampler can't handle
+    const MIN_ALPHA: Float = 1e-3;
+
+    /// Scattering functions of a surface. Directions live in the local shading frame where the
+    /// normal is +z, both point away from the surface: `wo` toward the viewer, `wi` toward the
+    /// light.
+    pub trait Bsdf: Debug + Send + Sync {
+        /// f(wo, wi), without the cosine
+        fn eval(&self, wo: Vector3<Float>, wi: Vector3<Float>) -> Vector3<Float>;
+
+        /// Picks `wi` roughly proportional to f * cos. None when the sample is wasted.
+        fn sample(&self, wo: Vector3<Float>, sampler: &mut dyn Sampler) -> Option<BsdfSample>;
+
+        /// Solid angle density of `sample` choosing `wi`
+        fn pdf(&self, wo: Vector3<Float>, wi: Vector3<Float>) -> Float;
+    }
+
+    #[derive(Debug, Clone, Copy)]
+    pub struct BsdfSample {
+        pub wi: Vector3<Float>,
+        pub value: Vector3<Float>,
+        pub pdf: Float,
+    }
+
+    impl BsdfSample {
+        /// f * |cos| / pdf, what a path throughput gets multiplied by
+        pub fn weight(&self) -> Vector3<Float> {
+            self.value * (self.wi.z.abs() / self.pdf)
+        }
+    }
+
+    /// Which scattering model a material uses, set with the bsdf command. Materials with a
+    /// transmission color are dielectrics whatever this says.
+    #[derive(Debug, Clone, Copy, PartialEq, Default)]
+    pub enum BsdfModel {
+        #[default]
+        Lambertian,
+        Phong,
+        /// Metal with complex ior `eta` + i `k` per channel
+        Conductor {
+            eta: Vector3<Float>,
+            k: Vector3<Float>,
+        },
+    }
+
+    fn same_hemisphere(a: Vector3<Float>, b: Vector3<Float>) -> bool {
+        a.z * b.z > 0.0
+    }
+
+    /// Perceived brightness of a linear rgb color
+    pub fn luminance(c: Vector3<Float>) -> Float {
+        0.2126 * c.x + 0.7152 * c.y + 0.0722 * c.z
+    }
+
+    /// Ideal diffuse reflection
+    #[derive(Debug)]
+    pub struct Lambertian {
+        pub albedo: Vector3<Float>,
+    }
+
+    impl Bsdf for Lambertian {
+        fn eval(&self, wo: Vector3<Float>, wi: Vector3<Float>) -> Vector3<Float> {
+            if wo.z > 0.0 && wi.z > 0.0 {
+                self.albedo / PI
+            } else {
+                Vector3::zero()
+            }
+        }
+
+        fn sample(&self, wo: Vector3<Float>, sampler: &mut dyn Sampler) -> Option<BsdfSample> {
+            if wo.z <= 0.0 {
+                return None;
+            }
+            let (u1, u2) = sampler.next_2d();
+            let wi = cosine_sample_hemisphere(u1, u2);
+            (wi.z > 0.0).then(|| BsdfSample { wi, value: self.eval(wo, wi), pdf: self.pdf(wo, wi) })
+        }
+
+        fn pdf(&self, wo: Vector3<Float>, wi: Vector3<Float>) -> Float {
+            if wo.z > 0.0 && wi.z > 0.0 {
+                wi.z / PI
+            } else {
+                0.0
+            }
+        }
+    }
+
+    /// Energy conserving Phong: a Lambertian lobe plus a cos^exponent lobe around the mirror
+    /// direction, normalized so `diffuse + specular` <= 1 never creates light
+    #[derive(Debug)]
+    pub struct Phong {
+        pub diffuse: Vector3<Float>,
+        pub specular: Vector3<Float>,
+        pub exponent: Float,
+    }
+
+    impl Phong {
+        fn specular_probability(&self) -> Float {
+            let (kd, ks) = (luminance(self.diffuse), luminance(self.specular));
+            if kd + ks > 0.0 {
+                ks / (kd + ks)
+            } else {
+                0.0
+            }
+        }
+    }
+
+    fn mirror(w: Vector3<Float>) -> Vector3<Float> {
+        Vector3::new(-w.x, -w.y, w.z)
+    }
+
+    impl Bsdf for Phong {
+        fn eval(&self, wo: Vector3<Float>, wi: Vector3<Float>) -> Vector3<Float> {
+            if wo.z <= 0.0 || wi.z <= 0.0 {
+                return Vector3::zero();
+            }
+            let cos_alpha = mirror(wo).dot(wi).max(0.0);
+            self.diffuse / PI
+                + self.specular
+                    * ((self.exponent + 2.0) / (2.0 * PI) * cos_alpha.powf(self.exponent))
+        }
+
+        fn sample(&self, wo: Vector3<Float>, sampler: &mut dyn Sampler) -> Option<BsdfSample> {
+            if wo.z <= 0.0 || (self.diffuse == Vector3::zero() && self.specular == Vector3::zero())
+            {
+                return None;
+            }
+            let choice = sampler.next_1d();
+            let (u1, u2) = sampler.next_2d();
+            let wi = if choice < self.specular_probability() {
+                let cos_alpha = u1.powf(1.0 / (self.exponent + 1.0));
+                let sin_alpha = (1.0 - cos_alpha * cos_alpha).max(0.0).sqrt();
+                let phi = 2.0 * PI * u2;
+                let lobe = Vector3::new(sin_alpha * phi.cos(), sin_alpha * phi.sin(), cos_alpha);
+                to_world(lobe, mirror(wo))
+            } else {
+                cosine_sample_hemisphere(u1, u2)
+            };
+            (wi.z > 0.0).then(|| BsdfSample { wi, value: self.eval(wo, wi), pdf: self.pdf(wo, wi) })
+        }
+
+        fn pdf(&self, wo: Vector3<Float>, wi: Vector3<Float>) -> Float {
+            if wo.z <= 0.0 || wi.z <= 0.0 {
+                return 0.0;
+            }
+            let p = self.specular_probability();
+            let cos_alpha = mirror(wo).dot(wi).max(0.0);
+            (1.0 - p) * wi.z / PI
+                + p * (self.exponent + 1.0) / (2.0 * PI) * cos_alpha.powf(self.exponent)
+        }
+    }
+
+    /// GGX / Trowbridge-Reitz microfacet distribution with the height correlated Smith
+    /// shadowing term
+    #[derive(Debug, Clone, Copy)]
+    pub struct Ggx {
+        pub alpha: Float,
+    }
+
+    impl Ggx {
+        /// From the perceptual roughness of the scene file
+        pub fn from_roughness(roughness: Float) -> Ggx {
+            Ggx { alpha: (roughness * roughness).max(MIN_ALPHA) }
+        }
+
+        pub fn d(&self, h: Vector3<Float>) -> Float {
+            let cos2 = h.z * h.z;
+            let a2 = self.alpha * self.alpha;
+            let denom = cos2 * (a2 - 1.0) + 1.0;
+            a2 / (PI * denom * denom)
+        }
+
+        fn lambda(&self, w: Vector3<Float>) -> Float {
+            let cos2 = w.z * w.z;
+            if cos2 == 0.0 {
+                return Float::INFINITY;
+            }
+            let tan2 = (1.0 - cos2).max(0.0) / cos2;
+            ((1.0 + self.alpha * self.alpha * tan2).sqrt() - 1.0) / 2.0
+        }
+
+        pub fn g(&self, wo: Vector3<Float>, wi: Vector3<Float>) -> Float {
+            1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+        }
+
+        /// Microfacet normal with density D(h) * cos(theta_h), always above the surface
+        pub fn sample_h(&self, u1: Float, u2: Float) -> Vector3<Float> {
+            let tan2 = self.alpha * self.alpha * u1 / (1.0 - u1).max(Float::EPSILON);
+            let cos_theta = 1.0 / (1.0 + tan2).sqrt();
+            let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+            let phi = 2.0 * PI * u2;
+            Vector3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta)
+        }
+
+        pub fn pdf_h(&self, h: Vector3<Float>) -> Float {
+            self.d(h) * h.z.abs()
+        }
+    }
+
+    /// Rough metal
+    #[derive(Debug)]
+    pub struct GgxConductor {
+        pub distribution: Ggx,
+        pub eta: Vector3<Float>,
+        pub k: Vector3<Float>,
+    }
+
+    impl GgxConductor {
+        fn fresnel(&self, cos_i: Float) -> Vector3<Float> {
+            Vector3::new(
+                fresnel_conductor(cos_i, self.eta.x, self.k.x),
+                fresnel_conductor(cos_i, self.eta.y, self.k.y),
+                fresnel_conductor(cos_i, self.eta.z, self.k.z),
+            )
+        }
+    }
+
+    impl Bsdf for GgxConductor {
+        fn eval(&self, wo: Vector3<Float>, wi: Vector3<Float>) -> Vector3<Float> {
+            if wo.z <= 0.0 || wi.z <= 0.0 {
+                return Vector3::zero();
+            }
+            let h = (wo + wi).normalize();
+            let ggx = &self.distribution;
+            self.fresnel(wo.dot(h)) * (ggx.d(h) * ggx.g(wo, wi) / (4.0 * wo.z * wi.z))
+        }
+
+        fn sample(&self, wo: Vector3<Float>, sampler: &mut dyn Sampler) -> Option<BsdfSample> {
+            if wo.z <= 0.0 {
+                return None;
+            }
+            let (u1, u2) = sampler.next_2d();
+            let h = self.distribution.sample_h(u1, u2);
+            let wi = h * (2.0 * wo.dot(h)) - wo;
+            (wi.z > 0.0).then(|| BsdfSample { wi, value: self.eval(wo, wi), pdf: self.pdf(wo, wi) })
+        }
+
+        fn pdf(&self, wo: Vector3<Float>, wi: Vector3<Float>) -> Float {
+            if wo.z <= 0.0 || wi.z <= 0.0 {
+                return 0.0;
+            }
+            let h = (wo + wi).normalize();
+            self.distribution.pdf_h(h) / (4.0 * wo.dot(h))
+        }
+    }
+
+    /// Rough glass (Walter et al. 2007). The outside is +z, `ior` is the inside over the
+    /// outside. Light entering the surface is tinted by `transmission`. Radiance isn't scaled
+    /// by the squared ior ratio, like the smooth glass of the integrators.
+    #[derive(Debug)]
+    pub struct RoughDielectric {
+        pub distribution: Ggx,
+        pub ior: Float,
+        pub transmission: Vector3<Float>,
+    }
+
+    impl RoughDielectric {
+        /// Transmitted over incident ior, seen from the side of `wo`
+        fn eta(&self, wo: Vector3<Float>) -> Float {
+            if wo.z > 0.0 {
+                self.ior
+            } else {
+                1.0 / self.ior
+            }
+        }
+
+        /// Microfacet normal between `wo` and `wi`, oriented to +z. None when degenerate.
+        fn half_vector(&self, wo: Vector3<Float>, wi: Vector3<Float>) -> Option<Vector3<Float>> {
+            let h = if same_hemisphere(wo, wi) { wo + wi } else { wo + wi * self.eta(wo) };
+            if h.magnitude2() == 0.0 {
+                return None;
+            }
+            let h = h.normalize();
+            Some(if h.z < 0.0 { -h } else { h })
+        }
+
+        fn fresnel(&self, wo: Vector3<Float>, h: Vector3<Float>) -> Float {
+            fresnel_dielectric(wo.dot(h).abs(), 1.0 / self.eta(wo))
+        }
+    }
+
+    impl Bsdf for RoughDielectric {
+        fn eval(&self, wo: Vector3<Float>, wi: Vector3<Float>) -> Vector3<Float> {
+            if wo.z == 0.0 || wi.z == 0.0 {
+                return Vector3::zero();
+            }
+            let Some(h) = self.half_vector(wo, wi) else {
+                return Vector3::zero();
+            };
+            let ggx = &self.distribution;
+            let d_g = ggx.d(h) * ggx.g(wo, wi);
+            let f = self.fresnel(wo, h);
+
+            if same_hemisphere(wo, wi) {
+                let value = f * d_g / (4.0 * (wo.z * wi.z).abs());
+                return Vector3::new(value, value, value);
+            }
+
+            // both directions must be on the same side of the microfacet they refract through
+            let (wo_h, wi_h) = (wo.dot(h), wi.dot(h));
+            if wo_h * wi_h >= 0.0 {
+                return Vector3::zero();
+            }
+            let eta = self.eta(wo);
+            let denom = wo_h + eta * wi_h;
+            let value = (1.0 - f) * d_g * eta * eta * (wi_h * wo_h).abs()
+                / ((wi.z * wo.z).abs() * denom * denom);
+            let tint = if wo.z > 0.0 { self.transmission } else { Vector3::new(1.0, 1.0, 1.0) };
+            tint * value
+        }
+
+        fn sample(&self, wo: Vector3<Float>, sampler: &mut dyn Sampler) -> Option<BsdfSample> {
+            if wo.z == 0.0 {
+                return None;
+            }
+            let choice = sampler.next_1d();
+            let (u1, u2) = sampler.next_2d();
+            let h = self.distribution.sample_h(u1, u2);
+
+            let reflected = choice < self.fresnel(wo, h);
+            let wi = if reflected {
+                h * (2.0 * wo.dot(h)) - wo
+            } else {
+                // refract through the microfacet facing wo
+                let facing_h = if wo.dot(h) < 0.0 { -h } else { h };
+                refract(-wo, facing_h, 1.0 / self.eta(wo))?
+            };
+
+            if same_hemisphere(wo, wi) != reflected {
+                return None;
+            }
+            let pdf = self.pdf(wo, wi);
+            (pdf > 0.0).then(|| BsdfSample { wi, value: self.eval(wo, wi), pdf })
+        }
+
+        fn pdf(&self, wo: Vector3<Float>, wi: Vector3<Float>) -> Float {
+            if wo.z == 0.0 || wi.z == 0.0 {
+                return 0.0;
+            }
+            let Some(h) = self.half_vector(wo, wi) else {
+                return 0.0;
+            };
+            let pdf_h = self.distribution.pdf_h(h);
+            let f = self.fresnel(wo, h);
+
+            if same_hemisphere(wo, wi) {
+                return f * pdf_h / (4.0 * wo.dot(h).abs());
+            }
+
+            let (wo_h, wi_h) = (wo.dot(h), wi.dot(h));
+            if wo_h * wi_h >= 0.0 {
+                return 0.0;
+            }
+            let eta = self.eta(wo);
+            let denom = wo_h + eta * wi_h;
+            (1.0 - f) * pdf_h * eta * eta * wi_h.abs() / (denom * denom)
+        }
+    }
+}
@@ -0,0 +1,178 @@
+#[cfg(test)]
+mod bsdf_test {
+
+    use cgmath::{InnerSpace, Vector3};
+
+    use crate::bsdf::bsdfs::{Bsdf, Ggx, GgxConductor, Lambertian, Phong, RoughDielectric};
+    use crate::sampling::samplers::{Rng, Sampler};
+    use crate::{float_consts::PI, Float};
+    use approx::assert_relative_eq;
+
+    const SAMPLES: usize = 200000;
+    const TOLERANCE: Float = if cfg!(feature = "f32") { 1e-3 } else { 1e-6 };
+
+    fn direction(theta: Float, phi: Float) -> Vector3<Float> {
+        Vector3::new(theta.sin() * phi.cos(), theta.sin() * phi.sin(), theta.cos())
+    }
+
+    fn uniform_sphere(rng: &mut Rng) -> Vector3<Float> {
+        let z = 1.0 - 2.0 * rng.next_1d();
+        let r = (1.0 - z * z).max(0.0).sqrt();
+        let phi = 2.0 * PI * rng.next_1d();
+        Vector3::new(r * phi.cos(), r * phi.sin(), z)
+    }
+
+    fn all_bsdfs() -> Vec<(&'static str, Box<dyn Bsdf>)> {
+        let white = Vector3::new(1.0, 1.0, 1.0);
+        vec![
+            ("lambertian", Box::new(Lambertian { albedo: white * 0.8 })),
+            (
+                "phong",
+                Box::new(Phong { diffuse: white * 0.3, specular: white * 0.7, exponent: 20.0 }),
+            ),
+            (
+                // k this high reflects everything, so only shadowing loses energy
+                "ggx conductor",
+                Box::new(GgxConductor {
+                    distribution: Ggx::from_roughness(0.5),
+                    eta: white * 0.2,
+                    k: white * 100.0,
+                }),
+            ),
+            (
+                "rough dielectric",
+                Box::new(RoughDielectric {
+                    distribution: Ggx::from_roughness(0.4),
+                    ior: 1.5,
+                    transmission: white,
+                }),
+            ),
+        ]
+    }
+
+    /// Monte Carlo estimate of the fraction of light leaving toward `wo`
+    fn albedo(bsdf: &dyn Bsdf, wo: Vector3<Float>, rng: &mut Rng) -> Vector3<Float> {
+        // summed in f64, f32 would drift over this many samples
+        let mut sum = Vector3::new(0.0, 0.0, 0.0);
+        for _ in 0..SAMPLES {
+            if let Some(sample) = bsdf.sample(wo, rng) {
+                sum += sample.weight().cast::<f64>().unwrap();
+            }
+        }
+        (sum / SAMPLES as f64).cast::<Float>().unwrap()
+    }
+
+    #[test]
+    fn should_reflect_lambertian_albedo() {
+        let bsdf = Lambertian { albedo: Vector3::new(0.8, 0.5, 0.2) };
+        let mut rng = Rng::new(0, 0);
+
+        let albedo = albedo(&bsdf, direction(0.7, 0.3), &mut rng);
+
+        assert_relative_eq!(albedo.x, 0.8, epsilon = TOLERANCE);
+        assert_relative_eq!(albedo.z, 0.2, epsilon = TOLERANCE);
+    }
+
+    #[test]
+    fn should_conserve_energy() {
+        let mut rng = Rng::new(1, 0);
+        for (name, bsdf) in all_bsdfs() {
+            for theta in [0.1, 0.8, 1.4] {
+                // given light leaving toward wo
+                let wo = direction(theta, 1.0);
+
+                // when adding up everything the bsdf scatters
+                let albedo = albedo(bsdf.as_ref(), wo, &mut rng);
+
+                // then it never creates light. Lobes cut by the horizon lose some at grazing
+                // angles, but a sampler bug would lose far more.
+                assert!(albedo.x <= 1.01, "{} at {} reflects {}", name, theta, albedo.x);
+                assert!(albedo.x > 0.3, "{} at {} reflects {}", name, theta, albedo.x);
+            }
+        }
+    }
+
+    #[test]
+    fn should_be_reciprocal() {
+        let mut rng = Rng::new(2, 0);
+        for (name, bsdf) in all_bsdfs() {
+            for _ in 0..1000 {
+                // given two directions above the surface
+                let (mut a, mut b) = (uniform_sphere(&mut rng), uniform_sphere(&mut rng));
+                a.z = a.z.abs();
+                b.z = b.z.abs();
+
+                // then light goes both ways alike
+                let (ab, ba) = (bsdf.eval(a, b).x, bsdf.eval(b, a).x);
+                assert_relative_eq!(ab, ba, epsilon = TOLERANCE, max_relative = TOLERANCE);
+                assert!(ab.is_finite(), "{} gives {}", name, ab);
+            }
+        }
+    }
+
+    #[test]
+    fn should_transmit_reciprocally_up_to_ior() {
+        // given a direction outside and one inside the glass
+        let bsdf = RoughDielectric {
+            distribution: Ggx::from_roughness(0.4),
+            ior: 1.5,
+            transmission: Vector3::new(1.0, 1.0, 1.0),
+        };
+        let mut rng = Rng::new(3, 0);
+        for _ in 0..1000 {
+            let (mut outside, mut inside) = (uniform_sphere(&mut rng), uniform_sphere(&mut rng));
+            outside.z = outside.z.abs();
+            inside.z = -inside.z.abs();
+
+            // then swapping them only changes the value by the squared ior ratio
+            let into = bsdf.eval(outside, inside).x;
+            let out = bsdf.eval(inside, outside).x;
+            assert_relative_eq!(
+                into,
+                out * 1.5 * 1.5,
+                epsilon = TOLERANCE,
+                max_relative = TOLERANCE
+            );
+        }
+    }
+
+    #[test]
+    fn should_have_normalized_pdf() {
+        // midpoint rule on a z, phi grid, uniform in solid angle and steadier than random
+        // samples around the sharp lobes
+        let steps = 1000;
+        let cell = 4.0 * PI / (steps * steps) as Float;
+        for (name, bsdf) in all_bsdfs() {
+            let wo = direction(0.5, 2.0);
+
+            // when integrating the pdf over the sphere
+            let mut integral = 0.0;
+            for i in 0..steps {
+                let z = 1.0 - 2.0 * (i as Float + 0.5) / steps as Float;
+                let r = (1.0 - z * z).sqrt();
+                for j in 0..steps {
+                    let phi = 2.0 * PI * (j as Float + 0.5) / steps as Float;
+                    integral += bsdf.pdf(wo, Vector3::new(r * phi.cos(), r * phi.sin(), z)) * cell;
+                }
+            }
+
+            // then no more than all samples land anywhere
+            assert!(integral <= 1.01 && integral > 0.9, "{} pdf integrates to {}", name, integral);
+        }
+    }
+
+    #[test]
+    fn should_sample_the_pdf_it_reports() {
+        let mut rng = Rng::new(5, 0);
+        for (name, bsdf) in all_bsdfs() {
+            let wo = direction(0.9, -1.0);
+            for _ in 0..1000 {
+                if let Some(sample) = bsdf.sample(wo, &mut rng) {
+                    assert_relative_eq!(sample.wi.magnitude(), 1.0, epsilon = 1e-6);
+                    assert!(sample.pdf > 0.0, "{} sampled a zero pdf direction", name);
+                    assert_relative_eq!(sample.pdf, bsdf.pdf(wo, sample.wi), max_relative = 1e-6);
+                }
+            }
+        }
+    }
+}
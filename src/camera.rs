@@ -1,5 +1,5 @@
 pub mod camera_view {
-    use std::f64::consts::PI;
+    use crate::{float_consts::PI, Float};
 
     use cgmath::{InnerSpace, Vector3};
 
@@ -7,30 +7,30 @@ pub mod camera_view {
     pub struct Camera {
         pub width: u32,
         pub height: u32,
-        pub fov_y: f64,
-        pub fov_x: f64,
-        pub half_fov_y: f64,
-        pub tan_half_fov_x: f64,
-        pub tan_half_fov_y: f64,
-        pub half_height: f64,
-        pub half_width: f64,
-        pub look_from: Vector3<f64>,
-        pub look_at: Vector3<f64>,
-        pub up: Vector3<f64>,
-        pub w: Vector3<f64>,
-        pub u: Vector3<f64>,
-        pub v: Vector3<f64>,
+        pub fov_y: Float,
+        pub fov_x: Float,
+        pub half_fov_y: Float,
+        pub tan_half_fov_x: Float,
+        pub tan_half_fov_y: Float,
+        pub half_height: Float,
+        pub half_width: Float,
+        pub look_from: Vector3<Float>,
+        pub look_at: Vector3<Float>,
+        pub up: Vector3<Float>,
+        pub w: Vector3<Float>,
+        pub u: Vector3<Float>,
+        pub v: Vector3<Float>,
     }
 
     #[derive(Debug, Clone, Copy)]
     pub struct Ray {
-        pub o: Vector3<f64>,
-        pub d: Vector3<f64>,
-        pub t: f64,
+        pub o: Vector3<Float>,
+        pub d: Vector3<Float>,
+        pub t: Float,
     }
 
     impl Ray {
-        pub fn new(o: Vector3<f64>, d: Vector3<f64>, t: f64) -> Ray {
+        pub fn new(o: Vector3<Float>, d: Vector3<Float>, t: Float) -> Ray {
             Ray { o, d, t }
         }
     }
@@ -39,10 +39,10 @@ pub mod camera_view {
         pub fn new(
             width: u32,
             height: u32,
-            look_from: Vector3<f64>,
-            look_at: Vector3<f64>,
-            up: Vector3<f64>,
-            fov_y: f64,
+            look_from: Vector3<Float>,
+            look_at: Vector3<Float>,
+            up: Vector3<Float>,
+            fov_y: Float,
         ) -> Self {
             let mut camera = Camera {
                 width,
@@ -81,7 +81,7 @@ pub mod camera_view {
 
             // vertical fov
             let fovy_rads = self.fov_y * PI / 180.0;
-            let aspect_ratio = (self.width / self.height) as f64;
+            let aspect_ratio = (self.width / self.height) as Float;
 
             self.fov_x = 2.0 * (((fovy_rads / 2.0).tan()) * aspect_ratio).atan();
 
@@ -90,11 +90,11 @@ pub mod camera_view {
 
             self.tan_half_fov_y = (fovy_rads / 2.0).tan();
 
-            self.half_height = self.height as f64 / 2.0;
-            self.half_width = self.width as f64 / 2.0;
+            self.half_height = self.height as Float / 2.0;
+            self.half_width = self.width as Float / 2.0;
         }
 
-        pub fn ray_thru_pixel(&self, x_mid: f64, y_mid: f64) -> Ray {
+        pub fn ray_thru_pixel(&self, x_mid: Float, y_mid: Float) -> Ray {
             let alpha = self.tan_half_fov_x * ((x_mid - self.half_width) / self.half_width);
             let beta = self.tan_half_fov_y * ((self.half_height - y_mid) / self.half_height);
 
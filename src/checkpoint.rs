@@ -0,0 +1,95 @@
+#[cfg(test)]
+mod test;
+
+pub mod checkpoints {
+    use std::fs::{self, File};
+    use std::io::{self, BufReader, BufWriter, Read, Write};
+    use std::path::Path;
+
+    use crate::ray_tracer::tracer::{Image, RenderState};
+    use crate::sampling::samplers::RunningStats;
+    use crate::Float;
+
+    const MAGIC: &[u8; 4] = b"RTCK";
+    const VERSION: u32 = 1;
+
+    /// FNV-1a hash of the scene description, stable from one run and build to the next
+    pub fn scene_hash(parts: &[&[u8]]) -> u64 {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for part in parts {
+            // the length keeps ("ab", "c") and ("a", "bc") apart
+            for byte in (part.len() as u64).to_le_bytes().iter().chain(part.iter()) {
+                hash ^= *byte as u64;
+                hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+            }
+        }
+        hash
+    }
+
+    /// Writes the state of a progressive render for the scene with hash `scene`. Goes through
+    /// a file next to `path` moved in place, so a render stopped while saving keeps the
+    /// previous checkpoint.
+    pub fn save(path: &Path, scene: u64, state: &RenderState) -> io::Result<()> {
+        let partial = path.with_extension("partial");
+        let mut out = BufWriter::new(File::create(&partial)?);
+        out.write_all(MAGIC)?;
+        write_u32(&mut out, VERSION)?;
+        out.write_all(&scene.to_le_bytes())?;
+        write_u32(&mut out, state.passes)?;
+        state.image.write(&mut out)?;
+        for stats in &state.stats {
+            stats.write(&mut out)?;
+        }
+        out.into_inner().map_err(|it| it.into_error())?.sync_all()?;
+        fs::rename(partial, path)
+    }
+
+    /// Reads back what `save` wrote, refusing checkpoints of any other scene than `scene` or
+    /// of another image `size`
+    pub fn load(path: &Path, scene: u64, size: (u32, u32)) -> io::Result<RenderState> {
+        let mut input = BufReader::new(File::open(path)?);
+        let mut magic = [0; 4];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC || read_u32(&mut input)? != VERSION {
+            return Err(invalid("not a checkpoint of this renderer version"));
+        }
+        let mut hash = [0; 8];
+        input.read_exact(&mut hash)?;
+        if u64::from_le_bytes(hash) != scene {
+            return Err(invalid("checkpoint was made for another scene"));
+        }
+
+        let passes = read_u32(&mut input)?;
+        let image = Image::read(&mut input, size)?;
+        let stats = (0..image.width as usize * image.height as usize)
+            .map(|_| RunningStats::read(&mut input))
+            .collect::<io::Result<Vec<_>>>()?;
+        Ok(RenderState { image, stats, passes })
+    }
+
+    fn invalid(message: &str) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, message)
+    }
+
+    pub fn write_u32(out: &mut impl Write, value: u32) -> io::Result<()> {
+        out.write_all(&value.to_le_bytes())
+    }
+
+    pub fn read_u32(input: &mut impl Read) -> io::Result<u32> {
+        let mut bytes = [0; 4];
+        input.read_exact(&mut bytes)?;
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    /// Floats are stored as f64 so f32 and f64 builds read each other's checkpoints
+    #[allow(clippy::unnecessary_cast)] // only a no-op in f64 builds
+    pub fn write_float(out: &mut impl Write, value: Float) -> io::Result<()> {
+        out.write_all(&(value as f64).to_le_bytes())
+    }
+
+    pub fn read_float(input: &mut impl Read) -> io::Result<Float> {
+        let mut bytes = [0; 8];
+        input.read_exact(&mut bytes)?;
+        Ok(f64::from_le_bytes(bytes) as Float)
+    }
+}
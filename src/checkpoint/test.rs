@@ -0,0 +1,108 @@
+#[cfg(test)]
+mod checkpoint_test {
+
+    use std::io::ErrorKind;
+    use std::path::PathBuf;
+
+    use approx::assert_relative_eq;
+
+    use crate::checkpoint::checkpoints::{load, save, scene_hash};
+    use crate::integrator::integrators::from_name;
+    use crate::ray_tracer::tracer::{Progressive, RayTracer, RenderState};
+    use crate::{read_scene, Scene};
+
+    /// Path traced scene of 4 passes
+    fn scene() -> Scene {
+        let mut scene = read_scene("tests/fixtures/area-lights.test".to_string());
+        scene.settings.spp = 4;
+        scene.settings.integrator = from_name("path", &[]).unwrap();
+        scene
+    }
+
+    fn render(scene: &Scene, spec: &str, resume: Option<RenderState>) -> RenderState {
+        let progressive = Progressive::parse(&spec.split_whitespace().collect::<Vec<_>>());
+        RayTracer {}.ray_trace_progressive(scene, &progressive.unwrap(), resume, |_, _| {})
+    }
+
+    fn checkpoint_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "my-ray-tracer-{}-{}.checkpoint",
+            name,
+            std::process::id()
+        ))
+    }
+
+    #[test]
+    fn should_hash_scene_contents() {
+        assert_eq!(scene_hash(&[b"size 4 4", b""]), scene_hash(&[b"size 4 4", b""]));
+        assert_ne!(scene_hash(&[b"size 4 4", b""]), scene_hash(&[b"size 4 5", b""]));
+        assert_ne!(scene_hash(&[b"ab", b"c"]), scene_hash(&[b"a", b"bc"]));
+    }
+
+    #[test]
+    fn should_read_back_saved_state() {
+        // given a render stopped after its first pass
+        let scene = scene();
+        let state = render(&scene, "1 passes budget 0", None);
+        let path = checkpoint_path("round-trip");
+
+        // when saving and loading it
+        save(&path, 42, &state).unwrap();
+        let loaded = load(&path, 42, (4, 4)).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        // then nothing is lost
+        assert_eq!(loaded.passes, 1);
+        assert_relative_eq!(loaded.noise(), state.noise());
+        for (x, y) in (0..4).flat_map(|y| (0..4).map(move |x| (x, y))) {
+            assert_eq!(loaded.image.radiance(x, y), state.image.radiance(x, y));
+            assert_eq!(loaded.image.samples(x, y), 1);
+        }
+    }
+
+    #[test]
+    fn should_refuse_checkpoint_of_other_scene() {
+        let state = render(&scene(), "1 passes budget 0", None);
+        let path = checkpoint_path("other-scene");
+
+        save(&path, 42, &state).unwrap();
+        let error = load(&path, 43, (4, 4)).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        assert_eq!(error.to_string(), "checkpoint was made for another scene");
+    }
+
+    #[test]
+    fn should_refuse_checkpoint_of_other_size() {
+        let state = render(&scene(), "1 passes budget 0", None);
+        let path = checkpoint_path("other-size");
+
+        save(&path, 42, &state).unwrap();
+        let error = load(&path, 42, (400_000, 300_000)).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        assert_eq!(error.to_string(), "image is 4x4, expected 400000x300000");
+    }
+
+    #[test]
+    fn should_resume_to_same_image_as_uninterrupted_render() {
+        // given a render interrupted after one pass and checkpointed
+        let scene = scene();
+        let path = checkpoint_path("resume");
+        save(&path, 7, &render(&scene, "1 passes budget 0", None)).unwrap();
+
+        // when resuming it and rendering it in one go
+        let resumed = render(&scene, "1 passes", Some(load(&path, 7, (4, 4)).unwrap()));
+        let straight = render(&scene, "1 passes", None);
+        std::fs::remove_file(&path).unwrap();
+
+        // then the remaining passes pick up the samples where they stopped
+        assert_eq!(resumed.passes, 4);
+        for (x, y) in (0..4).flat_map(|y| (0..4).map(move |x| (x, y))) {
+            assert_eq!(resumed.image.radiance(x, y), straight.image.radiance(x, y));
+            assert_eq!(resumed.image.samples(x, y), 4);
+        }
+    }
+}
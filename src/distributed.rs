@@ -0,0 +1,310 @@
+#[cfg(test)]
+mod test;
+
+pub mod workers {
+    use std::collections::VecDeque;
+    use std::io::{self, BufReader, BufWriter, Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::path::Path;
+    use std::process::{Child, Command, Stdio};
+    use std::sync::Mutex;
+    use std::thread;
+    use std::time::Duration;
+
+    use log::{info, warn};
+    use rayon::prelude::*;
+
+    use crate::checkpoint::checkpoints::{read_u32, write_u32};
+    use crate::progress::reporting::{count_rays, take_rays, ProgressBar};
+    use crate::ray_tracer::tracer::{tile_padding, Image, RayTracer};
+    use crate::tile::tiles::{self, Tile};
+    use crate::Scene;
+
+    const MAGIC: &[u8; 4] = b"RTWK";
+    const VERSION: u32 = 1;
+    /// How long a TCP worker may stay silent before it counts as failed and its tiles go to the
+    /// others, well beyond what a batch of tiles takes
+    pub const WORKER_TIMEOUT: Duration = Duration::from_secs(600);
+
+    /// Byte streams to and from a worker, a TCP connection or the pipes of a child process.
+    ///
+    /// The worker opens with its scene hash and thread count. Jobs are a tile count followed
+    /// by the tiles, each answered with the images of the tiles and the rays they took; a count
+    /// of zero ends the session.
+    pub struct Connection {
+        name: String,
+        input: BufReader<Box<dyn Read + Send>>,
+        out: BufWriter<Box<dyn Write + Send>>,
+        child: Option<Child>,
+    }
+
+    impl Connection {
+        pub fn new(
+            name: &str,
+            input: Box<dyn Read + Send>,
+            out: Box<dyn Write + Send>,
+        ) -> Connection {
+            Connection {
+                name: name.to_string(),
+                input: BufReader::new(input),
+                out: BufWriter::new(out),
+                child: None,
+            }
+        }
+
+        /// Worker started with `--listen` at `address`, failing reads once it says nothing for
+        /// `timeout`
+        pub fn connect(address: &str, timeout: Duration) -> io::Result<Connection> {
+            let stream = TcpStream::connect(address)?;
+            stream.set_nodelay(true)?;
+            stream.set_read_timeout(Some(timeout))?;
+            Ok(Connection::new(address, Box::new(stream.try_clone()?), Box::new(stream)))
+        }
+
+        /// Starts `program` with `args` and talks to it over its stdin and stdout, its stderr
+        /// goes to ours
+        pub fn spawn(program: &Path, args: &[String]) -> io::Result<Connection> {
+            let mut child = Command::new(program)
+                .args(args)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::inherit())
+                .spawn()?;
+            let input = child.stdout.take().unwrap();
+            let out = child.stdin.take().unwrap();
+            let name = format!("process {}", child.id());
+            let mut connection = Connection::new(&name, Box::new(input), Box::new(out));
+            connection.child = Some(child);
+            Ok(connection)
+        }
+
+        /// Reads the greeting of the worker and returns its thread count
+        fn hello(&mut self, scene: u64) -> io::Result<u32> {
+            let mut magic = [0; 4];
+            self.input.read_exact(&mut magic)?;
+            if &magic != MAGIC || read_u32(&mut self.input)? != VERSION {
+                return Err(invalid("not a worker of this renderer version"));
+            }
+            let mut hash = [0; 8];
+            self.input.read_exact(&mut hash)?;
+            if u64::from_le_bytes(hash) != scene {
+                return Err(invalid("worker loaded another scene"));
+            }
+            Ok(read_u32(&mut self.input)?.max(1))
+        }
+
+        /// Has the worker render `tiles`, returns their images and the rays they took
+        fn render(&mut self, tiles: &[Tile], padding: u32) -> io::Result<(Vec<Image>, u64)> {
+            write_u32(&mut self.out, tiles.len() as u32)?;
+            for tile in tiles {
+                for value in [tile.x, tile.y, tile.width, tile.height] {
+                    write_u32(&mut self.out, value)?;
+                }
+            }
+            self.out.flush()?;
+
+            let mut images = Vec::with_capacity(tiles.len());
+            for tile in tiles {
+                let size = (tile.width + 2 * padding, tile.height + 2 * padding);
+                images.push(Image::read(&mut self.input, size)?);
+            }
+            let mut rays = [0; 8];
+            self.input.read_exact(&mut rays)?;
+            Ok((images, u64::from_le_bytes(rays)))
+        }
+
+        /// Ends the session and waits for a child worker to exit
+        fn finish(mut self) -> io::Result<()> {
+            write_u32(&mut self.out, 0)?;
+            self.out.flush()?;
+            if let Some(mut child) = self.child.take() {
+                child.wait()?;
+            }
+            Ok(())
+        }
+    }
+
+    impl Drop for Connection {
+        // a child that failed must not outlive the render
+        fn drop(&mut self) {
+            if let Some(child) = &mut self.child {
+                let _ = child.kill();
+                let _ = child.wait();
+            }
+        }
+    }
+
+    /// Renders the frame on the workers behind `connections`, which must have loaded the scene
+    /// with hash `hash`. Each worker gets as many tiles at a time as it has threads. Tiles of
+    /// workers that fail go back to the others, or get rendered here once none is left. The
+    /// tiles are merged in the order of the settings, so the image is the one a local render
+    /// makes.
+    pub fn render(scene: &Scene, hash: u64, connections: Vec<Connection>) -> Image {
+        let cam = scene.cams.first().unwrap();
+        let settings = &scene.settings;
+        let tiles = tiles::split(cam.width, cam.height, settings.tile_size, settings.tile_order);
+        let padding = tile_padding(scene);
+
+        let pending: Mutex<VecDeque<usize>> = Mutex::new((0..tiles.len()).collect());
+        let rendered: Vec<Mutex<Option<Image>>> = tiles.iter().map(|_| Mutex::new(None)).collect();
+        let progress = ProgressBar::new(cam.width as u64 * cam.height as u64);
+        thread::scope(|scope| {
+            for connection in connections {
+                let (tiles, pending, rendered, progress) = (&tiles, &pending, &rendered, &progress);
+                scope.spawn(move || {
+                    let name = connection.name.clone();
+                    let job = Job { hash, padding, tiles, pending, rendered, progress };
+                    if let Err(e) = job.dispatch(connection) {
+                        warn!("Worker {} failed: {}", name, e);
+                    }
+                });
+            }
+        });
+
+        let tracer = RayTracer {};
+        let rendered: Vec<Option<Image>> =
+            rendered.into_iter().map(|it| it.into_inner().unwrap()).collect();
+        let left = rendered.iter().filter(|it| it.is_none()).count();
+        if left > 0 {
+            warn!("No worker rendered {} tiles, rendering them here", left);
+        }
+        let images: Vec<Image> = tiles
+            .par_iter()
+            .zip(rendered)
+            .map(|(tile, image)| {
+                image.unwrap_or_else(|| {
+                    let image = tracer.render_tile(scene, tile);
+                    progress.advance(tile.width as u64 * tile.height as u64);
+                    image
+                })
+            })
+            .collect();
+        progress.finish();
+        tracer.merge_tiles(scene, &tiles, images)
+    }
+
+    /// What the thread driving one worker shares with the others
+    struct Job<'a> {
+        hash: u64,
+        padding: u32,
+        tiles: &'a [Tile],
+        pending: &'a Mutex<VecDeque<usize>>,
+        rendered: &'a [Mutex<Option<Image>>],
+        progress: &'a ProgressBar,
+    }
+
+    impl Job<'_> {
+        fn dispatch(&self, mut connection: Connection) -> io::Result<()> {
+            let threads = connection.hello(self.hash)?;
+            info!("Worker {} joined with {} threads", connection.name, threads);
+            loop {
+                let batch: Vec<usize> = {
+                    let mut pending = self.pending.lock().unwrap();
+                    let count = (threads as usize).min(pending.len());
+                    pending.drain(..count).collect()
+                };
+                if batch.is_empty() {
+                    return connection.finish();
+                }
+
+                let tiles: Vec<Tile> = batch.iter().map(|&index| self.tiles[index]).collect();
+                match connection.render(&tiles, self.padding) {
+                    Ok((images, rays)) => {
+                        count_rays(rays);
+                        for ((index, tile), image) in batch.iter().zip(&tiles).zip(images) {
+                            *self.rendered[*index].lock().unwrap() = Some(image);
+                            self.progress.advance(tile.width as u64 * tile.height as u64);
+                        }
+                    },
+                    Err(e) => {
+                        self.pending.lock().unwrap().extend(batch);
+                        return Err(e);
+                    },
+                }
+            }
+        }
+    }
+
+    /// Worker side of a connection: renders the jobs of a coordinator on the thread pool until
+    /// it's done. `hash` tells the coordinator which scene was loaded.
+    pub fn serve(scene: &Scene, hash: u64, input: impl Read, out: impl Write) -> io::Result<()> {
+        let cam = scene.cams.first().unwrap();
+        let size = scene.settings.tile_size.max(1);
+        // no job asks for more tiles than the frame has
+        let frame_tiles = cam.width.div_ceil(size) as u64 * cam.height.div_ceil(size) as u64;
+        let (mut input, mut out) = (BufReader::new(input), BufWriter::new(out));
+        out.write_all(MAGIC)?;
+        write_u32(&mut out, VERSION)?;
+        out.write_all(&hash.to_le_bytes())?;
+        write_u32(&mut out, rayon::current_num_threads() as u32)?;
+        out.flush()?;
+
+        let tracer = RayTracer {};
+        loop {
+            let count = read_u32(&mut input)?;
+            if count == 0 {
+                return Ok(());
+            }
+            if count as u64 > frame_tiles {
+                return Err(invalid("more tiles than the frame has"));
+            }
+            let mut tiles = Vec::with_capacity(count as usize);
+            for _ in 0..count {
+                let (x, y) = (read_u32(&mut input)?, read_u32(&mut input)?);
+                let (width, height) = (read_u32(&mut input)?, read_u32(&mut input)?);
+                if x.saturating_add(width) > cam.width || y.saturating_add(height) > cam.height {
+                    return Err(invalid("tile outside the frame"));
+                }
+                tiles.push(Tile { x, y, width, height });
+            }
+
+            let rendered: Vec<(Image, u64)> = tiles
+                .par_iter()
+                .map(|tile| {
+                    let image = tracer.render_tile(scene, tile);
+                    (image, take_rays())
+                })
+                .collect();
+            let mut rays = 0;
+            for (image, tile_rays) in &rendered {
+                image.write(&mut out)?;
+                rays += tile_rays;
+            }
+            out.write_all(&rays.to_le_bytes())?;
+            out.flush()?;
+        }
+    }
+
+    /// Serves every coordinator connecting to `listener`, each on a thread of its own, until
+    /// the process is stopped
+    pub fn listen(scene: &Scene, hash: u64, listener: TcpListener) -> io::Result<()> {
+        info!("Waiting for coordinators on {}", listener.local_addr()?);
+        thread::scope(|scope| {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        warn!("Failed to accept a coordinator: {}", e);
+                        continue;
+                    },
+                };
+                scope.spawn(move || {
+                    let peer =
+                        stream.peer_addr().map_or("unknown".to_string(), |it| it.to_string());
+                    info!("Coordinator {} connected", peer);
+                    let served =
+                        stream.try_clone().and_then(|input| serve(scene, hash, input, &stream));
+                    match served {
+                        Ok(()) => info!("Coordinator {} is done", peer),
+                        Err(e) => warn!("Coordinator {} failed: {}", peer, e),
+                    }
+                });
+            }
+            Ok(())
+        })
+    }
+
+    fn invalid(message: &str) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, message)
+    }
+}
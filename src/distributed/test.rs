@@ -0,0 +1,178 @@
+#[cfg(test)]
+mod distributed_test {
+
+    use std::io::{self, Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+    use std::time::Duration;
+
+    use crate::distributed::workers::{render, serve, Connection, WORKER_TIMEOUT};
+    use crate::filter::filters::Filter;
+    use crate::integrator::integrators::from_name;
+    use crate::ray_tracer::tracer::{Image, RayTracer};
+    use crate::sampling::samplers::AdaptiveSampling;
+    use crate::{read_scene, Scene};
+
+    const HASH: u64 = 42;
+
+    /// Path traced scene of one pixel tiles, its filter reaches into the neighbouring ones and
+    /// noisy pixels take more samples
+    fn scene() -> Scene {
+        let mut scene = read_scene("tests/fixtures/area-lights.test".to_string());
+        scene.settings.integrator = from_name("path", &[]).unwrap();
+        scene.settings.tile_size = 1;
+        scene.settings.filter = Filter::Gaussian { radius: 1.0, alpha: 2.0 };
+        scene.settings.adaptive = Some(AdaptiveSampling { threshold: 0.05, max_spp: 32 });
+        scene
+    }
+
+    fn assert_same_image(actual: &Image, expected: &Image) {
+        assert_eq!((actual.width, actual.height), (expected.width, expected.height));
+        for y in 0..expected.height {
+            for x in 0..expected.width {
+                assert_eq!(actual.radiance(x, y), expected.radiance(x, y), "pixel {} {}", x, y);
+            }
+        }
+        assert_eq!(actual.average_samples(), expected.average_samples());
+    }
+
+    /// Connection to a worker serving `scene` with `hash` on a thread of `scope`, over pipes
+    fn pipe_worker<'scope>(
+        scope: &'scope thread::Scope<'scope, '_>,
+        scene: &'scope Scene,
+        hash: u64,
+    ) -> Connection {
+        let (jobs, job_writer) = io::pipe().unwrap();
+        let (result_reader, results) = io::pipe().unwrap();
+        scope.spawn(move || serve(scene, hash, jobs, results));
+        Connection::new("pipe", Box::new(result_reader), Box::new(job_writer))
+    }
+
+    #[test]
+    fn should_render_same_image_as_local_render_over_pipes() {
+        // given a scene rendered here
+        let scene = scene();
+        let expected = RayTracer {}.ray_trace_par(&scene);
+
+        // when two workers render it
+        let image = thread::scope(|scope| {
+            let connections =
+                vec![pipe_worker(scope, &scene, HASH), pipe_worker(scope, &scene, HASH)];
+            render(&scene, HASH, connections)
+        });
+
+        // then merging their tiles makes the same image
+        assert_same_image(&image, &expected);
+    }
+
+    #[test]
+    fn should_render_same_image_as_local_render_over_tcp() {
+        // given a scene rendered here and a worker listening on a free port
+        let scene = scene();
+        let expected = RayTracer {}.ray_trace_par(&scene);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+
+        // when the coordinator connects to it twice
+        let image = thread::scope(|scope| {
+            let scene = &scene;
+            scope.spawn(move || {
+                for stream in listener.incoming().take(2) {
+                    let stream = stream.unwrap();
+                    scope.spawn(move || serve(scene, HASH, stream.try_clone().unwrap(), &stream));
+                }
+            });
+            let connections = vec![
+                Connection::connect(&address, WORKER_TIMEOUT).unwrap(),
+                Connection::connect(&address, WORKER_TIMEOUT).unwrap(),
+            ];
+            render(scene, HASH, connections)
+        });
+
+        // then the tiles come back the same as rendered here
+        assert_same_image(&image, &expected);
+    }
+
+    #[test]
+    fn should_render_tiles_of_failed_workers_elsewhere() {
+        // given a scene rendered here
+        let scene = scene();
+        let expected = RayTracer {}.ray_trace_par(&scene);
+
+        // when one worker loaded another scene, one hangs up mid job and one works
+        let image = thread::scope(|scope| {
+            // greets like a real worker, then hangs up on its first job
+            let mut greeting = b"RTWK".to_vec();
+            greeting.extend(1u32.to_le_bytes());
+            greeting.extend(HASH.to_le_bytes());
+            greeting.extend(4u32.to_le_bytes());
+            let dropout = Connection::new(
+                "dropout",
+                Box::new(io::Cursor::new(greeting)),
+                Box::new(io::sink()),
+            );
+            let connections = vec![
+                pipe_worker(scope, &scene, HASH + 1),
+                dropout,
+                pipe_worker(scope, &scene, HASH),
+            ];
+            render(&scene, HASH, connections)
+        });
+
+        // then the tiles of the others are taken care of
+        assert_same_image(&image, &expected);
+    }
+
+    #[test]
+    fn should_give_up_on_hung_worker() {
+        // given a scene rendered here and a worker that greets, then never answers
+        let scene = scene();
+        let expected = RayTracer {}.ray_trace_par(&scene);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+
+        // when the coordinator waits on it for a moment only
+        let image = thread::scope(|scope| {
+            scope.spawn(move || {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut greeting = b"RTWK".to_vec();
+                greeting.extend(1u32.to_le_bytes());
+                greeting.extend(HASH.to_le_bytes());
+                greeting.extend(4u32.to_le_bytes());
+                stream.write_all(&greeting).unwrap();
+                // holds the connection open without reading jobs, until the coordinator leaves
+                let _ = stream.read(&mut [0; 1]);
+            });
+            let hung = Connection::connect(&address, Duration::from_millis(200)).unwrap();
+            render(&scene, HASH, vec![hung])
+        });
+
+        // then its tiles are rendered here
+        assert_same_image(&image, &expected);
+    }
+
+    #[test]
+    fn should_render_everything_here_without_workers() {
+        // given a scene none of the workers could be reached for
+        let scene = scene();
+
+        // when rendering without any
+        let image = render(&scene, HASH, vec![]);
+
+        // then the coordinator renders the frame itself
+        assert_same_image(&image, &RayTracer {}.ray_trace_par(&scene));
+    }
+
+    #[test]
+    fn should_refuse_job_of_more_tiles_than_the_frame_has() {
+        // given a job claiming four billion tiles of a 4 by 4 frame
+        let scene = scene();
+        let job = u32::MAX.to_le_bytes();
+
+        // when a worker reads it
+        let result = serve(&scene, HASH, &job[..], io::sink());
+
+        // then it stops before reading or allocating any of them
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,203 @@
+#[cfg(test)]
+mod test;
+
+pub mod environments {
+    use std::fs::File;
+    use std::io::BufReader;
+    use std::path::Path;
+
+    use cgmath::{InnerSpace, Vector3, Zero};
+    use image::codecs::hdr::{HdrDecoder, HdrMetadata};
+
+    use crate::bsdf::bsdfs::luminance;
+    use crate::light::lights::LightSample;
+    use crate::sampling::samplers::Distribution1D;
+    use crate::sky::daylight::PreethamSky;
+    use crate::{float_consts::PI, Float};
+
+    /// What rays leaving the scene see. It also lights the scene from infinitely far away, so
+    /// shading samples it like any other light.
+    #[derive(Debug, Default)]
+    pub enum Environment {
+        #[default]
+        Black,
+        Constant(Vector3<Float>),
+        /// `horizon` blending into `zenith` going up, the ground gets the horizon color
+        Gradient {
+            horizon: Vector3<Float>,
+            zenith: Vector3<Float>,
+        },
+        Map(EnvironmentMap),
+        /// Analytic daylight, the sun itself is a separate directional light
+        Sky(PreethamSky),
+    }
+
+    impl Environment {
+        pub fn is_black(&self) -> bool {
+            match self {
+                Environment::Black => true,
+                Environment::Constant(color) => *color == Vector3::zero(),
+                _ => false,
+            }
+        }
+
+        /// Radiance arriving along `-d`, `d` doesn't need to be unit length
+        pub fn radiance(&self, d: Vector3<Float>) -> Vector3<Float> {
+            match self {
+                Environment::Black => Vector3::zero(),
+                Environment::Constant(color) => *color,
+                Environment::Gradient { horizon, zenith } => {
+                    let up = d.normalize().y.max(0.0);
+                    horizon * (1.0 - up) + zenith * up
+                },
+                Environment::Map(map) => map.radiance(d.normalize()),
+                Environment::Sky(sky) => sky.radiance(d),
+            }
+        }
+
+        /// Direction toward the environment from two uniform numbers, weighted by its pdf.
+        /// Maps are importance sampled by luminance, daylight samples the upper hemisphere and
+        /// the other skies the whole sphere uniformly.
+        pub fn illuminate(&self, u: (Float, Float)) -> LightSample {
+            let (wi, pdf) = match self {
+                Environment::Map(map) => map.sample(u),
+                Environment::Sky(_) => {
+                    let (x, y, z) = uniform_sample_sphere(u).into();
+                    (Vector3::new(x, y.abs(), z), 1.0 / (2.0 * PI))
+                },
+                _ => (uniform_sample_sphere(u), 1.0 / (4.0 * PI)),
+            };
+            let radiance = if pdf > 0.0 { self.radiance(wi) / pdf } else { Vector3::zero() };
+            LightSample { wi, distance: Float::INFINITY, radiance, pdf }
+        }
+
+        /// Solid angle density `illuminate` has for direction `d`
+        pub fn pdf(&self, d: Vector3<Float>) -> Float {
+            match self {
+                Environment::Black => 0.0,
+                Environment::Map(map) => map.pdf(d.normalize()),
+                Environment::Sky(_) if d.y < 0.0 => 0.0,
+                Environment::Sky(_) => 1.0 / (2.0 * PI),
+                _ => 1.0 / (4.0 * PI),
+            }
+        }
+    }
+
+    /// Equirectangular radiance map: u follows the azimuth, v goes from straight up (+y) at the
+    /// top row to straight down at the bottom one. Texels are looked up without filtering so
+    /// radiance and sampling pdf agree exactly.
+    #[derive(Debug)]
+    pub struct EnvironmentMap {
+        width: usize,
+        height: usize,
+        /// Row major, top row first
+        texels: Vec<Vector3<Float>>,
+        rows: Distribution1D,
+        columns: Vec<Distribution1D>,
+    }
+
+    impl EnvironmentMap {
+        /// Reads an `.hdr` or `.exr` file, `scale` multiplies its radiance
+        pub fn load(path: &Path, scale: Float) -> image::ImageResult<EnvironmentMap> {
+            let texel = |rgb: &[f32]| {
+                Vector3::new(rgb[0] as Float, rgb[1] as Float, rgb[2] as Float) * scale
+            };
+
+            // the generic loader tone maps radiance files to 8 bits, their decoder keeps floats
+            if path.extension().is_some_and(|it| it.eq_ignore_ascii_case("hdr")) {
+                let decoder = HdrDecoder::new(BufReader::new(File::open(path)?))?;
+                let HdrMetadata { width, height, .. } = decoder.metadata();
+                let texels = decoder.read_image_hdr()?.iter().map(|it| texel(&it.0)).collect();
+                return Ok(EnvironmentMap::from_texels(width as usize, height as usize, texels));
+            }
+
+            let image = image::open(path)?.into_rgb32f();
+            let texels = image.pixels().map(|it| texel(&it.0)).collect();
+            Ok(EnvironmentMap::from_texels(image.width() as usize, image.height() as usize, texels))
+        }
+
+        pub fn from_texels(
+            width: usize,
+            height: usize,
+            texels: Vec<Vector3<Float>>,
+        ) -> EnvironmentMap {
+            assert_eq!(texels.len(), width * height, "environment map size mismatch");
+
+            // rows near the poles cover less solid angle
+            let columns: Vec<Distribution1D> = (0..height)
+                .map(|y| {
+                    let sin_theta = (PI * (y as Float + 0.5) / height as Float).sin();
+                    let row = &texels[y * width..(y + 1) * width];
+                    let weights: Vec<Float> =
+                        row.iter().map(|it| luminance(*it) * sin_theta).collect();
+                    Distribution1D::new(&weights)
+                })
+                .collect();
+            let rows =
+                Distribution1D::new(&columns.iter().map(|it| it.total()).collect::<Vec<_>>());
+
+            EnvironmentMap { width, height, texels, rows, columns }
+        }
+
+        pub fn radiance(&self, d: Vector3<Float>) -> Vector3<Float> {
+            let (u, v) = direction_to_uv(d);
+            self.texels[self.texel_index(u, v)]
+        }
+
+        /// Direction picked proportionally to luminance and its solid angle pdf
+        pub fn sample(&self, (u1, u2): (Float, Float)) -> (Vector3<Float>, Float) {
+            if self.rows.total() <= 0.0 {
+                return (Vector3::unit_y(), 0.0);
+            }
+            let (v, row, pdf_v) = self.rows.sample(u1);
+            let (u, _, pdf_u) = self.columns[row].sample(u2);
+
+            let d = uv_to_direction(u, v);
+            let sin_theta = (PI * v).sin();
+            if sin_theta <= 0.0 {
+                return (d, 0.0);
+            }
+            // the (u, v) square maps to the sphere with dw = 2 pi^2 sin(theta) du dv
+            (d, pdf_u * pdf_v / (2.0 * PI * PI * sin_theta))
+        }
+
+        /// Solid angle density `sample` has for direction `d`
+        pub fn pdf(&self, d: Vector3<Float>) -> Float {
+            let (u, v) = direction_to_uv(d);
+            let sin_theta = (PI * v).sin();
+            if self.rows.total() <= 0.0 || sin_theta <= 0.0 {
+                return 0.0;
+            }
+            let index = self.texel_index(u, v);
+            let (row, column) = (index / self.width, index % self.width);
+            self.rows.pdf(row) * self.columns[row].pdf(column) / (2.0 * PI * PI * sin_theta)
+        }
+
+        fn texel_index(&self, u: Float, v: Float) -> usize {
+            let x = ((u * self.width as Float) as usize).min(self.width - 1);
+            let y = ((v * self.height as Float) as usize).min(self.height - 1);
+            y * self.width + x
+        }
+    }
+
+    /// Equirectangular coordinates of a unit direction, both in [0, 1]. -z is the middle of
+    /// the map.
+    pub fn direction_to_uv(d: Vector3<Float>) -> (Float, Float) {
+        let u = 0.5 + d.x.atan2(-d.z) / (2.0 * PI);
+        let v = d.y.clamp(-1.0, 1.0).acos() / PI;
+        (u, v)
+    }
+
+    pub fn uv_to_direction(u: Float, v: Float) -> Vector3<Float> {
+        let phi = 2.0 * PI * (u - 0.5);
+        let theta = PI * v;
+        Vector3::new(theta.sin() * phi.sin(), theta.cos(), -theta.sin() * phi.cos())
+    }
+
+    fn uniform_sample_sphere((u1, u2): (Float, Float)) -> Vector3<Float> {
+        let z = 1.0 - 2.0 * u1;
+        let r = (1.0 - z * z).max(0.0).sqrt();
+        let phi = 2.0 * PI * u2;
+        Vector3::new(r * phi.cos(), r * phi.sin(), z)
+    }
+}
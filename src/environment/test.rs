@@ -0,0 +1,156 @@
+#[cfg(test)]
+mod environment_test {
+
+    use std::path::Path;
+
+    use cgmath::{InnerSpace, Vector3, Zero};
+
+    use crate::environment::environments::{
+        direction_to_uv, uv_to_direction, Environment, EnvironmentMap,
+    };
+    use crate::sampling::samplers::{Rng, Sampler};
+    use crate::{float_consts::PI, read_scene, Float};
+    use approx::assert_relative_eq;
+
+    const TOLERANCE: Float = if cfg!(feature = "f32") { 1e-4 } else { 1e-6 };
+
+    /// Monte Carlo estimate of the radiance integrated over the whole sphere
+    fn integrate(environment: &Environment, samples: u32) -> Vector3<Float> {
+        let mut rng = Rng::new(11, 0);
+        let mut sum = Vector3::<f64>::zero();
+        for _ in 0..samples {
+            sum += environment.illuminate(rng.next_2d()).radiance.cast::<f64>().unwrap();
+        }
+        (sum / samples as f64).cast::<Float>().unwrap()
+    }
+
+    /// 8x4 map, mostly dim with one bright texel
+    fn spotty_map() -> EnvironmentMap {
+        let mut texels = vec![Vector3::new(0.1, 0.2, 0.3); 32];
+        texels[8 + 5] = Vector3::new(50.0, 40.0, 30.0);
+        texels[24] = Vector3::new(0.0, 0.0, 0.0);
+        EnvironmentMap::from_texels(8, 4, texels)
+    }
+
+    #[test]
+    fn should_map_directions_to_uv_and_back() {
+        // -z is the center of the map, +y the top
+        let (u, v) = direction_to_uv(Vector3::new(0.0, 0.0, -1.0));
+        assert_relative_eq!(u, 0.5);
+        assert_relative_eq!(v, 0.5, epsilon = TOLERANCE);
+        assert_relative_eq!(direction_to_uv(Vector3::unit_y()).1, 0.0);
+
+        for (u, v) in [(0.1, 0.2), (0.5, 0.5), (0.75, 0.9), (0.3, 0.6)] {
+            let d = uv_to_direction(u, v);
+            assert_relative_eq!(d.magnitude(), 1.0, epsilon = TOLERANCE);
+            let (u2, v2) = direction_to_uv(d);
+            assert_relative_eq!(u2, u, epsilon = TOLERANCE);
+            assert_relative_eq!(v2, v, epsilon = TOLERANCE);
+        }
+    }
+
+    #[test]
+    fn should_blend_gradient_sky() {
+        let sky = Environment::Gradient {
+            horizon: Vector3::new(1.0, 1.0, 1.0),
+            zenith: Vector3::new(0.0, 0.0, 1.0),
+        };
+
+        assert_relative_eq!(sky.radiance(Vector3::new(0.0, 2.0, 0.0)).x, 0.0);
+        assert_relative_eq!(sky.radiance(Vector3::new(1.0, 0.0, 0.0)).x, 1.0);
+        assert_relative_eq!(
+            sky.radiance(Vector3::new(1.0, 1.0, 0.0)).x,
+            1.0 - (0.5 as Float).sqrt()
+        );
+        assert_relative_eq!(sky.radiance(Vector3::new(0.0, -1.0, 0.0)).x, 1.0);
+    }
+
+    #[test]
+    fn should_integrate_constant_sky() {
+        // given a constant sky sampled over the sphere
+        let sky = Environment::Constant(Vector3::new(1.0, 0.5, 0.0));
+
+        // then every sample weighs radiance / (1 / 4 pi)
+        assert_relative_eq!(integrate(&sky, 16).x, 4.0 * PI, epsilon = TOLERANCE);
+        assert!(Environment::Black.is_black());
+        assert!(Environment::Constant(Vector3::zero()).is_black());
+        assert!(!sky.is_black());
+    }
+
+    #[test]
+    fn should_importance_sample_environment_map() {
+        // given a map with one texel far brighter than the rest
+        let map = spotty_map();
+        let mut rng = Rng::new(5, 0);
+
+        // when sampling it, most directions point at the bright texel
+        let bright = Vector3::new(50.0, 40.0, 30.0);
+        let hits =
+            (0..1000).filter(|_| map.radiance(map.sample(rng.next_2d()).0) == bright).count();
+        assert!(hits > 900, "{} samples on the bright texel", hits);
+
+        // and sample and pdf agree
+        for _ in 0..100 {
+            let (d, pdf) = map.sample(rng.next_2d());
+            assert_relative_eq!(pdf, map.pdf(d), max_relative = 1e-3);
+        }
+
+        // and the black texel is never picked
+        assert_relative_eq!(map.pdf(uv_to_direction(0.05, 0.8)), 0.0);
+    }
+
+    #[test]
+    fn should_integrate_environment_map() {
+        // given the exact integral of the map, texel by texel solid angle
+        let map = spotty_map();
+        let mut expected = Vector3::zero();
+        for y in 0..4 {
+            let (top, bottom) =
+                ((PI * y as Float / 4.0).cos(), (PI * (y + 1) as Float / 4.0).cos());
+            for x in 0..8 {
+                let center = uv_to_direction((x as Float + 0.5) / 8.0, (y as Float + 0.5) / 4.0);
+                expected += map.radiance(center) * (2.0 * PI / 8.0 * (top - bottom));
+            }
+        }
+
+        // when integrating with importance sampling
+        let estimate = integrate(&Environment::Map(map), 20000);
+
+        // then both agree on every channel
+        assert_relative_eq!(estimate.x, expected.x, max_relative = 1e-2);
+        assert_relative_eq!(estimate.y, expected.y, max_relative = 1e-2);
+        assert_relative_eq!(estimate.z, expected.z, max_relative = 1e-2);
+    }
+
+    #[test]
+    fn should_read_environment_map() {
+        // given a 4x2 hdr with a (1, 1, 2) sky over a 0.5 ground, scaled by 2
+        let scene = read_scene("tests/fixtures/environment.test".to_string());
+
+        // then looking up and down reads both halves
+        let up = scene.environment.radiance(Vector3::new(0.3, 1.0, 0.2));
+        assert_eq!(up, Vector3::new(2.0, 2.0, 4.0));
+        let down = scene.environment.radiance(Vector3::new(-0.5, -1.0, 0.0));
+        assert_eq!(down, Vector3::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn should_load_exr_environment() {
+        // given a 2x1 exr, the left half bright and the right one black
+        let map = EnvironmentMap::load(Path::new("tests/fixtures/two-texels.exr"), 1.0).unwrap();
+
+        // then the left half of the map is toward -x
+        assert_eq!(map.radiance(Vector3::new(-1.0, 0.0, 0.0)), Vector3::new(3.5, 0.25, 8.0));
+        assert_eq!(map.radiance(Vector3::new(1.0, 0.0, 0.0)), Vector3::zero());
+    }
+
+    #[test]
+    fn should_read_sky() {
+        let scene = read_scene("tests/fixtures/furnace-sky.test".to_string());
+
+        match scene.environment {
+            Environment::Constant(color) => assert_relative_eq!(color.x, 1.0),
+            _ => unreachable!(),
+        }
+    }
+}
@@ -0,0 +1,108 @@
+#[cfg(test)]
+mod test;
+
+pub mod filters {
+    use crate::{float_consts::PI, Float};
+
+    /// Reconstruction filter: how much a sample counts toward a pixel whose center is `dx`,
+    /// `dy` pixels away. Every filter is separable and vanishes `radius` pixels out.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum Filter {
+        /// Each sample counts fully in the pixels it covers, a plain average with radius 1/2
+        Box { radius: Float },
+        /// Weight falling linearly to zero at the radius
+        Tent { radius: Float },
+        /// Gaussian of falloff `alpha`, shifted down to reach zero at the radius
+        Gaussian { radius: Float, alpha: Float },
+        /// Mitchell-Netravali cubic, `b` blurs and `c` rings. Its negative lobes sharpen.
+        Mitchell { radius: Float, b: Float, c: Float },
+        /// Sinc windowed by a wider sinc that reaches zero at the radius
+        Lanczos { radius: Float },
+    }
+
+    impl Default for Filter {
+        fn default() -> Self {
+            Filter::Box { radius: 0.5 }
+        }
+    }
+
+    impl Filter {
+        /// Builds a filter from its name in the scene file and its optional parameters:
+        /// - `box [radius]`, 0.5 by default
+        /// - `tent [radius]`, 1
+        /// - `gaussian [radius] [alpha]`, 1.5 and 2
+        /// - `mitchell [radius] [b] [c]`, 2, 1/3 and 1/3
+        /// - `lanczos [radius]`, 3
+        ///
+        /// None for unknown names and radii that aren't positive.
+        pub fn from_name(name: &str, args: &[Float]) -> Option<Filter> {
+            let arg = |i: usize, default: Float| args.get(i).copied().unwrap_or(default);
+            match name {
+                "box" => Some(Filter::Box { radius: arg(0, 0.5) }),
+                "tent" => Some(Filter::Tent { radius: arg(0, 1.0) }),
+                "gaussian" => Some(Filter::Gaussian { radius: arg(0, 1.5), alpha: arg(1, 2.0) }),
+                "mitchell" => Some(Filter::Mitchell {
+                    radius: arg(0, 2.0),
+                    b: arg(1, 1.0 / 3.0),
+                    c: arg(2, 1.0 / 3.0),
+                }),
+                "lanczos" => Some(Filter::Lanczos { radius: arg(0, 3.0) }),
+                _ => None,
+            }
+            .filter(|it| it.radius() > 0.0)
+        }
+
+        pub fn radius(&self) -> Float {
+            match *self {
+                Filter::Box { radius }
+                | Filter::Tent { radius }
+                | Filter::Gaussian { radius, .. }
+                | Filter::Mitchell { radius, .. }
+                | Filter::Lanczos { radius } => radius,
+            }
+        }
+
+        pub fn eval(&self, dx: Float, dy: Float) -> Float {
+            self.eval_1d(dx) * self.eval_1d(dy)
+        }
+
+        fn eval_1d(&self, x: Float) -> Float {
+            let radius = self.radius();
+            let x = x.abs();
+            if x > radius {
+                return 0.0;
+            }
+            match *self {
+                Filter::Box { .. } => 1.0,
+                Filter::Tent { .. } => radius - x,
+                Filter::Gaussian { alpha, .. } => {
+                    ((-alpha * x * x).exp() - (-alpha * radius * radius).exp()).max(0.0)
+                },
+                Filter::Mitchell { b, c, .. } => mitchell(2.0 * x / radius, b, c),
+                Filter::Lanczos { .. } => sinc(x) * sinc(x / radius),
+            }
+        }
+    }
+
+    /// The Mitchell-Netravali cubic over [0, 2]
+    fn mitchell(x: Float, b: Float, c: Float) -> Float {
+        let (x2, x3) = (x * x, x * x * x);
+        let value = if x < 1.0 {
+            (12.0 - 9.0 * b - 6.0 * c) * x3 + (-18.0 + 12.0 * b + 6.0 * c) * x2 + (6.0 - 2.0 * b)
+        } else {
+            (-b - 6.0 * c) * x3
+                + (6.0 * b + 30.0 * c) * x2
+                + (-12.0 * b - 48.0 * c) * x
+                + (8.0 * b + 24.0 * c)
+        };
+        value / 6.0
+    }
+
+    fn sinc(x: Float) -> Float {
+        if x.abs() < 1e-5 {
+            1.0
+        } else {
+            (PI * x).sin() / (PI * x)
+        }
+    }
+}
@@ -0,0 +1,71 @@
+#[cfg(test)]
+mod filter_test {
+
+    use crate::filter::filters::Filter;
+    use crate::Float;
+    use approx::assert_relative_eq;
+
+    fn all_filters() -> Vec<Filter> {
+        ["box", "tent", "gaussian", "mitchell", "lanczos"]
+            .iter()
+            .map(|name| Filter::from_name(name, &[]).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn should_read_filters_with_default_parameters() {
+        assert_eq!(Filter::default(), Filter::Box { radius: 0.5 });
+        assert_eq!(
+            Filter::from_name("gaussian", &[2.0]),
+            Some(Filter::Gaussian { radius: 2.0, alpha: 2.0 })
+        );
+        assert_eq!(
+            Filter::from_name("mitchell", &[]),
+            Some(Filter::Mitchell { radius: 2.0, b: 1.0 / 3.0, c: 1.0 / 3.0 })
+        );
+        assert_eq!(Filter::from_name("lanczos", &[]).map(|it| it.radius()), Some(3.0));
+        assert_eq!(Filter::from_name("sinc", &[]), None);
+        assert_eq!(Filter::from_name("box", &[0.0]), None);
+        assert_eq!(Filter::from_name("tent", &[-1.0]), None);
+    }
+
+    #[test]
+    fn should_peak_at_center_and_vanish_at_radius() {
+        for filter in all_filters() {
+            let radius = filter.radius();
+            assert!(filter.eval(0.0, 0.0) > 0.0, "{:?}", filter);
+            assert!(filter.eval(0.0, 0.0) >= filter.eval(0.3, 0.2), "{:?}", filter);
+            assert_eq!(filter.eval(radius + 0.01, 0.0), 0.0, "{:?}", filter);
+            assert_eq!(filter.eval(0.0, -radius - 0.01), 0.0, "{:?}", filter);
+            if !matches!(filter, Filter::Box { .. }) {
+                assert_relative_eq!(filter.eval(radius, 0.0), 0.0, epsilon = 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn should_sharpen_with_negative_lobes() {
+        // given the filters with lobes below zero
+        let mitchell = Filter::from_name("mitchell", &[]).unwrap();
+        let lanczos = Filter::from_name("lanczos", &[]).unwrap();
+
+        // then samples a pixel and a half away count against it
+        assert!(mitchell.eval(1.5, 0.0) < 0.0);
+        assert!(lanczos.eval(1.5, 0.0) < 0.0);
+    }
+
+    #[test]
+    fn should_normalize_mitchell_cubic() {
+        // given the 1D Mitchell-Netravali cubic, the filter along x over its value at 0
+        let filter = Filter::from_name("mitchell", &[]).unwrap();
+        let steps = 4000;
+        let dx = 4.0 / steps as Float;
+
+        // when integrating over its support
+        let integral: Float =
+            (0..steps).map(|i| filter.eval(-2.0 + (i as Float + 0.5) * dx, 0.0) * dx).sum();
+
+        // then the cubic integrates to one
+        assert_relative_eq!(integral / filter.eval(0.0, 0.0).sqrt(), 1.0, epsilon = 1e-4);
+    }
+}
@@ -0,0 +1,699 @@
+#[cfg(test)]
+mod test;
+
+pub mod integrators {
+    use std::fmt::Debug;
+
+    use cgmath::{ElementWise, InnerSpace, Vector3, Zero};
+
+    use crate::camera::camera_view::Ray;
+    use crate::light::lights::{Light, LightSample};
+    use crate::material::materials::{fresnel_dielectric, reflect, refract, Material};
+    use crate::medium::media::{HomogeneousMedium, MediumSample};
+    use crate::ray_tracer::tracer::{HitInfo, RayTracer, TestHit, RAY_EPSILON};
+    use crate::sampling::samplers::{cosine_sample_hemisphere, to_world, Frame, Sampler};
+    use crate::texture::textures::SurfacePoint;
+    use crate::{Float, Scene};
+
+    // russian roulette only kicks in after the first bounces, they carry most of the light
+    const ROULETTE_START_BOUNCE: u32 = 3;
+
+    // scene queries, it has no state
+    const TRACER: RayTracer = RayTracer {};
+
+    /// Turns a camera ray into radiance. The render loop owns pixels and samples, an integrator
+    /// only decides what a ray sees, so new ones plug in without touching `RayTracer`.
+    pub trait Integrator: Debug + Send + Sync {
+        fn radiance(&self, ray: &Ray, scene: &Scene, sampler: &mut dyn Sampler) -> Vector3<Float>;
+    }
+
+    /// Builds an integrator from its name in the scene file or the command line, followed by
+    /// its optional parameters:
+    /// - `flat`
+    /// - `normals`
+    /// - `depth [far]`
+    /// - `whitted`
+    /// - `ao [samples] [max distance]`
+    /// - `path`
+    pub fn from_name(name: &str, args: &[Float]) -> Option<Box<dyn Integrator>> {
+        let arg = |i: usize, default: Float| args.get(i).copied().unwrap_or(default);
+        match name {
+            "flat" => Some(Box::new(FlatColor)),
+            "normals" => Some(Box::new(Normals)),
+            "depth" => Some(Box::new(Depth { far: arg(0, 20.0) })),
+            "whitted" => Some(Box::new(Whitted)),
+            "ao" => Some(Box::new(AmbientOcclusion {
+                samples: arg(0, 16.0) as u32,
+                max_distance: arg(1, Float::INFINITY),
+            })),
+            "path" => Some(Box::new(PathTracing)),
+            _ => None,
+        }
+    }
+
+    /// Flat shape colors, glass still refracts
+    #[derive(Debug)]
+    pub struct FlatColor;
+
+    impl FlatColor {
+        fn trace(&self, ray: &Ray, scene: &Scene, depth: i32) -> Vector3<Float> {
+            if depth > scene.settings.trace_depth {
+                return Vector3::zero();
+            }
+            let TestHit::Hit(info) = TRACER.intersect(ray, scene) else {
+                return scene.environment.radiance(ray.d);
+            };
+
+            let material = &scene.materials[info.material() as usize];
+            if material.is_dielectric() {
+                trace_dielectric(ray, &info, material, depth, &mut |ray, depth| {
+                    self.trace(ray, scene, depth)
+                })
+            } else {
+                info.color().to_radiance()
+            }
+        }
+    }
+
+    impl Integrator for FlatColor {
+        fn radiance(&self, ray: &Ray, scene: &Scene, _: &mut dyn Sampler) -> Vector3<Float> {
+            self.trace(ray, scene, 0)
+        }
+    }
+
+    /// World space normals mapped from [-1, 1] to [0, 1]
+    #[derive(Debug)]
+    pub struct Normals;
+
+    impl Integrator for Normals {
+        fn radiance(&self, ray: &Ray, scene: &Scene, _: &mut dyn Sampler) -> Vector3<Float> {
+            match TRACER.intersect(ray, scene) {
+                TestHit::Hit(info) => (info.normal() + Vector3::new(1.0, 1.0, 1.0)) / 2.0,
+                TestHit::NoHit => Vector3::zero(),
+            }
+        }
+    }
+
+    /// Distance to the first hit, white up close fading to black at `far`
+    #[derive(Debug)]
+    pub struct Depth {
+        pub far: Float,
+    }
+
+    impl Integrator for Depth {
+        fn radiance(&self, ray: &Ray, scene: &Scene, _: &mut dyn Sampler) -> Vector3<Float> {
+            match TRACER.intersect(ray, scene) {
+                TestHit::Hit(info) => {
+                    let distance = info.t_value() * ray.d.magnitude();
+                    let gray = 1.0 - (distance / self.far).min(1.0);
+                    Vector3::new(gray, gray, gray)
+                },
+                TestHit::NoHit => Vector3::zero(),
+            }
+        }
+    }
+
+    /// Classic Whitted ray tracing: ambient + emission + Phong lighting from every visible
+    /// light, plus a mirror ray weighted by the specular color, up to `trace_depth` bounces.
+    /// Fog dims what rays see and scatters light from the lights into them once, media inside
+    /// shapes only show in the shadows they cast. With ambient occlusion set, the ambient term
+    /// of camera hits is scaled by how open their hemisphere is.
+    #[derive(Debug)]
+    pub struct Whitted;
+
+    impl Whitted {
+        fn trace(
+            &self,
+            ray: &Ray,
+            scene: &Scene,
+            depth: i32,
+            sampler: &mut dyn Sampler,
+        ) -> Vector3<Float> {
+            if depth > scene.settings.trace_depth {
+                return Vector3::zero();
+            }
+            let hit = TRACER.intersect(ray, scene);
+
+            let Some(fog) = &scene.fog else {
+                return self.shade(ray, hit, scene, depth, sampler);
+            };
+            match fog.sample(hit_distance(ray, &hit), sampler.next_2d()) {
+                MediumSample::Scatter { distance, weight } => {
+                    let d = ray.d.normalize();
+                    let p = ray.o + d * distance;
+                    weight.mul_element_wise(in_scattered(scene, p, d, fog, sampler))
+                },
+                MediumSample::Pass { weight } => {
+                    weight.mul_element_wise(self.shade(ray, hit, scene, depth, sampler))
+                },
+            }
+        }
+
+        /// Light leaving the surface `ray` hit toward its origin
+        fn shade(
+            &self,
+            ray: &Ray,
+            hit: TestHit,
+            scene: &Scene,
+            depth: i32,
+            sampler: &mut dyn Sampler,
+        ) -> Vector3<Float> {
+            let TestHit::Hit(info) = hit else {
+                return scene.environment.radiance(ray.d);
+            };
+            if scene.materials[info.material() as usize].is_medium_boundary() {
+                let through =
+                    Ray::new(info.point() + ray.d.normalize() * RAY_EPSILON, ray.d, ray.t);
+                return self.trace(&through, scene, depth, sampler);
+            }
+
+            let (info, material) = surface(scene, info);
+            let material = &material;
+            if material.is_dielectric() {
+                return trace_dielectric(ray, &info, material, depth, &mut |ray, depth| {
+                    self.trace(ray, scene, depth, sampler)
+                });
+            }
+
+            let d = ray.d.normalize();
+            let n = facing(info.normal(), d);
+            let origin = info.point() + n * RAY_EPSILON;
+
+            // occlusion only darkens what the camera sees directly, reflections keep it flat
+            let ambient = match &scene.settings.ambient_occlusion {
+                Some(ao) if depth == 0 => {
+                    material.ambient * ao.visibility(scene, origin, n, sampler)
+                },
+                _ => material.ambient,
+            };
+            let mut color = ambient + material.emission;
+            color += direct_light(
+                scene,
+                origin,
+                |_| origin,
+                |_| scene.fog.as_ref(),
+                sampler,
+                |sample| {
+                    let cos = n.dot(sample.wi);
+                    if cos <= 0.0 {
+                        return Vector3::zero();
+                    }
+                    let half = (sample.wi - d).normalize();
+                    let specular =
+                        material.specular * n.dot(half).max(0.0).powf(material.shininess);
+                    sample.radiance.mul_element_wise(material.diffuse * cos + specular)
+                },
+                // nothing else finds the lights
+                |_| 0.0,
+            );
+
+            if material.specular != Vector3::zero() {
+                let reflected = Ray::new(origin, reflect(d, n), ray.t);
+                color += material.specular.mul_element_wise(self.trace(
+                    &reflected,
+                    scene,
+                    depth + 1,
+                    sampler,
+                ));
+            }
+
+            color
+        }
+    }
+
+    impl Integrator for Whitted {
+        fn radiance(&self, ray: &Ray, scene: &Scene, sampler: &mut dyn Sampler) -> Vector3<Float> {
+            self.trace(ray, scene, 0, sampler)
+        }
+    }
+
+    /// Fraction of the cosine weighted hemisphere above the first hit that is open for at
+    /// least `max_distance`. The same settings can darken the ambient term of Whitted instead.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct AmbientOcclusion {
+        pub samples: u32,
+        pub max_distance: Float,
+    }
+
+    impl AmbientOcclusion {
+        /// Share of `samples` cosine weighted rays from `origin` around `n` that escape
+        pub fn visibility(
+            &self,
+            scene: &Scene,
+            origin: Vector3<Float>,
+            n: Vector3<Float>,
+            sampler: &mut dyn Sampler,
+        ) -> Float {
+            let samples = self.samples.max(1);
+            let open = (0..samples)
+                .filter(|_| {
+                    let (u1, u2) = sampler.next_2d();
+                    let wi = to_world(cosine_sample_hemisphere(u1, u2), n);
+                    !TRACER.occluded(origin, wi, self.max_distance, scene)
+                })
+                .count();
+            open as Float / samples as Float
+        }
+    }
+
+    impl Integrator for AmbientOcclusion {
+        fn radiance(&self, ray: &Ray, scene: &Scene, sampler: &mut dyn Sampler) -> Vector3<Float> {
+            let TestHit::Hit(info) = TRACER.intersect(ray, scene) else {
+                return Vector3::zero();
+            };
+
+            let n = facing(info.normal(), ray.d);
+            let visibility = self.visibility(scene, info.point() + n * RAY_EPSILON, n, sampler);
+            Vector3::new(visibility, visibility, visibility)
+        }
+    }
+
+    /// Unbiased Monte Carlo path tracing. Surfaces gather the lights directly (next event
+    /// estimation) and continue in a direction sampled from their bsdf, smooth glass picks
+    /// reflection or refraction by its Fresnel reflectance. Emission of mesh lights, and the
+    /// environment when the path escapes, is found by both, so the two are weighted by the
+    /// power heuristic (multiple importance sampling); after smooth glass and from the camera
+    /// only the path sees it. In fog and inside
+    /// shapes holding a medium, free flight sampling picks where the path scatters, and the
+    /// lights and the next direction follow the phase function there. Paths end by russian
+    /// roulette, which keeps the estimate unbiased, or after `trace_depth` bounces off surfaces
+    /// and in media.
+    #[derive(Debug)]
+    pub struct PathTracing;
+
+    impl Integrator for PathTracing {
+        fn radiance(&self, ray: &Ray, scene: &Scene, sampler: &mut dyn Sampler) -> Vector3<Float> {
+            let mut radiance = Vector3::zero();
+            let mut throughput = Vector3::new(1.0, 1.0, 1.0);
+            let mut ray = *ray;
+            // where the ray started and the density it was scattered with, None for camera rays
+            // and smooth glass which can't sample lights
+            let mut scattered: Option<(Vector3<Float>, Float)> = None;
+            let mut medium = scene.fog.as_ref();
+            let mut bounce = 0;
+
+            while bounce as i32 <= scene.settings.trace_depth {
+                let hit = TRACER.intersect(&ray, scene);
+
+                if let Some(current) = medium {
+                    match current.sample(hit_distance(&ray, &hit), sampler.next_2d()) {
+                        MediumSample::Scatter { distance, weight } => {
+                            throughput = throughput.mul_element_wise(weight);
+                            let d = ray.d.normalize();
+                            let p = ray.o + d * distance;
+                            let direct = in_scattered(scene, p, d, current, sampler);
+                            radiance += throughput.mul_element_wise(direct);
+
+                            let phase = current.phase();
+                            let wi = phase.sample(d, sampler.next_2d());
+                            ray = Ray::new(p, wi, ray.t);
+                            scattered = Some((p, phase.eval(d.dot(wi))));
+                            if !survives_roulette(&mut throughput, bounce, sampler) {
+                                break;
+                            }
+                            bounce += 1;
+                            continue;
+                        },
+                        MediumSample::Pass { weight } => {
+                            throughput = throughput.mul_element_wise(weight);
+                        },
+                    }
+                }
+
+                let TestHit::Hit(info) = hit else {
+                    let weight = scattered.map_or(1.0, |(_, pdf)| {
+                        let light_pdf = scene.environment.pdf(ray.d) * light_samples(scene);
+                        power_heuristic(pdf, light_pdf)
+                    });
+                    let sky = scene.environment.radiance(ray.d) * weight;
+                    radiance += throughput.mul_element_wise(sky);
+                    break;
+                };
+                if scene.materials[info.material() as usize].is_medium_boundary() {
+                    // crossing into or out of a medium doesn't count as a bounce
+                    let material = &scene.materials[info.material() as usize];
+                    medium = medium_after(scene, &info, material, ray.d, medium);
+                    ray = Ray::new(info.point() + ray.d.normalize() * RAY_EPSILON, ray.d, ray.t);
+                    continue;
+                }
+                let (info, material) = surface(scene, info);
+                let material = &material;
+                if material.emission != Vector3::zero() {
+                    let weight = emission_weight(scene, &info, material, scattered);
+                    radiance += throughput.mul_element_wise(material.emission * weight);
+                }
+
+                let d = ray.d.normalize();
+                let p = info.point();
+
+                if material.is_smooth_dielectric() {
+                    let (n, eta, entering) = dielectric_side(d, info.normal(), material.ior);
+                    let reflectance = fresnel_dielectric(-d.dot(n), eta);
+
+                    ray = match refract(d, n, eta) {
+                        Some(refracted) if sampler.next_1d() >= reflectance => {
+                            if entering {
+                                throughput = throughput.mul_element_wise(material.transmission);
+                            }
+                            Ray::new(p - n * RAY_EPSILON, refracted, ray.t)
+                        },
+                        _ => Ray::new(p + n * RAY_EPSILON, reflect(d, n), ray.t),
+                    };
+                    scattered = None;
+                } else {
+                    // glass needs to know the outside, everything else is two sided
+                    let n = if material.is_dielectric() {
+                        info.normal()
+                    } else {
+                        facing(info.normal(), d)
+                    };
+                    let frame = Frame::from(n);
+                    let wo = frame.to_local(-d);
+                    // textures change the bsdf from point to point
+                    let textured;
+                    let bsdf = if material.is_textured() {
+                        textured = material.bsdf();
+                        &textured
+                    } else {
+                        &scene.bsdfs[info.material() as usize]
+                    };
+                    // start on the side the new direction leaves from
+                    let offset = |w: Vector3<Float>| p + n * (RAY_EPSILON.copysign(w.dot(n)));
+
+                    let behind =
+                        |w: Vector3<Float>| medium_after(scene, &info, material, w, medium);
+                    let shade = |sample: &LightSample| {
+                        let wi = frame.to_local(sample.wi);
+                        bsdf.eval(wo, wi).mul_element_wise(sample.radiance * wi.z.abs())
+                    };
+                    let pdf = |wi: Vector3<Float>| bsdf.pdf(wo, frame.to_local(wi));
+                    let direct = direct_light(scene, p, offset, behind, sampler, shade, pdf);
+                    radiance += throughput.mul_element_wise(direct);
+
+                    let Some(sample) = bsdf.sample(wo, sampler) else {
+                        break;
+                    };
+                    throughput = throughput.mul_element_wise(sample.weight());
+                    let wi = frame.to_world(sample.wi);
+                    ray = Ray::new(offset(wi), wi, ray.t);
+                    scattered = Some((p, sample.pdf));
+                }
+                medium = medium_after(scene, &info, material, ray.d, medium);
+
+                if !survives_roulette(&mut throughput, bounce, sampler) {
+                    break;
+                }
+                bounce += 1;
+            }
+
+            radiance
+        }
+    }
+
+    /// Whether a path goes on after `bounce`. Russian roulette ends it with a probability that
+    /// grows as its throughput drops, survivors get their throughput scaled up to compensate.
+    fn survives_roulette(
+        throughput: &mut Vector3<Float>,
+        bounce: u32,
+        sampler: &mut dyn Sampler,
+    ) -> bool {
+        if *throughput == Vector3::zero() {
+            return false;
+        }
+        if bounce >= ROULETTE_START_BOUNCE {
+            let survival = throughput.x.max(throughput.y).max(throughput.z).min(1.0);
+            if sampler.next_1d() >= survival {
+                return false;
+            }
+            *throughput /= survival;
+        }
+        true
+    }
+
+    /// Light scattered toward `-d` at `p` inside `medium`, the lights weighted by its phase
+    /// function instead of a bsdf
+    fn in_scattered(
+        scene: &Scene,
+        p: Vector3<Float>,
+        d: Vector3<Float>,
+        medium: &HomogeneousMedium,
+        sampler: &mut dyn Sampler,
+    ) -> Vector3<Float> {
+        let phase = medium.phase();
+        direct_light(
+            scene,
+            p,
+            |_| p,
+            |_| Some(medium),
+            sampler,
+            |sample| sample.radiance * phase.eval(d.dot(sample.wi)),
+            |wi| phase.eval(d.dot(wi)),
+        )
+    }
+
+    /// Medium a ray leaving the surface of `info` along `w` travels through: the inside of a
+    /// shape holding a medium when `w` goes against its outward normal, the fog when it goes
+    /// out, and `current` for shapes without one
+    fn medium_after<'a>(
+        scene: &'a Scene,
+        info: &HitInfo,
+        material: &Material,
+        w: Vector3<Float>,
+        current: Option<&'a HomogeneousMedium>,
+    ) -> Option<&'a HomogeneousMedium> {
+        match material.medium {
+            None => current,
+            Some(_) if w.dot(info.normal()) >= 0.0 => scene.fog.as_ref(),
+            Some(index) => Some(&scene.media[index as usize]),
+        }
+    }
+
+    /// Fraction of the light travelling `distance` from `origin` along unit `wi` that arrives:
+    /// none behind an opaque surface, what the media on the way let through otherwise. Medium
+    /// boundaries let the ray on into the medium behind them.
+    fn transmittance(
+        scene: &Scene,
+        origin: Vector3<Float>,
+        wi: Vector3<Float>,
+        distance: Float,
+        medium: Option<&HomogeneousMedium>,
+    ) -> Vector3<Float> {
+        let all = Vector3::new(1.0, 1.0, 1.0);
+        if scene.fog.is_none() && scene.media.is_empty() {
+            let blocked = TRACER.occluded(origin, wi, distance, scene);
+            return if blocked { Vector3::zero() } else { all };
+        }
+
+        let (mut origin, mut remaining, mut medium, mut result) = (origin, distance, medium, all);
+        while remaining > 0.0 {
+            let ray = Ray::new(origin, wi, remaining);
+            let hit = match TRACER.intersect(&ray, scene) {
+                TestHit::Hit(info) if (info.point() - origin).magnitude() < remaining => Some(info),
+                _ => None,
+            };
+            let segment = hit.map_or(remaining, |it| (it.point() - origin).magnitude());
+            if let Some(current) = medium {
+                result = result.mul_element_wise(current.transmittance(segment));
+            }
+
+            let Some(info) = hit else {
+                return result;
+            };
+            let material = &scene.materials[info.material() as usize];
+            if !material.is_medium_boundary() || result == Vector3::zero() {
+                return Vector3::zero();
+            }
+            medium = medium_after(scene, &info, material, wi, medium);
+            origin = info.point() + wi * RAY_EPSILON;
+            remaining -= segment + RAY_EPSILON;
+        }
+        result
+    }
+
+    /// How far along `ray` its hit is, infinitely far for a miss
+    fn hit_distance(ray: &Ray, hit: &TestHit) -> Float {
+        match hit {
+            TestHit::Hit(info) => (info.point() - ray.o).magnitude(),
+            TestHit::NoHit => Float::INFINITY,
+        }
+    }
+
+    /// Light reaching `p` from every light and the environment through unblocked shadow rays.
+    /// `shade` turns a light sample into reflected radiance, `origin` gives the shadow ray start
+    /// for a direction and `medium` the medium it starts in. Area lights and the environment
+    /// average `light_samples` samples, deltas take one. `scatter_pdf` is the density the path
+    /// goes on in a direction with: samples of lights the path can run into as well are weighted
+    /// against it.
+    fn direct_light<'a>(
+        scene: &'a Scene,
+        p: Vector3<Float>,
+        origin: impl Fn(Vector3<Float>) -> Vector3<Float>,
+        medium: impl Fn(Vector3<Float>) -> Option<&'a HomogeneousMedium>,
+        sampler: &mut dyn Sampler,
+        shade: impl Fn(&LightSample) -> Vector3<Float>,
+        scatter_pdf: impl Fn(Vector3<Float>) -> Float,
+    ) -> Vector3<Float> {
+        let area_samples = light_samples(scene);
+        let mut estimate =
+            |samples: Float, visible: bool, illuminate: &dyn Fn((Float, Float)) -> LightSample| {
+                let mut sum = Vector3::zero();
+                for _ in 0..samples as u32 {
+                    let sample = illuminate(sampler.next_2d());
+                    let reflected = shade(&sample);
+                    if reflected != Vector3::zero() {
+                        let start = origin(sample.wi);
+                        let weight = if visible {
+                            power_heuristic(sample.pdf * samples, scatter_pdf(sample.wi))
+                        } else {
+                            1.0
+                        };
+                        // aim from the offset start at the sampled point, so the shadow ray
+                        // still ends short of the light
+                        let (wi, distance) = if sample.distance.is_finite() {
+                            let to_light = p + sample.wi * sample.distance - start;
+                            (to_light.normalize(), to_light.magnitude())
+                        } else {
+                            (sample.wi, sample.distance)
+                        };
+                        let through = transmittance(scene, start, wi, distance, medium(wi));
+                        sum += reflected.mul_element_wise(through) * weight;
+                    }
+                }
+                sum / samples
+            };
+
+        let mut total = Vector3::zero();
+        for light in &scene.lights {
+            let samples = if light.is_delta() { 1.0 } else { area_samples };
+            total += estimate(samples, light.is_visible(), &|u| light.illuminate(p, u));
+        }
+        if !scene.environment.is_black() {
+            total += estimate(area_samples, true, &|u| scene.environment.illuminate(u));
+        }
+        total
+    }
+
+    /// Shadow rays each area light and the environment get
+    fn light_samples(scene: &Scene) -> Float {
+        scene.settings.light_samples.max(1) as Float
+    }
+
+    /// Share of an estimate drawn with density `pdf` when another strategy draws the same
+    /// direction with density `other`
+    fn power_heuristic(pdf: Float, other: Float) -> Float {
+        if other == 0.0 || pdf.is_infinite() {
+            return 1.0;
+        }
+        let (a, b) = (pdf * pdf, other * other);
+        if b.is_infinite() {
+            0.0
+        } else {
+            a / (a + b)
+        }
+    }
+
+    /// Share of the emission at `info` the path adds when it was scattered toward it as
+    /// `scattered` says. Mesh lights were sampled at the surface before, the power heuristic
+    /// splits their light between both strategies; other emitters are only found this way.
+    fn emission_weight(
+        scene: &Scene,
+        info: &HitInfo,
+        material: &Material,
+        scattered: Option<(Vector3<Float>, Float)>,
+    ) -> Float {
+        let (Some((from, pdf)), Some(triangle)) = (scattered, info.triangle()) else {
+            return 1.0;
+        };
+        if !material.is_area_light() {
+            return 1.0;
+        }
+        let light = scene.lights.iter().find_map(|it| match it {
+            Light::Mesh(light) if light.mesh == triangle.mesh => Some(light),
+            _ => None,
+        });
+        let Some(light) = light else {
+            return 1.0;
+        };
+        // light samples see the face, not the shading normal
+        let [a, b, c] = scene.meshes[triangle.mesh as usize].triangle_vertices(triangle.triangle);
+        let light_pdf = light.pdf(from, info.point(), (b - a).cross(c - a).normalize());
+        power_heuristic(pdf, light_pdf * light_samples(scene))
+    }
+
+    /// Material at the hit with its textures looked up, and the hit with the shading normal
+    /// its normal or bump map gives
+    fn surface(scene: &Scene, info: HitInfo) -> (HitInfo, Material) {
+        let material = &scene.materials[info.material() as usize];
+        if !material.is_textured() && !material.perturbs_normal() {
+            return (info, *material);
+        }
+        let point = SurfacePoint {
+            uv: info.uv(),
+            world: info.point(),
+            object: info.object_point(),
+            normal: info.normal(),
+            dpdu: info.dpdu(),
+            dpdv: info.dpdv(),
+        };
+        let info = if material.perturbs_normal() {
+            info.with_normal(material.shading_normal(&point, &scene.textures))
+        } else {
+            info
+        };
+        (info, material.at(&point, &scene.textures))
+    }
+
+    /// Reflects and refracts at a glass surface, blending both rays by Fresnel. `trace` follows
+    /// the new rays with the depth they are at, so glass looks the same to every integrator.
+    fn trace_dielectric(
+        ray: &Ray,
+        info: &HitInfo,
+        material: &Material,
+        depth: i32,
+        trace: &mut dyn FnMut(&Ray, i32) -> Vector3<Float>,
+    ) -> Vector3<Float> {
+        let d = ray.d.normalize();
+        let p = info.point();
+        let (n, eta, entering) = dielectric_side(d, info.normal(), material.ior);
+
+        let reflectance = fresnel_dielectric(-d.dot(n), eta);
+        let reflected = trace(&Ray::new(p + n * RAY_EPSILON, reflect(d, n), ray.t), depth + 1);
+
+        match refract(d, n, eta) {
+            // total internal reflection
+            None => reflected,
+            Some(refracted_d) => {
+                let refracted_ray = Ray::new(p - n * RAY_EPSILON, refracted_d, ray.t);
+                let mut refracted = trace(&refracted_ray, depth + 1);
+                // tint once, when the light crosses into the medium
+                if entering {
+                    refracted = refracted.mul_element_wise(material.transmission);
+                }
+                reflected * reflectance + refracted * (1.0 - reflectance)
+            },
+        }
+    }
+
+    /// Normal flipped to the side the ray `d` comes from
+    fn facing(n: Vector3<Float>, d: Vector3<Float>) -> Vector3<Float> {
+        if n.dot(d) > 0.0 {
+            -n
+        } else {
+            n
+        }
+    }
+
+    /// Normal facing the incoming ray and relative ior at a glass boundary, and whether the ray
+    /// enters the medium. Shape normals point outward.
+    fn dielectric_side(
+        d: Vector3<Float>,
+        n: Vector3<Float>,
+        ior: Float,
+    ) -> (Vector3<Float>, Float, bool) {
+        if d.dot(n) < 0.0 {
+            (n, 1.0 / ior, true)
+        } else {
+            (-n, ior, false)
+        }
+    }
+}
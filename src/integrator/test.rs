@@ -0,0 +1,267 @@
+#[cfg(test)]
+mod integrator_test {
+
+    use cgmath::{InnerSpace, Vector3};
+
+    use crate::camera::camera_view::Ray;
+    use crate::integrator::integrators::{
+        from_name, AmbientOcclusion, Depth, Integrator, Normals, PathTracing, Whitted,
+    };
+    use crate::light::lights::Light;
+    use crate::material::materials::fresnel_conductor;
+    use crate::sampling::samplers::Rng;
+    use crate::{float_consts::PI, read_scene, Float};
+    use approx::assert_relative_eq;
+
+    fn ray_to(from: Vector3<Float>, to: Vector3<Float>) -> Ray {
+        Ray::new(from, (to - from).normalize(), 10000.0)
+    }
+
+    fn head_on() -> Ray {
+        ray_to(Vector3::new(0.0, 0.0, 5.0), Vector3::new(0.0, 0.0, 0.0))
+    }
+
+    #[test]
+    fn should_build_integrators_by_name() {
+        assert!(from_name("flat", &[]).is_some());
+        assert!(from_name("normals", &[]).is_some());
+        assert!(from_name("whitted", &[]).is_some());
+        assert!(from_name("path", &[]).is_some());
+        assert!(from_name("mlt", &[]).is_none());
+
+        let ao = format!("{:?}", from_name("ao", &[4.0, 2.5]).unwrap());
+        assert_eq!(ao, "AmbientOcclusion { samples: 4, max_distance: 2.5 }");
+    }
+
+    #[test]
+    fn should_show_normals() {
+        let scene = read_scene("tests/fixtures/lit-sphere.test".to_string());
+
+        let radiance = Normals.radiance(&head_on(), &scene, &mut Rng::new(0, 0));
+
+        // +z maps to (0.5, 0.5, 1)
+        assert_relative_eq!(radiance.x, 0.5, epsilon = 1e-6);
+        assert_relative_eq!(radiance.z, 1.0, epsilon = 1e-6);
+    }
+
+    #[test]
+    fn should_show_depth() {
+        let scene = read_scene("tests/fixtures/lit-sphere.test".to_string());
+
+        let radiance = Depth { far: 8.0 }.radiance(&head_on(), &scene, &mut Rng::new(0, 0));
+
+        // hit 4 units away
+        assert_relative_eq!(radiance.x, 0.5, epsilon = 1e-6);
+    }
+
+    #[test]
+    fn should_shade_whitted_diffuse() {
+        // given a sphere lit head on from the camera
+        let scene = read_scene("tests/fixtures/lit-sphere.test".to_string());
+
+        // when tracing to its center
+        let radiance = Whitted.radiance(&head_on(), &scene, &mut Rng::new(0, 0));
+
+        // then ambient + diffuse * cos with cos = 1
+        assert_relative_eq!(radiance.x, 0.1 + 0.5, epsilon = 1e-6);
+    }
+
+    #[test]
+    fn should_shadow_whitted() {
+        // given a light hidden behind the upper sphere
+        let mut scene = read_scene("tests/fixtures/lit-sphere.test".to_string());
+        scene.lights = vec![Light::Point {
+            position: Vector3::new(0.0, 8.0, 0.0),
+            color: Vector3::new(1.0, 1.0, 1.0),
+            attenuation: Vector3::new(1.0, 0.0, 0.0),
+        }];
+        let ray = ray_to(Vector3::new(0.0, 0.0, 5.0), Vector3::new(0.0, 0.99, 0.0));
+
+        // when tracing to the top of the lower sphere
+        let radiance = Whitted.radiance(&ray, &scene, &mut Rng::new(0, 0));
+
+        // then only ambient is left
+        assert_relative_eq!(radiance.x, 0.1, epsilon = 1e-6);
+    }
+
+    #[test]
+    fn should_shade_whitted_with_spot_cone() {
+        // given a narrow spot on the camera, aimed at the lower sphere
+        let mut scene = read_scene("tests/fixtures/lit-sphere.test".to_string());
+        let spot = |direction| Light::Spot {
+            position: Vector3::new(0.0, 0.0, 5.0),
+            direction,
+            color: Vector3::new(1.0, 1.0, 1.0),
+            attenuation: Vector3::new(1.0, 0.0, 0.0),
+            cos_inner: 0.99,
+            cos_outer: 0.98,
+            exponent: 1.0,
+        };
+
+        // then its center is lit like by a point light
+        scene.lights = vec![spot(Vector3::new(0.0, 0.0, -1.0))];
+        let radiance = Whitted.radiance(&head_on(), &scene, &mut Rng::new(0, 0));
+        assert_relative_eq!(radiance.x, 0.1 + 0.5, epsilon = 1e-6);
+
+        // and dark once the spot looks at the upper one
+        scene.lights = vec![spot(Vector3::new(0.0, 4.0, -5.0).normalize())];
+        let radiance = Whitted.radiance(&head_on(), &scene, &mut Rng::new(0, 0));
+        assert_relative_eq!(radiance.x, 0.1, epsilon = 1e-6);
+    }
+
+    #[test]
+    fn should_occlude_ambient_under_sphere() {
+        // given the top of the lower sphere, the upper one 2 units above it
+        let scene = read_scene("tests/fixtures/lit-sphere.test".to_string());
+        let ray = ray_to(Vector3::new(0.0, 0.0, 5.0), Vector3::new(0.0, 0.99, 0.0));
+        let mut rng = Rng::new(0, 0);
+
+        // when some directions are blocked unless they are cut short
+        let near = AmbientOcclusion { samples: 256, max_distance: 0.5 };
+        let far = AmbientOcclusion { samples: 256, max_distance: Float::INFINITY };
+
+        // then only the unlimited one sees the blocker
+        assert_relative_eq!(near.radiance(&ray, &scene, &mut rng).x, 1.0);
+        let visibility = far.radiance(&ray, &scene, &mut rng).x;
+        assert!(visibility < 0.95 && visibility > 0.5, "visibility {}", visibility);
+    }
+
+    #[test]
+    fn should_darken_whitted_ambient_with_occlusion() {
+        // given the top of the lower sphere with only its ambient term left
+        let mut scene = read_scene("tests/fixtures/lit-sphere.test".to_string());
+        scene.lights.clear();
+        let ray = ray_to(Vector3::new(0.0, 0.0, 5.0), Vector3::new(0.0, 0.99, 0.0));
+        let mut rng = Rng::new(0, 0);
+        assert_relative_eq!(Whitted.radiance(&ray, &scene, &mut rng).x, 0.1);
+
+        // when occlusion scales it, then the upper sphere takes some away
+        scene.settings.ambient_occlusion =
+            Some(AmbientOcclusion { samples: 256, max_distance: Float::INFINITY });
+        let occluded = Whitted.radiance(&ray, &scene, &mut rng).x;
+        assert!(occluded < 0.095 && occluded > 0.05, "ambient {}", occluded);
+
+        // unless it is out of reach
+        scene.settings.ambient_occlusion =
+            Some(AmbientOcclusion { samples: 256, max_distance: 0.5 });
+        assert_relative_eq!(Whitted.radiance(&ray, &scene, &mut rng).x, 0.1);
+    }
+
+    #[test]
+    fn should_gather_point_light_in_path_tracer() {
+        // given the lit sphere, nothing else to bounce off
+        let scene = read_scene("tests/fixtures/lit-sphere.test".to_string());
+
+        // when path tracing to its center
+        let radiance = PathTracing.radiance(&head_on(), &scene, &mut Rng::new(0, 0));
+
+        // then only next event estimation contributes, albedo / pi * cos
+        assert_relative_eq!(radiance.x, 0.5 / PI, epsilon = 1e-6);
+    }
+
+    #[test]
+    fn should_converge_in_white_furnace() {
+        // given a camera inside a glowing sphere with albedo 1/2
+        let scene = read_scene("tests/fixtures/furnace.test".to_string());
+        let ray = ray_to(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+
+        // when averaging many paths
+        let mut rng = Rng::new(3, 0);
+        let samples = 20000;
+        let sum: Float = (0..samples)
+            .map(|_| scene.settings.integrator.radiance(&ray, &scene, &mut rng).x)
+            .sum();
+
+        // then russian roulette doesn't bias the infinite bounce series 1 + 1/2 + 1/4 + ...
+        assert_relative_eq!(sum / samples as Float, 2.0, epsilon = 0.02);
+    }
+
+    #[test]
+    fn should_stop_paths_at_max_depth() {
+        // given the glowing sphere, where every bounce adds half the light of the one before
+        let mut scene = read_scene("tests/fixtures/furnace.test".to_string());
+        let ray = ray_to(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+
+        // then paths see the emission of the first maxdepth + 1 hits, before roulette starts
+        for (depth, expected) in [(0, 1.0), (1, 1.5), (2, 1.75)] {
+            scene.settings.trace_depth = depth;
+            let radiance = PathTracing.radiance(&ray, &scene, &mut Rng::new(0, 0));
+            assert_relative_eq!(radiance.x, expected, epsilon = 1e-6);
+        }
+    }
+
+    #[test]
+    fn should_weigh_light_and_bsdf_samples_of_mesh_light() {
+        // given a floor under an emissive panel, seen straight down
+        let mut scene = read_scene("tests/fixtures/mesh-light.test".to_string());
+        let ray = ray_to(Vector3::new(0.0, 0.5, 0.0), Vector3::new(0.01, 0.0, 0.02));
+
+        for light_samples in [1, 8] {
+            // when the panel is found by shadow rays and by bounces alike
+            scene.settings.light_samples = light_samples;
+            let mut rng = Rng::new(5, 0);
+            let samples = 20000;
+            let sum: Float =
+                (0..samples).map(|_| PathTracing.radiance(&ray, &scene, &mut rng).x).sum();
+
+            // then their weights add up to the form factor of the panel times the albedo
+            let corner = 2.0 * (0.5 as Float).sqrt() * (0.5 as Float).sqrt().atan() / (2.0 * PI);
+            assert_relative_eq!(sum / samples as Float, 0.5 * 4.0 * corner, epsilon = 0.01);
+        }
+    }
+
+    #[test]
+    fn should_see_sky_when_missing() {
+        // given a ray passing above the sphere
+        let scene = read_scene("tests/fixtures/furnace-sky.test".to_string());
+        let ray = ray_to(Vector3::new(0.0, 0.0, 5.0), Vector3::new(0.0, 3.0, 0.0));
+        let mut rng = Rng::new(0, 0);
+
+        // then every shading integrator returns the sky
+        for integrator in
+            [from_name("flat", &[]), from_name("whitted", &[]), from_name("path", &[])]
+        {
+            let radiance = integrator.unwrap().radiance(&ray, &scene, &mut rng);
+            assert_eq!(radiance, Vector3::new(1.0, 1.0, 1.0));
+        }
+    }
+
+    #[test]
+    fn should_light_diffuse_sphere_with_sky() {
+        // given a convex diffuse sphere with albedo 1/2 under a white sky
+        let scene = read_scene("tests/fixtures/furnace-sky.test".to_string());
+        let mut rng = Rng::new(9, 0);
+
+        // when averaging many paths
+        let samples = 20000;
+        let sum: Float =
+            (0..samples).map(|_| PathTracing.radiance(&head_on(), &scene, &mut rng).x).sum();
+
+        // then it reflects half the sky, sampled as a light
+        assert_relative_eq!(sum / samples as Float, 0.5, epsilon = 0.02);
+    }
+
+    #[test]
+    fn should_reflect_sky_off_glossy_conductor() {
+        // given a convex gold sphere of roughness 0.05 under a white sky
+        let scene = read_scene("tests/fixtures/conductor-sky.test".to_string());
+        let mut rng = Rng::new(3, 0);
+
+        // when averaging many paths hitting it head on
+        let samples = 2000;
+        let sum = (0..samples)
+            .map(|_| PathTracing.radiance(&head_on(), &scene, &mut rng))
+            .fold(Vector3::new(0.0, 0.0, 0.0), |sum, it| sum + it);
+
+        // then it reflects the sky scaled by the Fresnel reflectance at normal incidence
+        let expected = Vector3::new(
+            fresnel_conductor(1.0, 0.18, 3.42),
+            fresnel_conductor(1.0, 0.42, 2.35),
+            fresnel_conductor(1.0, 1.37, 1.77),
+        );
+        let average = sum / samples as Float;
+        for channel in 0..3 {
+            assert_relative_eq!(average[channel], expected[channel], epsilon = 0.02);
+        }
+    }
+}
@@ -0,0 +1,315 @@
+#[cfg(test)]
+mod test;
+
+pub mod lights {
+    use cgmath::{InnerSpace, Vector3};
+
+    use crate::sampling::samplers::{Distribution1D, Frame};
+    use crate::{float_consts::PI, Float};
+
+    // area light shadow rays stop this fraction short of the light, so they can't hit the
+    // emitting triangle itself
+    const SHADOW_SHORTENING: Float = 1e-4;
+
+    /// Light sources. Points, spots and directional lights are deltas. Quad, disk and sphere lights
+    /// only exist for shadow rays, camera rays pass through them. Mesh lights are the
+    /// emissive triangles of the scene meshes.
+    #[derive(Debug, Clone)]
+    pub enum Light {
+        /// `attenuation` holds the constant, linear and quadratic terms of the falloff
+        Point {
+            position: Vector3<Float>,
+            color: Vector3<Float>,
+            attenuation: Vector3<Float>,
+        },
+        /// Point light restricted to a cone around `direction`, which points away from the
+        /// light. Full intensity inside the inner angle, fading to nothing at the outer one.
+        Spot {
+            position: Vector3<Float>,
+            direction: Vector3<Float>,
+            color: Vector3<Float>,
+            attenuation: Vector3<Float>,
+            cos_inner: Float,
+            cos_outer: Float,
+            /// Shapes the fade between both cones, 1 is linear in the cosine
+            exponent: Float,
+        },
+        /// `direction` points toward the light
+        Directional {
+            direction: Vector3<Float>,
+            color: Vector3<Float>,
+        },
+        /// Parallelogram emitting `radiance` on the side of edge_u x edge_v
+        Quad {
+            corner: Vector3<Float>,
+            edge_u: Vector3<Float>,
+            edge_v: Vector3<Float>,
+            radiance: Vector3<Float>,
+        },
+        /// Disk emitting `radiance` on the side of its `normal`
+        Disk {
+            center: Vector3<Float>,
+            normal: Vector3<Float>,
+            radius: Float,
+            radiance: Vector3<Float>,
+        },
+        Sphere {
+            center: Vector3<Float>,
+            radius: Float,
+            radiance: Vector3<Float>,
+        },
+        Mesh(MeshLight),
+    }
+
+    /// Emissive triangles of one mesh, sampled as one light: a triangle picked by its area,
+    /// then a uniform point on it. Emits on both sides, mesh winding can't be trusted.
+    #[derive(Debug, Clone)]
+    pub struct MeshLight {
+        /// Index of the mesh in the scene
+        pub mesh: u32,
+        /// Vertices and emitted radiance of each triangle
+        triangles: Vec<([Vector3<Float>; 3], Vector3<Float>)>,
+        areas: Distribution1D,
+    }
+
+    impl MeshLight {
+        pub fn new(mesh: u32, triangles: Vec<([Vector3<Float>; 3], Vector3<Float>)>) -> MeshLight {
+            let areas: Vec<Float> = triangles
+                .iter()
+                .map(|([a, b, c], _)| (b - a).cross(c - a).magnitude() / 2.0)
+                .collect();
+            MeshLight { mesh, triangles, areas: Distribution1D::new(&areas) }
+        }
+
+        /// Solid angle density of sampling from `p` the point `y` with normal `normal` on one of
+        /// the triangles
+        pub fn pdf(&self, p: Vector3<Float>, y: Vector3<Float>, normal: Vector3<Float>) -> Float {
+            let to_light = y - p;
+            let distance2 = to_light.magnitude2();
+            if self.areas.total() == 0.0 || distance2 == 0.0 {
+                return 0.0;
+            }
+            let cos_light = to_light.dot(normal).abs() / distance2.sqrt();
+            if cos_light == 0.0 {
+                return 0.0;
+            }
+            distance2 / (cos_light * self.areas.total())
+        }
+
+        fn illuminate(&self, p: Vector3<Float>, u1: Float, u2: Float) -> LightSample {
+            if self.areas.total() == 0.0 {
+                return LightSample::none();
+            }
+            let (x, index, _) = self.areas.sample(u1);
+            // where u1 fell inside the bin of the triangle, uniform again
+            let u1 = x * self.areas.len() as Float - index as Float;
+            let ([a, b, c], radiance) = self.triangles[index];
+
+            // uniform barycentrics
+            let su = u1.sqrt();
+            let y = a * (1.0 - su) + b * (u2 * su) + c * (su * (1.0 - u2));
+            let normal = (b - a).cross(c - a).normalize();
+            // area pdf of the triangle pick times the uniform point on it
+            let pdf_area = 1.0 / self.areas.total();
+            LightSample::from_area(p, y, normal, true, radiance, pdf_area)
+        }
+    }
+
+    /// What a light sends to a point: unit direction toward the light, distance to it and the
+    /// arriving radiance. For area lights `radiance` is already divided by the sampling pdf,
+    /// so shading is `f * radiance * cos` for every kind of light.
+    #[derive(Debug, Clone, Copy)]
+    pub struct LightSample {
+        pub wi: Vector3<Float>,
+        pub distance: Float,
+        pub radiance: Vector3<Float>,
+        /// Solid angle density `wi` was picked with, infinite for delta lights
+        pub pdf: Float,
+    }
+
+    impl LightSample {
+        fn none() -> LightSample {
+            LightSample {
+                wi: Vector3::unit_z(),
+                distance: 0.0,
+                radiance: Vector3::new(0.0, 0.0, 0.0),
+                pdf: 0.0,
+            }
+        }
+
+        /// Sample of the point `y` on an area light with area density `pdf_area`, emitting
+        /// along `normal` (both sides if `two_sided`)
+        fn from_area(
+            p: Vector3<Float>,
+            y: Vector3<Float>,
+            normal: Vector3<Float>,
+            two_sided: bool,
+            radiance: Vector3<Float>,
+            pdf_area: Float,
+        ) -> LightSample {
+            let to_light = y - p;
+            let distance2 = to_light.magnitude2();
+            if distance2 == 0.0 {
+                return LightSample::none();
+            }
+            let distance = distance2.sqrt();
+            let wi = to_light / distance;
+
+            let cos_light = -wi.dot(normal);
+            let cos_light = if two_sided { cos_light.abs() } else { cos_light };
+            if cos_light <= 0.0 {
+                return LightSample::none();
+            }
+
+            let pdf = pdf_area * distance2 / cos_light;
+            LightSample {
+                wi,
+                distance: distance * (1.0 - SHADOW_SHORTENING),
+                radiance: radiance / pdf,
+                pdf,
+            }
+        }
+    }
+
+    impl Light {
+        /// Delta lights need one shadow ray, area lights get several for soft shadows
+        pub fn is_delta(&self) -> bool {
+            matches!(self, Light::Point { .. } | Light::Spot { .. } | Light::Directional { .. })
+        }
+
+        /// Area lights without area, their samples would divide by zero
+        pub fn is_degenerate(&self) -> bool {
+            let size = match *self {
+                Light::Quad { edge_u, edge_v, .. } => edge_u.cross(edge_v).magnitude2(),
+                // a zero normal comes out of normalizing as NaN
+                Light::Disk { normal, radius, .. } => radius * normal.magnitude2(),
+                Light::Sphere { radius, .. } => radius,
+                _ => return false,
+            };
+            size.is_nan() || size <= 0.0
+        }
+
+        /// Whether rays can hit the light, so bsdf sampling finds it too
+        pub fn is_visible(&self) -> bool {
+            matches!(self, Light::Mesh(_))
+        }
+
+        /// Samples the light as seen from `p`, `u` are two uniform numbers deltas ignore
+        pub fn illuminate(&self, p: Vector3<Float>, (u1, u2): (Float, Float)) -> LightSample {
+            match *self {
+                Light::Point { position, color, attenuation } => {
+                    sample_point(p, position, color, attenuation)
+                },
+                Light::Spot {
+                    position,
+                    direction,
+                    color,
+                    attenuation,
+                    cos_inner,
+                    cos_outer,
+                    exponent,
+                } => {
+                    let sample = sample_point(p, position, color, attenuation);
+                    let cos = -sample.wi.dot(direction);
+                    let cone = if cos >= cos_inner {
+                        1.0
+                    } else if cos <= cos_outer {
+                        0.0
+                    } else {
+                        ((cos - cos_outer) / (cos_inner - cos_outer)).powf(exponent)
+                    };
+                    LightSample { radiance: sample.radiance * cone, ..sample }
+                },
+                Light::Directional { direction, color } => LightSample {
+                    wi: direction.normalize(),
+                    distance: Float::INFINITY,
+                    radiance: color,
+                    pdf: Float::INFINITY,
+                },
+                Light::Quad { corner, edge_u, edge_v, radiance } => {
+                    let cross = edge_u.cross(edge_v);
+                    let pdf_area = 1.0 / cross.magnitude();
+                    let y = corner + edge_u * u1 + edge_v * u2;
+                    LightSample::from_area(p, y, cross * pdf_area, false, radiance, pdf_area)
+                },
+                Light::Disk { center, normal, radius, radiance } => {
+                    let r = radius * u1.sqrt();
+                    let phi = 2.0 * PI * u2;
+                    let y = center
+                        + Frame::from(normal).to_world(Vector3::new(
+                            r * phi.cos(),
+                            r * phi.sin(),
+                            0.0,
+                        ));
+                    let pdf_area = 1.0 / (PI * radius * radius);
+                    LightSample::from_area(p, y, normal, false, radiance, pdf_area)
+                },
+                Light::Sphere { center, radius, radiance } => {
+                    sample_sphere(p, center, radius, radiance, u1, u2)
+                },
+                Light::Mesh(ref mesh) => mesh.illuminate(p, u1, u2),
+            }
+        }
+    }
+
+    fn sample_point(
+        p: Vector3<Float>,
+        position: Vector3<Float>,
+        color: Vector3<Float>,
+        attenuation: Vector3<Float>,
+    ) -> LightSample {
+        let to_light = position - p;
+        let distance = to_light.magnitude();
+        let falloff =
+            attenuation.x + attenuation.y * distance + attenuation.z * distance * distance;
+        LightSample {
+            wi: to_light / distance,
+            distance,
+            radiance: color / falloff,
+            pdf: Float::INFINITY,
+        }
+    }
+
+    /// Uniform directions in the cone the sphere covers seen from `p`, far fewer wasted
+    /// samples than picking points on the whole surface
+    fn sample_sphere(
+        p: Vector3<Float>,
+        center: Vector3<Float>,
+        radius: Float,
+        radiance: Vector3<Float>,
+        u1: Float,
+        u2: Float,
+    ) -> LightSample {
+        let to_center = center - p;
+        let distance2 = to_center.magnitude2();
+        if distance2 <= radius * radius {
+            // inside the light
+            return LightSample::none();
+        }
+        let distance = distance2.sqrt();
+        let axis = to_center / distance;
+
+        let sin2_max = radius * radius / distance2;
+        let cos_max = (1.0 - sin2_max).max(0.0).sqrt();
+        let cos_theta = 1.0 - u1 * (1.0 - cos_max);
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * u2;
+        let wi = Frame::from(axis).to_world(Vector3::new(
+            sin_theta * phi.cos(),
+            sin_theta * phi.sin(),
+            cos_theta,
+        ));
+
+        // nearest intersection with the sphere along wi
+        let along = distance * cos_theta;
+        let hit = along - (radius * radius - distance2 * sin_theta * sin_theta).max(0.0).sqrt();
+
+        let solid_angle = 2.0 * PI * (1.0 - cos_max);
+        LightSample {
+            wi,
+            distance: hit * (1.0 - SHADOW_SHORTENING),
+            radiance: radiance * solid_angle,
+            pdf: 1.0 / solid_angle,
+        }
+    }
+}
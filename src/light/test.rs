@@ -0,0 +1,294 @@
+#[cfg(test)]
+mod light_test {
+
+    use cgmath::{InnerSpace, Vector3, Zero};
+
+    use crate::light::lights::{Light, MeshLight};
+    use crate::sampling::samplers::{Rng, Sampler};
+    use crate::{float_consts::PI, read_scene, Float};
+    use approx::assert_relative_eq;
+
+    // the cone fade amplifies rounding of the cosine, a lot under f32
+    const TOLERANCE: Float = if cfg!(feature = "f32") { 1e-3 } else { 1e-6 };
+
+    /// Monte Carlo irradiance at `p` under the normal `n`
+    fn irradiance(light: &Light, p: Vector3<Float>, n: Vector3<Float>, samples: u32) -> Float {
+        let mut rng = Rng::new(7, 0);
+        let mut sum = Vector3::<f64>::zero();
+        for _ in 0..samples {
+            let sample = light.illuminate(p, rng.next_2d());
+            sum += (sample.radiance * sample.wi.dot(n).max(0.0)).cast::<f64>().unwrap();
+        }
+        (sum / samples as f64).cast::<Float>().unwrap().x
+    }
+
+    #[test]
+    fn should_attenuate_point_light() {
+        // given a point light 2 units above with quadratic falloff
+        let light = Light::Point {
+            position: Vector3::new(0.0, 2.0, 0.0),
+            color: Vector3::new(1.0, 1.0, 1.0),
+            attenuation: Vector3::new(1.0, 0.0, 1.0),
+        };
+
+        // when illuminating the origin
+        let sample = light.illuminate(Vector3::new(0.0, 0.0, 0.0), (0.5, 0.5));
+
+        // then it arrives from above divided by 1 + d^2
+        assert_relative_eq!(sample.wi.y, 1.0);
+        assert_relative_eq!(sample.distance, 2.0);
+        assert_relative_eq!(sample.radiance.x, 1.0 / 5.0);
+    }
+
+    #[test]
+    fn should_not_attenuate_directional_light() {
+        let light = Light::Directional {
+            direction: Vector3::new(0.0, 3.0, 4.0),
+            color: Vector3::new(0.5, 0.5, 0.5),
+        };
+
+        let sample = light.illuminate(Vector3::new(10.0, -4.0, 2.0), (0.5, 0.5));
+
+        assert_relative_eq!(sample.wi.magnitude(), 1.0);
+        assert_relative_eq!(sample.wi.z, 0.8);
+        assert!(sample.distance.is_infinite());
+        assert_relative_eq!(sample.radiance.x, 0.5);
+    }
+
+    #[test]
+    fn should_match_disk_irradiance_on_axis() {
+        // given a unit radiance disk of radius 1 facing down from 2 units above
+        let light = Light::Disk {
+            center: Vector3::new(0.0, 2.0, 0.0),
+            normal: Vector3::new(0.0, -1.0, 0.0),
+            radius: 1.0,
+            radiance: Vector3::new(1.0, 1.0, 1.0),
+        };
+
+        // when integrating the irradiance under it
+        let e = irradiance(&light, Vector3::new(0.0, 0.0, 0.0), Vector3::unit_y(), 20000);
+
+        // then it matches pi L R^2 / (h^2 + R^2)
+        assert_relative_eq!(e, PI / 5.0, epsilon = 1e-2);
+    }
+
+    #[test]
+    fn should_only_light_the_front_of_a_disk() {
+        let light = Light::Disk {
+            center: Vector3::new(0.0, 2.0, 0.0),
+            normal: Vector3::new(0.0, 1.0, 0.0),
+            radius: 1.0,
+            radiance: Vector3::new(1.0, 1.0, 1.0),
+        };
+
+        let sample = light.illuminate(Vector3::new(0.0, 0.0, 0.0), (0.3, 0.6));
+
+        assert_relative_eq!(sample.radiance.x, 0.0);
+    }
+
+    #[test]
+    fn should_match_sphere_irradiance() {
+        // given a unit radiance sphere of radius 1, 3 units above
+        let light = Light::Sphere {
+            center: Vector3::new(0.0, 3.0, 0.0),
+            radius: 1.0,
+            radiance: Vector3::new(1.0, 1.0, 1.0),
+        };
+
+        // when integrating the irradiance under it
+        let e = irradiance(&light, Vector3::new(0.0, 0.0, 0.0), Vector3::unit_y(), 20000);
+
+        // then it is the same as a point light of power pi L R^2, pi L (R / d)^2
+        assert_relative_eq!(e, PI / 9.0, epsilon = 1e-2);
+
+        // and shadow rays stop at the near surface
+        let sample = light.illuminate(Vector3::new(0.0, 0.0, 0.0), (0.0, 0.0));
+        assert_relative_eq!(sample.distance, 2.0, epsilon = 1e-3);
+    }
+
+    #[test]
+    fn should_light_like_quad_when_split_in_triangles() {
+        // given a 2x2 quad 1 unit above, and the same quad as two triangles
+        let (a, b, c, d) = (
+            Vector3::new(-1.0, 1.0, -1.0),
+            Vector3::new(1.0, 1.0, -1.0),
+            Vector3::new(1.0, 1.0, 1.0),
+            Vector3::new(-1.0, 1.0, 1.0),
+        );
+        let radiance = Vector3::new(1.0, 1.0, 1.0);
+        let quad = Light::Quad { corner: a, edge_u: b - a, edge_v: d - a, radiance };
+        let triangles =
+            Light::Mesh(MeshLight::new(0, vec![([a, b, c], radiance), ([a, c, d], radiance)]));
+
+        // when integrating the irradiance at a point off center
+        let p = Vector3::new(0.3, 0.0, -0.2);
+        let n = Vector3::unit_y();
+        let e_quad = irradiance(&quad, p, n, 20000);
+        let e_triangles = irradiance(&triangles, p, n, 20000);
+
+        // then both agree
+        assert_relative_eq!(e_quad, e_triangles, epsilon = 2e-2);
+        assert!(e_quad > 0.5);
+    }
+
+    #[test]
+    fn should_light_like_its_triangles_when_picking_by_area() {
+        // given a mesh of a small dim and a large bright triangle above, and each on its own
+        let small = (
+            [
+                Vector3::new(-1.0, 1.0, -1.0),
+                Vector3::new(0.0, 1.0, -1.0),
+                Vector3::new(-1.0, 1.0, 0.0),
+            ],
+            Vector3::new(1.0, 1.0, 1.0),
+        );
+        let large = (
+            [Vector3::new(0.0, 1.0, 0.0), Vector3::new(3.0, 1.0, 0.0), Vector3::new(0.0, 1.0, 3.0)],
+            Vector3::new(4.0, 4.0, 4.0),
+        );
+        let mesh = Light::Mesh(MeshLight::new(0, vec![small, large]));
+
+        // when integrating the irradiance below them
+        let p = Vector3::new(0.2, 0.0, 0.1);
+        let n = Vector3::unit_y();
+        let e_mesh = irradiance(&mesh, p, n, 40000);
+        let e_small = irradiance(&Light::Mesh(MeshLight::new(0, vec![small])), p, n, 40000);
+        let e_large = irradiance(&Light::Mesh(MeshLight::new(0, vec![large])), p, n, 40000);
+
+        // then the area pdf of the pick makes up for the choice
+        assert_relative_eq!(e_mesh, e_small + e_large, max_relative = 2e-2);
+    }
+
+    #[test]
+    fn should_read_area_lights() {
+        // given a scene with an emissive quad made of triangles and the explicit area lights
+        let scene = read_scene("tests/fixtures/area-lights.test".to_string());
+
+        // then the light samples setting is read and the emissive triangles become one light
+        assert_eq!(scene.settings.light_samples, 8);
+        let lights: Vec<String> = scene.lights.iter().map(|it| format!("{:?}", it)).collect();
+        assert_eq!(lights.len(), 4);
+        assert!(lights[0].starts_with("Quad"));
+        assert!(lights[1].starts_with("Disk"));
+        assert!(lights[2].starts_with("Sphere"));
+        assert!(lights[3].starts_with("Mesh"));
+
+        // and the quad is moved by the transform in effect
+        match scene.lights[0] {
+            Light::Quad { corner, .. } => assert_relative_eq!(corner.y, 5.0),
+            _ => unreachable!(),
+        }
+    }
+
+    fn spot(exponent: Float) -> Light {
+        // 2 units above the origin looking down, 10 to 20 degree cone
+        Light::Spot {
+            position: Vector3::new(0.0, 2.0, 0.0),
+            direction: Vector3::new(0.0, -1.0, 0.0),
+            color: Vector3::new(1.0, 1.0, 1.0),
+            attenuation: Vector3::new(1.0, 0.0, 0.0),
+            cos_inner: (10.0 as Float).to_radians().cos(),
+            cos_outer: (20.0 as Float).to_radians().cos(),
+            exponent,
+        }
+    }
+
+    /// Point on the floor seen from the spot at `degrees` off its axis
+    fn off_axis(degrees: Float) -> Vector3<Float> {
+        Vector3::new(2.0 * degrees.to_radians().tan(), 0.0, 0.0)
+    }
+
+    #[test]
+    fn should_flag_area_lights_without_area() {
+        let white = Vector3::new(1.0, 1.0, 1.0);
+        let quad = |edge_v| Light::Quad {
+            corner: Vector3::zero(),
+            edge_u: Vector3::unit_x(),
+            edge_v,
+            radiance: white,
+        };
+        let disk = |normal, radius| Light::Disk {
+            center: Vector3::zero(),
+            normal,
+            radius,
+            radiance: white,
+        };
+        let sphere = |radius| Light::Sphere { center: Vector3::zero(), radius, radiance: white };
+
+        // lights with area are fine
+        assert!(!quad(Vector3::unit_z()).is_degenerate());
+        assert!(!disk(Vector3::unit_y(), 1.0).is_degenerate());
+        assert!(!sphere(1.0).is_degenerate());
+
+        // parallel edges, a normal that didn't normalize and zero radii have none
+        assert!(quad(Vector3::unit_x() * 2.0).is_degenerate());
+        assert!(disk(Vector3::zero().normalize(), 1.0).is_degenerate());
+        assert!(disk(Vector3::unit_y(), 0.0).is_degenerate());
+        assert!(sphere(0.0).is_degenerate());
+    }
+
+    #[test]
+    fn should_light_like_point_inside_spot_cone() {
+        let sample = spot(1.0).illuminate(off_axis(5.0), (0.5, 0.5));
+
+        assert_relative_eq!(sample.wi.y, (5.0 as Float).to_radians().cos(), epsilon = 1e-6);
+        assert_relative_eq!(sample.radiance.x, 1.0);
+    }
+
+    #[test]
+    fn should_not_light_outside_spot_cone() {
+        let sample = spot(1.0).illuminate(off_axis(25.0), (0.5, 0.5));
+
+        assert_relative_eq!(sample.radiance.x, 0.0);
+
+        // and nothing behind the spot either
+        let sample = spot(1.0).illuminate(Vector3::new(0.0, 4.0, 0.0), (0.5, 0.5));
+        assert_relative_eq!(sample.radiance.x, 0.0);
+    }
+
+    #[test]
+    fn should_fade_between_spot_cones() {
+        // given a point half way between both cones, in cosine
+        let (inner, outer) = ((10.0 as Float).to_radians(), (20.0 as Float).to_radians());
+        let cos = (inner.cos() + outer.cos()) / 2.0;
+        let p = Vector3::new(2.0 * cos.acos().tan(), 0.0, 0.0);
+
+        // then the fade is linear with exponent 1 and sharper with higher ones
+        assert_relative_eq!(
+            spot(1.0).illuminate(p, (0.5, 0.5)).radiance.x,
+            0.5,
+            epsilon = TOLERANCE
+        );
+        assert_relative_eq!(
+            spot(3.0).illuminate(p, (0.5, 0.5)).radiance.x,
+            0.125,
+            epsilon = TOLERANCE
+        );
+    }
+
+    #[test]
+    fn should_read_spot_light() {
+        let scene = read_scene("tests/fixtures/spot.test".to_string());
+
+        match scene.lights[0] {
+            Light::Spot {
+                position,
+                direction,
+                attenuation,
+                cos_inner,
+                cos_outer,
+                exponent,
+                ..
+            } => {
+                assert_relative_eq!(position.y, 4.0);
+                assert_relative_eq!(direction.magnitude(), 1.0);
+                assert_relative_eq!(direction.y, -1.0);
+                assert_relative_eq!(attenuation.z, 0.5);
+                assert_relative_eq!(cos_inner, (15.0 as Float).to_radians().cos());
+                assert_relative_eq!(cos_outer, (30.0 as Float).to_radians().cos());
+                assert_relative_eq!(exponent, 2.0);
+            },
+            _ => unreachable!(),
+        }
+    }
+}
@@ -1,43 +1,97 @@
+#[cfg(test)]
+mod test;
+
+pub mod bsdf;
 pub mod camera;
+pub mod checkpoint;
+pub mod distributed;
+pub mod environment;
+pub mod filter;
+pub mod integrator;
+pub mod light;
+pub mod material;
+pub mod medium;
+pub mod mesh;
+pub mod mesh_io;
+pub mod progress;
 mod ray_tracer;
+pub mod sampling;
+pub mod sdf;
 pub mod shapes;
+pub mod sky;
+pub mod texture;
+pub mod tile;
 
 use crate::{
+    bsdf::bsdfs::{Bsdf, BsdfModel},
     camera::camera_view::Camera,
-    shapes::shape_components::{Sphere, Triangle},
+    checkpoint::checkpoints,
+    distributed::workers::{self, Connection},
+    environment::environments::{Environment, EnvironmentMap},
+    filter::filters::Filter,
+    light::lights::{Light, MeshLight},
+    material::materials::Material,
+    medium::media::HomogeneousMedium,
+    mesh::triangle_mesh::{TriangleMesh, TriangleRef},
+    mesh_io::mesh_loaders,
+    sampling::samplers::{AdaptiveSampling, SamplerKind},
+    sdf::sdf_shapes::{Sdf, SdfNode},
+    shapes::shape_components::Sphere,
+    sky::daylight::{sun_direction, PreethamSky},
+    texture::textures::{ImageTexture, Pattern, Texture, TextureSpace, WrapMode},
+    tile::tiles::TileOrder,
 };
-use cgmath::{Matrix4, One, Rad, SquareMatrix, Vector3, Vector4};
-use log::{debug, error, info, warn};
-use ray_tracer::tracer::RayTracer;
+use cgmath::{InnerSpace, Matrix4, One, Rad, Vector2, Vector3, Vector4, Zero};
+use float_consts::PI;
+use integrator::integrators::{self, AmbientOcclusion, FlatColor, Integrator};
+use log::{error, info, trace, warn, LevelFilter};
+use log4rs::append::console::{ConsoleAppender, Target};
+use log4rs::config::{Appender, Config, Root};
+use ray_tracer::tracer::{Image, Progressive, RayTracer, RenderState};
 
 use std::time::Instant;
 use std::{
     env,
-    f64::consts::PI,
-    fs::File,
+    fs::{self, File},
     io::{self, BufRead},
+    net::TcpListener,
+    path::{Path, PathBuf},
 };
 
-trait ConvertToVector4<T> {
-    fn to_vector4(&self) -> Vector4<T>;
-}
-
-impl<T> ConvertToVector4<T> for Vector3<T>
-where
-    T: From<f32> + Copy,
-{
-    fn to_vector4(&self) -> Vector4<T> {
-        Vector4::new(self.x, self.y, self.z, T::from(1.0))
-    }
-}
+/// Scalar used by the geometry and the tracer. Building with `--features f32` halves the memory
+/// traffic of large meshes, f64 stays the default for precision sensitive scenes.
+#[cfg(not(feature = "f32"))]
+pub type Float = f64;
+#[cfg(feature = "f32")]
+pub type Float = f32;
+#[cfg(feature = "f32")]
+pub use std::f32::consts as float_consts;
+#[cfg(not(feature = "f32"))]
+pub use std::f64::consts as float_consts;
 
 #[derive(Debug)]
 pub struct Scene {
     cams: Vec<Camera>,
     spheres: Vec<Sphere>,
-    triangles: Vec<Triangle>,
-    vertices: Vec<Vector3<f64>>,
+    sdfs: Vec<Sdf>,
+    /// meshes[0] holds the triangles declared with vertex/tri
+    meshes: Vec<TriangleMesh>,
+    triangle_refs: Vec<TriangleRef>,
+    materials: Vec<Material>,
+    /// Scattering function of each material, same index
+    bsdfs: Vec<Box<dyn Bsdf>>,
+    /// Images the material texture slots point to
+    textures: Vec<Texture>,
+    lights: Vec<Light>,
+    /// Medium filling the space outside every shape, clear when None
+    fog: Option<HomogeneousMedium>,
+    /// Media inside the closed shapes whose material points to them
+    media: Vec<HomogeneousMedium>,
+    /// Seen by rays that miss everything, and lighting the scene from afar
+    environment: Environment,
     settings: RenderSettings,
+    /// Mesh, texture and environment files the scene file loaded
+    sources: Vec<PathBuf>,
 }
 
 impl std::default::Default for Scene {
@@ -45,10 +99,37 @@ impl std::default::Default for Scene {
         Self {
             cams: vec![],
             spheres: vec![],
-            triangles: vec![],
-            vertices: vec![],
+            sdfs: vec![],
+            meshes: vec![TriangleMesh::default()],
+            triangle_refs: vec![],
+            materials: vec![Material::default()],
+            bsdfs: vec![Material::default().bsdf()],
+            textures: vec![],
+            lights: vec![],
+            fog: None,
+            media: vec![],
+            environment: Environment::default(),
             settings: RenderSettings::default(),
+            sources: vec![],
+        }
+    }
+}
+
+impl Scene {
+    /// Adds a mesh with all its triangles
+    pub fn add_mesh(&mut self, mesh: TriangleMesh) {
+        let index = self.meshes.len() as u32;
+        self.triangle_refs.extend(mesh.triangle_refs(index));
+        self.meshes.push(mesh);
+    }
+
+    /// Index of `material` in the scene, consecutive shapes sharing a material share the entry
+    pub fn material_index(&mut self, material: Material) -> u32 {
+        if self.materials.last() != Some(&material) {
+            self.materials.push(material);
+            self.bsdfs.push(material.bsdf());
         }
+        self.materials.len() as u32 - 1
     }
 }
 
@@ -58,6 +139,25 @@ struct RenderSettings {
     pub height: u32,
     trace_depth: i32,
     pub output_file: String,
+    cull_backfaces: bool,
+    /// Samples per pixel
+    spp: u32,
+    /// Shadow rays per area light and shading point
+    light_samples: u32,
+    /// Scales the ambient term of Whitted by how occluded camera hits are
+    ambient_occlusion: Option<AmbientOcclusion>,
+    /// Sequence the samples of each pixel are drawn from
+    sampler: SamplerKind,
+    /// Extra samples for the pixels that are still noisy after `spp`, batch renders only
+    adaptive: Option<AdaptiveSampling>,
+    /// Render passes of one sample per pixel instead, saving snapshots along the way
+    progressive: Option<Progressive>,
+    /// How samples are weighted into the pixels around them
+    filter: Filter,
+    /// Side in pixels of the square tiles the threads render
+    tile_size: u32,
+    tile_order: TileOrder,
+    integrator: Box<dyn Integrator>,
 }
 
 impl RenderSettings {
@@ -67,80 +167,574 @@ impl RenderSettings {
             height: 300,
             trace_depth: 5,
             output_file: "image_out.png".to_string(),
+            cull_backfaces: false,
+            spp: 1,
+            light_samples: 1,
+            ambient_occlusion: None,
+            sampler: SamplerKind::default(),
+            adaptive: None,
+            progressive: None,
+            filter: Filter::default(),
+            tile_size: 32,
+            tile_order: TileOrder::default(),
+            integrator: Box::new(FlatColor),
         }
     }
 }
 
 fn main() -> io::Result<()> {
-    log4rs::init_file("src/log4rs.yml", Default::default()).unwrap();
     let args: Vec<String> = env::args().collect();
-    if args.len() < 2 {
-        panic!("Input file is mandatory");
+    if args.iter().any(|it| it == "--worker") {
+        // stdout carries the tiles of pipe workers
+        let stderr = ConsoleAppender::builder().target(Target::Stderr).build();
+        let config = Config::builder()
+            .appender(Appender::builder().build("stderr", Box::new(stderr)))
+            .build(Root::builder().appender("stderr").build(LevelFilter::Debug))
+            .unwrap();
+        log4rs::init_config(config).unwrap();
+    } else {
+        log4rs::init_file("src/log4rs.yml", Default::default()).unwrap();
     }
-
-    let file_name = &args[1];
+    let cli = CliArgs::parse(&args[1..]);
+    let Some(file_name) = cli.positional.first() else {
+        panic!("Input file is mandatory: my-ray-tracer <scene file in src/> [output png] [--integrator <name> [params]] [--sampler <name>] [--heatmap <png>] [--progressive <snapshots>] [--threads <n>] [--checkpoint <file>] [--resume <file>] [--workers <n|host:port>,..] [--worker] [--listen <host:port>]");
+    };
 
     let file_path = "src/".to_string() + file_name;
-    let output_file = "output_".to_string() + file_name + ".png";
-    let scene = read_scene(file_path);
+    let output_file = cli
+        .positional
+        .get(1)
+        .cloned()
+        .unwrap_or_else(|| "output_".to_string() + file_name + ".png");
+    let mut scene = read_scene(file_path.clone());
+    let scene_hash = hash_scene(&file_path, &scene, &cli)?;
+
+    // the command line wins over the scene file
+    if let Some(spec) = &cli.integrator {
+        let mut words = spec.split_whitespace();
+        let name = words.next().unwrap_or_default();
+        let params: Vec<Float> = words
+            .map(|w| w.parse::<Float>().unwrap_or_else(|_| panic!("Bad number {} in {}", w, spec)))
+            .collect();
+        scene.settings.integrator = integrators::from_name(name, &params)
+            .unwrap_or_else(|| panic!("Unknown integrator {}", spec));
+    }
+    if let Some(name) = &cli.sampler {
+        scene.settings.sampler =
+            SamplerKind::from_name(name).unwrap_or_else(|| panic!("Unknown sampler {}", name));
+    }
+    if let Some(spec) = &cli.progressive {
+        let words: Vec<&str> = spec.split_whitespace().collect();
+        scene.settings.progressive =
+            Some(Progressive::parse(&words).unwrap_or_else(|| panic!("Bad progressive {}", spec)));
+    }
+
+    if let Some(threads) = cli.threads {
+        rayon::ThreadPoolBuilder::new().num_threads(threads).build_global().unwrap();
+    }
+
+    if cli.worker {
+        return workers::serve(&scene, scene_hash, io::stdin().lock(), io::stdout().lock());
+    }
+    if let Some(address) = &cli.listen {
+        return workers::listen(&scene, scene_hash, TcpListener::bind(address)?);
+    }
+
+    let resume = cli.resume.as_ref().map(|path| {
+        let cam = scene.cams.first().unwrap();
+        let state = checkpoints::load(Path::new(path), scene_hash, (cam.width, cam.height))
+            .unwrap_or_else(|e| panic!("Can't resume from {}: {}", path, e));
+        info!("Resuming after {} passes from {}", state.passes, path);
+        state
+    });
+    // resumed renders keep checkpointing where they resumed from
+    let checkpoint_file = cli.checkpoint.as_ref().or(cli.resume.as_ref()).map(Path::new);
+    let save_checkpoint = |state: &RenderState| {
+        if let Some(path) = checkpoint_file {
+            checkpoints::save(path, scene_hash, state).unwrap();
+            info!("Saved checkpoint after {} passes to {}", state.passes, path.display());
+        }
+    };
 
     let now = Instant::now();
-    let image = RayTracer {}.ray_trace_par(&scene);
+    let image = if let Some(spec) = &cli.workers {
+        if scene.settings.progressive.is_some() || checkpoint_file.is_some() {
+            warn!("Distributed renders take all their samples at once, without checkpoints");
+        }
+        workers::render(&scene, scene_hash, open_workers(spec, &cli))
+    } else {
+        match &scene.settings.progressive {
+            // snapshots go to the output file, stopping the render leaves the last one there
+            Some(progressive) => {
+                let state =
+                    RayTracer {}.ray_trace_progressive(&scene, progressive, resume, |state, at| {
+                        save_image(&output_file, &state.image);
+                        info!(
+                            "Saved snapshot after {} passes and {:.1} seconds, noise {:.4}",
+                            at.passes, at.seconds, at.noise
+                        );
+                        save_checkpoint(state);
+                    });
+                save_checkpoint(&state);
+                state.image
+            },
+            None => {
+                if checkpoint_file.is_some() {
+                    warn!("Checkpoints are only taken by progressive renders");
+                }
+                RayTracer {}.ray_trace_par(&scene)
+            },
+        }
+    };
     info!("Finished render in {} milliseconds", now.elapsed().as_millis());
+    info!("Took {:.1} samples per pixel on average", image.average_samples());
 
+    save_image(&output_file, &image);
+    info!("Saved image to file {}s", output_file);
+
+    if let Some(heatmap_file) = &cli.heatmap {
+        image::save_buffer(
+            heatmap_file,
+            &image.samples_heatmap(),
+            image.width,
+            image.height,
+            image::ColorType::Rgb8,
+        )
+        .unwrap();
+        info!("Saved samples per pixel heatmap to file {}", heatmap_file);
+    }
+    Ok(())
+}
+
+/// Connects to the workers of a comma separated list: numbers start that many local workers
+/// talking over pipes, anything else is the address of a worker started with `--listen`.
+/// Workers that can't be reached are left out.
+fn open_workers(spec: &str, cli: &CliArgs) -> Vec<Connection> {
+    // local workers load the same scene with the same options
+    let mut args = vec![cli.positional[0].clone(), "--worker".to_string()];
+    for (option, value) in [
+        ("--integrator", &cli.integrator),
+        ("--sampler", &cli.sampler),
+        ("--threads", &cli.threads.map(|it| it.to_string())),
+    ] {
+        if let Some(value) = value {
+            args.extend([option.to_string(), value.clone()]);
+        }
+    }
+
+    let mut connections = vec![];
+    for worker in spec.split(',').map(str::trim).filter(|it| !it.is_empty()) {
+        let opened = match worker.parse::<usize>() {
+            Ok(count) => env::current_exe().and_then(|program| {
+                (0..count).map(|_| Connection::spawn(&program, &args)).collect()
+            }),
+            Err(_) => Connection::connect(worker, workers::WORKER_TIMEOUT).map(|it| vec![it]),
+        };
+        match opened {
+            Ok(opened) => connections.extend(opened),
+            Err(e) => warn!("Can't reach worker {}: {}", worker, e),
+        }
+    }
+    connections
+}
+
+/// Writes the image next to `path` first and moves it in place, so a render stopped while
+/// saving doesn't leave a truncated file behind
+fn save_image(path: &str, image: &Image) {
+    let path = Path::new(path);
+    let extension = path.extension().and_then(|it| it.to_str()).unwrap_or("png");
+    let partial = path.with_extension(format!("partial.{}", extension));
     image::save_buffer(
-        output_file.clone(),
+        &partial,
         &image.convert_to_one_row_array(),
         image.width,
         image.height,
         image::ColorType::Rgb8,
     )
     .unwrap();
+    fs::rename(&partial, path).unwrap();
+}
 
-    info!("Saved image to file {}s", output_file);
-    Ok(())
+/// Positional arguments plus `--option value` pairs
+#[derive(Debug, Default)]
+struct CliArgs {
+    positional: Vec<String>,
+    /// Name and parameters, quoted as one argument: --integrator "ao 32 1.5"
+    integrator: Option<String>,
+    /// random, stratified, halton or sobol
+    sampler: Option<String>,
+    /// Where to save an image of the samples each pixel took
+    heatmap: Option<String>,
+    /// Snapshot interval and stop criteria, quoted as one argument: --progressive "10 seconds"
+    progressive: Option<String>,
+    /// Size of the thread pool, one thread per core by default
+    threads: Option<usize>,
+    /// Where progressive renders save their state at every snapshot
+    checkpoint: Option<String>,
+    /// Checkpoint to carry on from, also checkpointed to unless --checkpoint says otherwise
+    resume: Option<String>,
+    /// Render on workers instead: --workers 4,render-box:7878
+    workers: Option<String>,
+    /// Be a worker of the coordinator on the other end of stdin and stdout
+    worker: bool,
+    /// Be a worker of any coordinator connecting to this address
+    listen: Option<String>,
+}
+
+impl CliArgs {
+    fn parse(args: &[String]) -> CliArgs {
+        let mut cli = CliArgs::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--integrator" => cli.integrator = args.next().cloned(),
+                "--sampler" => cli.sampler = args.next().cloned(),
+                "--heatmap" => cli.heatmap = args.next().cloned(),
+                "--progressive" => cli.progressive = args.next().cloned(),
+                "--checkpoint" => cli.checkpoint = args.next().cloned(),
+                "--resume" => cli.resume = args.next().cloned(),
+                "--workers" => cli.workers = args.next().cloned(),
+                "--worker" => cli.worker = true,
+                "--listen" => cli.listen = args.next().cloned(),
+                "--threads" => cli.threads = args.next().and_then(|it| it.parse().ok()),
+                _ if arg.starts_with("--") => warn!("Neglecting option {}", arg),
+                _ => cli.positional.push(arg.clone()),
+            }
+        }
+        cli
+    }
 }
 
 fn read_scene(file_path: String) -> Scene {
-    let file = File::open(file_path).unwrap();
+    // mesh files are looked up next to the scene file
+    let scene_dir = Path::new(&file_path).parent().unwrap_or(Path::new("")).to_path_buf();
+    let file = File::open(&file_path).unwrap();
     let reader = io::BufReader::new(file);
     let mut scene = Scene::default();
 
-    let mut transfstack: Vec<Matrix4<f64>> = vec![Matrix4::one()];
-    let mut inverse_transfstack: Vec<Matrix4<f64>> = vec![Matrix4::one()];
+    let mut transfstack: Vec<Matrix4<Float>> = vec![Matrix4::one()];
+    // distance function nodes waiting to be combined into an sdf shape
+    let mut sdf_stack: Vec<SdfNode> = vec![];
+    // material given to every shape created from now on
+    let mut material = Material::default();
+    // constant, linear and quadratic falloff of the next point lights
+    let mut attenuation = Vector3::new(1.0, 0.0, 0.0);
 
-    for line in reader.lines() {
+    for (number, line) in reader.lines().enumerate() {
         match line {
             Ok(line) => {
                 if !line.starts_with('#') && !line.is_empty() {
-                    info!("Line: {}", line);
+                    trace!("Line: {}", line);
                     let _list: Vec<&str> = line.split(' ').filter(|s| !s.is_empty()).collect();
                     let cmd = _list[0];
-                    let args: Vec<f64> =
-                        _list[1..].iter().map(|a| a.parse::<f64>().unwrap()).collect();
+                    let args = scene_args(cmd, &_list[1..]).unwrap_or_else(|token| {
+                        panic!("Bad number {} on line {}: {}", token, number + 1, line)
+                    });
 
                     match cmd {
                         "size" => handle_size(&mut scene, args[0] as u32, args[1] as u32),
-                        "output" => scene.settings.output_file = args[0].to_string(),
+                        "output" => scene.settings.output_file = _list[1].to_string(),
                         "maxdepth" => scene.settings.trace_depth = args[0] as i32,
+                        "cullbackfaces" => {
+                            scene.settings.cull_backfaces = args[0] != 0.0;
+                            scene.meshes[0].cull_backfaces = scene.settings.cull_backfaces;
+                        },
                         "camera" => scene.cams.push(create_camera(
                             scene.settings.width,
                             scene.settings.height,
                             &args,
                         )),
+                        "spp" => scene.settings.spp = args[0] as u32,
+                        "sampler" => match SamplerKind::from_name(_list[1]) {
+                            Some(sampler) => scene.settings.sampler = sampler,
+                            None => warn!("Unknown sampler {}", _list[1]),
+                        },
+                        "adaptive" => {
+                            scene.settings.adaptive = Some(AdaptiveSampling {
+                                threshold: args[0],
+                                max_spp: args[1] as u32,
+                            })
+                        },
+                        "progressive" => match Progressive::parse(&_list[1..]) {
+                            Some(progressive) => scene.settings.progressive = Some(progressive),
+                            None => warn!("Bad progressive {}", line),
+                        },
+                        "tilesize" => scene.settings.tile_size = args[0] as u32,
+                        "tileorder" => match TileOrder::from_name(_list[1]) {
+                            Some(order) => scene.settings.tile_order = order,
+                            None => warn!("Unknown tile order {}", _list[1]),
+                        },
+                        "filter" => match Filter::from_name(_list[1], &args) {
+                            Some(filter) => scene.settings.filter = filter,
+                            None => panic!("Bad filter on line {}: {}", number + 1, line),
+                        },
+                        "ambientocclusion" => {
+                            scene.settings.ambient_occlusion = Some(AmbientOcclusion {
+                                samples: args[0] as u32,
+                                max_distance: args.get(1).copied().unwrap_or(Float::INFINITY),
+                            })
+                        },
+                        "integrator" => match integrators::from_name(_list[1], &args) {
+                            Some(integrator) => scene.settings.integrator = integrator,
+                            None => warn!("Unknown integrator {}", _list[1]),
+                        },
+
+                        // LIGHTS
+                        "point" => {
+                            let position = transfstack.last().unwrap()
+                                * Vector4::new(args[0], args[1], args[2], 1.0);
+                            scene.lights.push(Light::Point {
+                                position: position.truncate(),
+                                color: Vector3::new(args[3], args[4], args[5]),
+                                attenuation,
+                            })
+                        },
+                        "spot" => {
+                            let transform = transfstack.last().unwrap();
+                            let inner: Float = args[9].to_radians();
+                            let outer: Float = args[10].to_radians();
+                            scene.lights.push(Light::Spot {
+                                position: transform_point(transform, &args[0..3]),
+                                direction: transform_vector(transform, &args[3..6]).normalize(),
+                                color: Vector3::new(args[6], args[7], args[8]),
+                                attenuation,
+                                cos_inner: inner.min(outer).cos(),
+                                cos_outer: outer.cos(),
+                                exponent: args.get(11).copied().unwrap_or(1.0),
+                            })
+                        },
+                        "directional" => {
+                            let direction = transfstack.last().unwrap()
+                                * Vector4::new(args[0], args[1], args[2], 0.0);
+                            scene.lights.push(Light::Directional {
+                                direction: direction.truncate(),
+                                color: Vector3::new(args[3], args[4], args[5]),
+                            })
+                        },
+                        "fog" => scene.fog = Some(create_medium(&args)),
+                        "environment" => {
+                            let path = scene_dir.join(_list[1]);
+                            let map =
+                                EnvironmentMap::load(&path, args.first().copied().unwrap_or(1.0))
+                                    .unwrap_or_else(|err| {
+                                        panic!(
+                                            "Could not load environment {}: {}",
+                                            path.display(),
+                                            err
+                                        )
+                                    });
+                            scene.environment = Environment::Map(map);
+                            scene.sources.push(path);
+                        },
+                        "sky" => {
+                            let horizon = Vector3::new(args[0], args[1], args[2]);
+                            scene.environment = match args.get(3..6) {
+                                Some(zenith) => Environment::Gradient {
+                                    horizon,
+                                    zenith: Vector3::new(zenith[0], zenith[1], zenith[2]),
+                                },
+                                None => Environment::Constant(horizon),
+                            };
+                        },
+                        "sunSky" => {
+                            let sun = transform_vector(transfstack.last().unwrap(), &args[0..3]);
+                            add_sun_sky(&mut scene, sun, &args[3..]);
+                        },
+                        "sunSkyTime" => {
+                            let sun = sun_direction(args[0], args[1], args[2]);
+                            add_sun_sky(&mut scene, sun, &args[3..]);
+                        },
+                        "attenuation" => attenuation = Vector3::new(args[0], args[1], args[2]),
+                        "lightsamples" => scene.settings.light_samples = args[0] as u32,
+                        "quadLight" | "diskLight" | "sphereLight" => {
+                            let transform = transfstack.last().unwrap();
+                            let light = match cmd {
+                                "quadLight" => Light::Quad {
+                                    corner: transform_point(transform, &args[0..3]),
+                                    edge_u: transform_vector(transform, &args[3..6]),
+                                    edge_v: transform_vector(transform, &args[6..9]),
+                                    radiance: Vector3::new(args[9], args[10], args[11]),
+                                },
+                                "diskLight" => Light::Disk {
+                                    center: transform_point(transform, &args[0..3]),
+                                    normal: transform_vector(transform, &args[3..6]).normalize(),
+                                    radius: args[6],
+                                    radiance: Vector3::new(args[7], args[8], args[9]),
+                                },
+                                _ => Light::Sphere {
+                                    center: transform_point(transform, &args[0..3]),
+                                    radius: args[3],
+                                    radiance: Vector3::new(args[4], args[5], args[6]),
+                                },
+                            };
+                            if light.is_degenerate() {
+                                panic!("Degenerate {} on line {}: {}", cmd, number + 1, line)
+                            }
+                            scene.lights.push(light)
+                        },
+
+                        // MATERIALS
+                        "ambient" => material.ambient = Vector3::new(args[0], args[1], args[2]),
+                        "diffuse" => material.diffuse = Vector3::new(args[0], args[1], args[2]),
+                        "specular" => material.specular = Vector3::new(args[0], args[1], args[2]),
+                        "shininess" => material.shininess = args[0],
+                        "emission" => material.emission = Vector3::new(args[0], args[1], args[2]),
+                        "ior" => material.ior = args[0],
+                        "transmission" => {
+                            material.transmission = Vector3::new(args[0], args[1], args[2])
+                        },
+                        "roughness" => material.roughness = args[0],
+                        "bumpscale" => material.bump_scale = args[0],
+                        "medium" => {
+                            material.medium = match _list[1] {
+                                "none" => None,
+                                _ => {
+                                    scene.media.push(create_medium(&args));
+                                    Some(scene.media.len() as u32 - 1)
+                                },
+                            }
+                        },
+                        "bsdf" => match _list[1] {
+                            "lambert" => material.model = BsdfModel::Lambertian,
+                            "phong" => material.model = BsdfModel::Phong,
+                            "conductor" => {
+                                material.model = BsdfModel::Conductor {
+                                    eta: Vector3::new(args[0], args[1], args[2]),
+                                    k: Vector3::new(args[3], args[4], args[5]),
+                                }
+                            },
+                            _ => warn!("Unknown bsdf {}", _list[1]),
+                        },
+                        "texture" => {
+                            let texture = match _list[2] {
+                                "none" => None,
+                                name => {
+                                    let texture = match Pattern::from_name(name, &args) {
+                                        Some(pattern) => Texture::Procedural {
+                                            pattern,
+                                            space: if _list.contains(&"world") {
+                                                TextureSpace::World
+                                            } else {
+                                                TextureSpace::Object
+                                            },
+                                        },
+                                        None => {
+                                            let path = scene_dir.join(name);
+                                            let image =
+                                                load_image_texture(&path, _list.get(3).copied());
+                                            scene.sources.push(path);
+                                            Texture::Image(image)
+                                        },
+                                    };
+                                    scene.textures.push(texture);
+                                    Some(scene.textures.len() as u32 - 1)
+                                },
+                            };
+                            match _list[1] {
+                                "ambient" => material.ambient_texture = texture,
+                                "diffuse" => material.diffuse_texture = texture,
+                                "specular" => material.specular_texture = texture,
+                                "shininess" => material.shininess_texture = texture,
+                                "emission" => material.emission_texture = texture,
+                                "roughness" => material.roughness_texture = texture,
+                                "normal" => material.normal_map = texture,
+                                "bump" => material.bump_map = texture,
+                                slot => warn!("Unknown texture slot {}", slot),
+                            }
+                        },
 
                         // GEOMETRY
                         "sphere" => {
-                            scene.spheres.push(create_sphere(&args, *transfstack.last().unwrap()))
+                            let mut sphere = create_sphere(&args, *transfstack.last().unwrap());
+                            sphere.g_shape.material = scene.material_index(material);
+                            scene.spheres.push(sphere);
                         },
                         "maxverts" => {
-                            // scene.vertices
+                            scene.meshes[0].positions.reserve(args[0] as usize);
                         },
                         "vertex" => {
-                            scene.vertices.push(Vector3::new(args[0], args[1], args[2]));
+                            let mesh = &mut scene.meshes[0];
+                            mesh.positions.push(Vector3::new(args[0], args[1], args[2]));
+                            // once some vertex has uvs every vertex needs them
+                            if !mesh.uvs.is_empty() {
+                                mesh.uvs.push(Vector2::zero());
+                            }
+                        },
+                        "vertextex" => {
+                            let mesh = &mut scene.meshes[0];
+                            mesh.uvs.resize(mesh.positions.len(), Vector2::zero());
+                            mesh.positions.push(Vector3::new(args[0], args[1], args[2]));
+                            mesh.uvs.push(Vector2::new(args[3], args[4]));
                         },
                         "tri" => {
-                            scene.triangles.push(create_triangle(args, &scene));
+                            let id = scene.material_index(material);
+                            add_triangle(
+                                &mut scene,
+                                args[0] as u32,
+                                args[1] as u32,
+                                args[2] as u32,
+                                id,
+                            )
+                        },
+                        "ply" | "stl" => {
+                            let path = scene_dir.join(_list[1]);
+                            let loaded = match cmd {
+                                "ply" => mesh_loaders::load_ply(&path),
+                                _ => mesh_loaders::load_stl(&path),
+                            };
+                            let mut mesh = loaded.unwrap_or_else(|err| {
+                                panic!("Could not load mesh {}: {}", path.display(), err)
+                            });
+                            mesh.transform(*transfstack.last().unwrap());
+                            mesh.cull_backfaces = scene.settings.cull_backfaces;
+                            mesh.material = scene.material_index(material);
+                            scene.add_mesh(mesh);
+                            scene.sources.push(path);
+                        },
+
+                        // SIGNED DISTANCE FIELDS
+                        "sdfSphere" => sdf_stack.push(SdfNode::Sphere {
+                            center: Vector3::new(args[0], args[1], args[2]),
+                            radius: args[3],
+                        }),
+                        "sdfBox" => sdf_stack.push(SdfNode::Cuboid {
+                            center: Vector3::new(args[0], args[1], args[2]),
+                            half_size: Vector3::new(args[3], args[4], args[5]),
+                        }),
+                        "sdfTorus" => sdf_stack.push(SdfNode::Torus {
+                            center: Vector3::new(args[0], args[1], args[2]),
+                            major_radius: args[3],
+                            minor_radius: args[4],
+                        }),
+                        "sdfCylinder" => sdf_stack.push(SdfNode::Cylinder {
+                            center: Vector3::new(args[0], args[1], args[2]),
+                            radius: args[3],
+                            half_height: args[4],
+                        }),
+                        "sdfUnion" => {
+                            let (a, b) = pop_sdf_operands(&mut sdf_stack);
+                            sdf_stack.push(SdfNode::Union(a, b));
+                        },
+                        "sdfSmoothUnion" => {
+                            let (a, b) = pop_sdf_operands(&mut sdf_stack);
+                            sdf_stack.push(SdfNode::SmoothUnion(a, b, args[0]));
+                        },
+                        "sdfIntersect" => {
+                            let (a, b) = pop_sdf_operands(&mut sdf_stack);
+                            sdf_stack.push(SdfNode::Intersection(a, b));
+                        },
+                        "sdfSubtract" => {
+                            let (a, b) = pop_sdf_operands(&mut sdf_stack);
+                            sdf_stack.push(SdfNode::Subtraction(a, b));
+                        },
+                        "sdfBlend" => {
+                            let (a, b) = pop_sdf_operands(&mut sdf_stack);
+                            sdf_stack.push(SdfNode::Blend(a, b, args[0]));
+                        },
+                        "sdfEnd" => {
+                            let mut sdf = create_sdf(&mut sdf_stack, *transfstack.last().unwrap());
+                            sdf.g_shape.material = scene.material_index(material);
+                            scene.sdfs.push(sdf);
                         },
 
                         // TRANSFORMS
@@ -150,14 +744,12 @@ fn read_scene(file_path: String) -> Scene {
                             // info!("Generated translation matrix: {:?} ", translation);
 
                             right_multiply(translation, &mut transfstack);
-                            left_multiply(translation.invert().unwrap(), &mut inverse_transfstack);
                             // info!("Stack state:  {:?}", transfstack.iter().copied().rev());
                         },
                         "scale" => {
                             let scale = Matrix4::from_nonuniform_scale(args[0], args[1], args[2]);
 
                             right_multiply(scale, &mut transfstack);
-                            left_multiply(scale.invert().unwrap(), &mut inverse_transfstack);
                             // info!("Stack state:  {:?}", transfstack.iter());
                         },
                         "rotate" => {
@@ -168,7 +760,6 @@ fn read_scene(file_path: String) -> Scene {
                             let scale = Matrix4::from_axis_angle(axis, theta);
 
                             right_multiply(scale, &mut transfstack);
-                            left_multiply(scale.invert().unwrap(), &mut inverse_transfstack);
                             // info!("Stack state:  {:?}", transfstack.iter().copied().rev());
                         },
                         "pushTransform" => {
@@ -191,22 +782,134 @@ fn read_scene(file_path: String) -> Scene {
         }
     }
 
+    add_mesh_lights(&mut scene);
     scene
 }
 
-fn create_triangle(args: Vec<f64>, scene: &Scene) -> Triangle {
-    let vert_indexes = vec![args[0] as usize, args[1] as usize, args[2] as usize];
-    let a = scene.vertices[vert_indexes[0]].to_vector4();
-    let b = scene.vertices[vert_indexes[1]].to_vector4();
-    let c = scene.vertices[vert_indexes[2]].to_vector4();
-    Triangle::new(vert_indexes, a, b, c)
+/// Checkpoints and workers only take up the scene they were made with: the scene file, the
+/// files it loaded and the command line options changing the image
+fn hash_scene(file_path: &str, scene: &Scene, cli: &CliArgs) -> io::Result<u64> {
+    let mut files = vec![fs::read(file_path)?];
+    for path in &scene.sources {
+        files.push(fs::read(path)?);
+    }
+    let mut parts: Vec<&[u8]> = files.iter().map(|it| it.as_slice()).collect();
+    parts.push(cli.integrator.as_deref().unwrap_or_default().as_bytes());
+    parts.push(cli.sampler.as_deref().unwrap_or_default().as_bytes());
+    Ok(checkpoints::scene_hash(&parts))
+}
+
+/// Numeric arguments of a scene command, or the first token that should be a number and
+/// isn't. Names (files, kinds, keywords) are read from the line by their command: they lead the
+/// numbers, and only textures take more after them.
+fn scene_args<'a>(cmd: &str, tokens: &[&'a str]) -> Result<Vec<Float>, &'a str> {
+    let names = match cmd {
+        "output" | "sampler" | "tileorder" | "filter" | "integrator" | "environment" | "bsdf"
+        | "ply" | "stl" => 1,
+        "medium" if tokens.first() == Some(&"none") => 1,
+        "texture" => 2,
+        // mixes numbers and keywords, parsed by `Progressive::parse`
+        "progressive" => return Ok(vec![]),
+        _ => 0,
+    };
+    let tokens = tokens.get(names..).unwrap_or_default();
+    let numbers = tokens.iter().take_while(|it| it.parse::<Float>().is_ok()).count();
+    // a wrap mode or texture space
+    let trailing_names =
+        cmd == "texture" && tokens[numbers..].iter().all(|it| it.parse::<Float>().is_err());
+    if let Some(token) = tokens.get(numbers).filter(|_| !trailing_names) {
+        return Err(token);
+    }
+    Ok(tokens[..numbers].iter().map(|it| it.parse().unwrap()).collect())
+}
+
+/// The triangles with an emissive material of each mesh become one light, so they are sampled
+/// for soft shadows instead of only found by chance
+fn add_mesh_lights(scene: &mut Scene) {
+    for (index, mesh) in scene.meshes.iter().enumerate() {
+        let emissive: Vec<_> = (0..mesh.triangle_count() as u32)
+            .filter_map(|triangle| {
+                let material = &scene.materials[mesh.triangle_material(triangle) as usize];
+                material
+                    .is_area_light()
+                    .then(|| (mesh.triangle_vertices(triangle), material.emission))
+            })
+            .collect();
+        if !emissive.is_empty() {
+            scene.lights.push(Light::Mesh(MeshLight::new(index as u32, emissive)));
+        }
+    }
+}
+
+/// Daylight sky and its sun light. `args` are the optional turbidity and scale.
+fn add_sun_sky(scene: &mut Scene, sun: Vector3<Float>, args: &[Float]) {
+    let turbidity = args.first().copied().unwrap_or(3.0);
+    // brings a sunlit white floor close to 1
+    let scale = args.get(1).copied().unwrap_or(0.02);
+    let sky = PreethamSky::new(sun, turbidity, scale);
+    scene.lights.push(Light::Directional { direction: sky.sun(), color: sky.sun_color() });
+    scene.environment = Environment::Sky(sky);
+}
+
+/// Medium from absorption and scattering colors and the optional phase asymmetry
+fn create_medium(args: &[Float]) -> HomogeneousMedium {
+    HomogeneousMedium {
+        sigma_a: Vector3::new(args[0], args[1], args[2]),
+        sigma_s: Vector3::new(args[3], args[4], args[5]),
+        g: args.get(6).copied().unwrap_or(0.0),
+    }
+}
+
+/// Image texture for the texture command, `wrap` is the optional wrap mode name
+fn load_image_texture(path: &Path, wrap: Option<&str>) -> ImageTexture {
+    let wrap = match wrap {
+        None => WrapMode::default(),
+        Some(name) => WrapMode::from_name(name).unwrap_or_else(|| {
+            warn!("Unknown wrap mode {}", name);
+            WrapMode::default()
+        }),
+    };
+    ImageTexture::load(path, wrap)
+        .unwrap_or_else(|err| panic!("Could not load texture {}: {}", path.display(), err))
+}
+
+fn transform_point(transform: &Matrix4<Float>, xyz: &[Float]) -> Vector3<Float> {
+    (transform * Vector4::new(xyz[0], xyz[1], xyz[2], 1.0)).truncate()
+}
+
+fn transform_vector(transform: &Matrix4<Float>, xyz: &[Float]) -> Vector3<Float> {
+    (transform * Vector4::new(xyz[0], xyz[1], xyz[2], 0.0)).truncate()
+}
+
+/// Adds a triangle to the mesh fed by vertex/tri
+fn add_triangle(scene: &mut Scene, a: u32, b: u32, c: u32, material: u32) {
+    let mesh = &mut scene.meshes[0];
+    scene.triangle_refs.push(TriangleRef { mesh: 0, triangle: mesh.triangle_count() as u32 });
+    mesh.push_triangle(a, b, c);
+    mesh.material_ids.push(material);
 }
 
-fn create_sphere(args: &[f64], transform: Matrix4<f64>) -> Sphere {
+fn create_sphere(args: &[Float], transform: Matrix4<Float>) -> Sphere {
     Sphere::from(args[0], args[1], args[2], args[3], transform)
 }
 
-fn create_camera(width: u32, height: u32, _args: &[f64]) -> Camera {
+/// Pops the two most recent sdf nodes, in the order they were declared
+fn pop_sdf_operands(sdf_stack: &mut Vec<SdfNode>) -> (Box<SdfNode>, Box<SdfNode>) {
+    let b = sdf_stack.pop().expect("sdf operator needs two operands");
+    let a = sdf_stack.pop().expect("sdf operator needs two operands");
+    (Box::new(a), Box::new(b))
+}
+
+/// Builds an sdf shape out of every pending node, leftovers are merged with a plain union
+fn create_sdf(sdf_stack: &mut Vec<SdfNode>, transform: Matrix4<Float>) -> Sdf {
+    let root = sdf_stack
+        .drain(..)
+        .reduce(|a, b| SdfNode::Union(Box::new(a), Box::new(b)))
+        .expect("sdfEnd without any sdf primitive");
+    Sdf::from(root, transform)
+}
+
+fn create_camera(width: u32, height: u32, _args: &[Float]) -> Camera {
     Camera::new(
         width,
         height,
@@ -222,12 +925,7 @@ fn handle_size(scene: &mut Scene, width: u32, height: u32) {
     scene.settings.height = height;
 }
 
-fn right_multiply(m: Matrix4<f64>, transfstack: &mut Vec<Matrix4<f64>>) {
+fn right_multiply(m: Matrix4<Float>, transfstack: &mut Vec<Matrix4<Float>>) {
     let top = transfstack.pop().unwrap();
     transfstack.push(top * m);
 }
-
-fn left_multiply(inverse_m: Matrix4<f64>, inverse_transfstack: &mut Vec<Matrix4<f64>>) {
-    let top = inverse_transfstack.pop().unwrap();
-    inverse_transfstack.push(inverse_m * top);
-}
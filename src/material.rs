@@ -0,0 +1,253 @@
+#[cfg(test)]
+mod test;
+
+pub mod materials {
+    use cgmath::{InnerSpace, Vector3, Zero};
+
+    use crate::bsdf::bsdfs::{
+        luminance, Bsdf, BsdfModel, Ggx, GgxConductor, Lambertian, Phong, RoughDielectric,
+    };
+    use crate::sampling::samplers::orthonormal_basis;
+    use crate::texture::textures::{SurfacePoint, Texture};
+    use crate::Float;
+
+    /// Surface description set by the ambient/diffuse/specular/shininess/emission commands and
+    /// shared by every shape declared after them. A non zero `transmission` makes it a dielectric
+    /// that refracts with index `ior`. The path tracer scatters with the bsdf `model`. Texture
+    /// slots index the scene textures and replace their value where set, scalar slots take the
+    /// luminance of the texture. A normal map or, failing that, a bump map bends the shading
+    /// normal. Closed shapes can hold a participating `medium`.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct Material {
+        pub ambient: Vector3<Float>,
+        pub diffuse: Vector3<Float>,
+        pub specular: Vector3<Float>,
+        pub shininess: Float,
+        pub emission: Vector3<Float>,
+        pub ior: Float,
+        pub transmission: Vector3<Float>,
+        /// Perceptual roughness of the microfacet models, 0 is smooth
+        pub roughness: Float,
+        pub model: BsdfModel,
+        pub ambient_texture: Option<u32>,
+        pub diffuse_texture: Option<u32>,
+        pub specular_texture: Option<u32>,
+        pub shininess_texture: Option<u32>,
+        pub emission_texture: Option<u32>,
+        pub roughness_texture: Option<u32>,
+        /// Tangent space normals, x along u, y along v and z out of the surface, stored as
+        /// colors in [0, 1]
+        pub normal_map: Option<u32>,
+        /// Height field whose luminance times `bump_scale` displaces the surface
+        pub bump_map: Option<u32>,
+        pub bump_scale: Float,
+        /// Medium filling the inside of the shapes made of it
+        pub medium: Option<u32>,
+    }
+
+    impl Default for Material {
+        fn default() -> Self {
+            Self {
+                ambient: Vector3::new(0.2, 0.2, 0.2),
+                diffuse: Vector3::zero(),
+                specular: Vector3::zero(),
+                shininess: 1.0,
+                emission: Vector3::zero(),
+                ior: 1.0,
+                transmission: Vector3::zero(),
+                roughness: 0.0,
+                model: BsdfModel::default(),
+                ambient_texture: None,
+                diffuse_texture: None,
+                specular_texture: None,
+                shininess_texture: None,
+                emission_texture: None,
+                roughness_texture: None,
+                normal_map: None,
+                bump_map: None,
+                bump_scale: 1.0,
+                medium: None,
+            }
+        }
+    }
+
+    impl Material {
+        pub fn is_dielectric(&self) -> bool {
+            self.transmission != Vector3::zero()
+        }
+
+        /// Smooth glass has no bsdf worth sampling, integrators trace it directly
+        pub fn is_smooth_dielectric(&self) -> bool {
+            self.is_dielectric() && self.roughness == 0.0
+        }
+
+        /// Surface only there to hold a medium, rays cross it untouched. Glass keeps refracting
+        /// with the medium behind it.
+        pub fn is_medium_boundary(&self) -> bool {
+            self.medium.is_some() && !self.is_dielectric()
+        }
+
+        pub fn is_textured(&self) -> bool {
+            [
+                self.ambient_texture,
+                self.diffuse_texture,
+                self.specular_texture,
+                self.shininess_texture,
+                self.emission_texture,
+                self.roughness_texture,
+            ]
+            .iter()
+            .any(Option::is_some)
+        }
+
+        /// Emits the same everywhere, so triangles made of it can be sampled as lights
+        pub fn is_area_light(&self) -> bool {
+            self.emission != Vector3::zero() && self.emission_texture.is_none()
+        }
+
+        /// The material at one point of a surface, with its textures looked up there
+        pub fn at(&self, point: &SurfacePoint, textures: &[Texture]) -> Material {
+            let lookup = |slot: Option<u32>, color: Vector3<Float>| match slot {
+                Some(texture) => textures[texture as usize].eval(point),
+                None => color,
+            };
+            let scalar = |slot: Option<u32>, value: Float| match slot {
+                Some(texture) => luminance(textures[texture as usize].eval(point)),
+                None => value,
+            };
+            Material {
+                ambient: lookup(self.ambient_texture, self.ambient),
+                diffuse: lookup(self.diffuse_texture, self.diffuse),
+                specular: lookup(self.specular_texture, self.specular),
+                shininess: scalar(self.shininess_texture, self.shininess),
+                emission: lookup(self.emission_texture, self.emission),
+                roughness: scalar(self.roughness_texture, self.roughness),
+                ..*self
+            }
+        }
+
+        pub fn perturbs_normal(&self) -> bool {
+            self.normal_map.is_some() || self.bump_map.is_some()
+        }
+
+        /// Normal shading uses at `point`: its surface normal bent by the normal or bump map
+        pub fn shading_normal(&self, point: &SurfacePoint, textures: &[Texture]) -> Vector3<Float> {
+            let n = point.normal;
+            let (dpdu, dpdv) = tangents(point);
+
+            if let Some(map) = self.normal_map {
+                let local = textures[map as usize].eval(point) * 2.0 - Vector3::new(1.0, 1.0, 1.0);
+                let t = (dpdu - n * n.dot(dpdu)).normalize();
+                // mirrored uvs flip the bitangent
+                let b = if n.cross(t).dot(dpdv) < 0.0 { -n.cross(t) } else { n.cross(t) };
+                let bent = t * local.x + b * local.y + n * local.z;
+                return if bent.magnitude2() > 0.0 { bent.normalize() } else { n };
+            }
+
+            if let Some(map) = self.bump_map {
+                let height = |it: &SurfacePoint| {
+                    luminance(textures[map as usize].eval(it)) * self.bump_scale
+                };
+                let base = height(point);
+                let dhdu = (height(&point.offset(BUMP_DELTA, 0.0)) - base) / BUMP_DELTA;
+                let dhdv = (height(&point.offset(0.0, BUMP_DELTA)) - base) / BUMP_DELTA;
+                // normal of the surface displaced along n, ignoring how n itself varies
+                let bent = (dpdu + n * dhdu).cross(dpdv + n * dhdv).normalize();
+                return if bent.dot(n) < 0.0 { -bent } else { bent };
+            }
+
+            n
+        }
+
+        /// Scattering function the path tracer uses for this material
+        pub fn bsdf(&self) -> Box<dyn Bsdf> {
+            if self.is_dielectric() {
+                return Box::new(RoughDielectric {
+                    distribution: Ggx::from_roughness(self.roughness),
+                    ior: self.ior,
+                    transmission: self.transmission,
+                });
+            }
+
+            match self.model {
+                BsdfModel::Lambertian => Box::new(Lambertian { albedo: self.diffuse }),
+                BsdfModel::Phong => Box::new(Phong {
+                    diffuse: self.diffuse,
+                    specular: self.specular,
+                    exponent: self.shininess,
+                }),
+                BsdfModel::Conductor { eta, k } => Box::new(GgxConductor {
+                    distribution: Ggx::from_roughness(self.roughness),
+                    eta,
+                    k,
+                }),
+            }
+        }
+    }
+
+    /// Step in uv of the finite differences that find the slope of bump maps
+    const BUMP_DELTA: Float = if cfg!(feature = "f32") { 1e-3 } else { 5e-4 };
+
+    /// Derivatives along u and v, or any frame around the normal when the surface has no
+    /// mapping
+    fn tangents(point: &SurfacePoint) -> (Vector3<Float>, Vector3<Float>) {
+        let area = point.dpdu.cross(point.dpdv).magnitude2();
+        if area > 1e-12 * point.dpdu.magnitude2() * point.dpdv.magnitude2() {
+            (point.dpdu, point.dpdv)
+        } else {
+            orthonormal_basis(point.normal)
+        }
+    }
+
+    /// Mirrors `d` around the normal `n`
+    pub fn reflect(d: Vector3<Float>, n: Vector3<Float>) -> Vector3<Float> {
+        d - n * (2.0 * d.dot(n))
+    }
+
+    /// Snell's law for a unit direction `d` hitting a surface with unit normal `n` facing against
+    /// it, `eta` = incident ior / transmitted ior. None on total internal reflection.
+    pub fn refract(d: Vector3<Float>, n: Vector3<Float>, eta: Float) -> Option<Vector3<Float>> {
+        let cos_i = -d.dot(n);
+        let sin2_t = eta * eta * (1.0 - cos_i * cos_i);
+        if sin2_t > 1.0 {
+            return None;
+        }
+        let cos_t = (1.0 - sin2_t).sqrt();
+        Some((d * eta + n * (eta * cos_i - cos_t)).normalize())
+    }
+
+    /// Exact Fresnel reflectance of unpolarized light on a dielectric boundary, `eta` = incident
+    /// ior / transmitted ior. The transmitted share is 1 - reflectance.
+    pub fn fresnel_dielectric(cos_i: Float, eta: Float) -> Float {
+        let cos_i = cos_i.clamp(0.0, 1.0);
+        let sin2_t = eta * eta * (1.0 - cos_i * cos_i);
+        if sin2_t >= 1.0 {
+            return 1.0;
+        }
+        let cos_t = (1.0 - sin2_t).sqrt();
+
+        let r_parallel = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+        let r_perpendicular = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+        (r_parallel * r_parallel + r_perpendicular * r_perpendicular) / 2.0
+    }
+
+    /// Exact Fresnel reflectance of unpolarized light on a conductor with complex ior
+    /// `eta` + i `k`, coming from a medium of ior 1
+    pub fn fresnel_conductor(cos_i: Float, eta: Float, k: Float) -> Float {
+        let cos2 = cos_i.clamp(0.0, 1.0).powi(2);
+        let sin2 = 1.0 - cos2;
+
+        let t0 = eta * eta - k * k - sin2;
+        let a2_plus_b2 = (t0 * t0 + 4.0 * eta * eta * k * k).sqrt();
+        let t1 = a2_plus_b2 + cos2;
+        let a = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+        let t2 = 2.0 * cos_i.clamp(0.0, 1.0) * a;
+        let r_perpendicular = (t1 - t2) / (t1 + t2);
+
+        let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+        let t4 = t2 * sin2;
+        let r_parallel = r_perpendicular * (t3 - t4) / (t3 + t4);
+
+        (r_parallel + r_perpendicular) / 2.0
+    }
+}
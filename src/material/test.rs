@@ -0,0 +1,188 @@
+#[cfg(test)]
+mod material_test {
+
+    use cgmath::{InnerSpace, Vector2, Vector3};
+
+    use crate::camera::camera_view::Ray;
+    use crate::integrator::integrators::{FlatColor, Integrator, Whitted};
+    use crate::material::materials::{
+        fresnel_conductor, fresnel_dielectric, reflect, refract, Material,
+    };
+    use crate::sampling::samplers::Rng;
+    use crate::texture::textures::{ImageTexture, SurfacePoint, Texture, WrapMode};
+    use crate::{read_scene, Float};
+    use approx::assert_relative_eq;
+
+    const GLASS: Float = 1.5;
+
+    #[test]
+    fn should_match_schlick_r0_at_normal_incidence() {
+        // given air to glass at normal incidence
+        let r0 = ((1.0 - GLASS) / (1.0 + GLASS)).powi(2);
+
+        // then exact fresnel gives the same reflectance both ways
+        assert_relative_eq!(fresnel_dielectric(1.0, 1.0 / GLASS), r0, epsilon = 1e-6);
+        assert_relative_eq!(fresnel_dielectric(1.0, GLASS), r0, epsilon = 1e-6);
+    }
+
+    #[test]
+    fn should_match_conductor_reflectance_at_normal_incidence() {
+        // given gold like complex ior at normal incidence
+        let (eta, k): (Float, Float) = (0.2, 3.0);
+
+        // then the classic ((n - 1)^2 + k^2) / ((n + 1)^2 + k^2)
+        let expected = ((eta - 1.0).powi(2) + k * k) / ((eta + 1.0).powi(2) + k * k);
+        assert_relative_eq!(fresnel_conductor(1.0, eta, k), expected, epsilon = 1e-6);
+
+        // and without absorption it is glass
+        for cos_i in [1.0, 0.7, 0.2] {
+            assert_relative_eq!(
+                fresnel_conductor(cos_i, GLASS, 0.0),
+                fresnel_dielectric(cos_i, 1.0 / GLASS),
+                epsilon = 1e-6
+            );
+        }
+    }
+
+    #[test]
+    fn should_reflect_everything_at_grazing_angle() {
+        assert_relative_eq!(fresnel_dielectric(0.0, 1.0 / GLASS), 1.0, epsilon = 1e-6);
+    }
+
+    #[test]
+    fn should_totally_reflect_past_critical_angle() {
+        // given a ray inside glass, just past the critical angle
+        let critical = (1.0 / GLASS).asin();
+        let angle = critical + 0.01;
+        let n = Vector3::new(0.0, 1.0, 0.0);
+        let d = Vector3::new(angle.sin(), -angle.cos(), 0.0);
+
+        // then nothing is transmitted
+        assert!(refract(d, n, GLASS).is_none());
+        assert_relative_eq!(fresnel_dielectric(angle.cos(), GLASS), 1.0);
+
+        // but just before it light still gets out
+        let angle = critical - 0.01;
+        let d = Vector3::new(angle.sin(), -angle.cos(), 0.0);
+        assert!(refract(d, n, GLASS).is_some());
+        assert!(fresnel_dielectric(angle.cos(), GLASS) < 1.0);
+    }
+
+    #[test]
+    fn should_follow_snell_law() {
+        // given a ray entering glass at 40 degrees
+        let angle: Float = 40.0_f64.to_radians() as Float;
+        let n = Vector3::new(0.0, 1.0, 0.0);
+        let d = Vector3::new(angle.sin(), -angle.cos(), 0.0);
+
+        // when refracting
+        let t = refract(d, n, 1.0 / GLASS).unwrap();
+
+        // then sin(i) = ior * sin(t), bending towards the normal
+        assert_relative_eq!(t.magnitude(), 1.0, epsilon = 1e-6);
+        assert_relative_eq!(angle.sin(), GLASS * t.x, epsilon = 1e-6);
+        assert!(t.y < 0.0);
+    }
+
+    #[test]
+    fn should_reflect_around_normal() {
+        let n = Vector3::new(0.0, 1.0, 0.0);
+        let d = Vector3::new(1.0, -1.0, 0.0).normalize();
+
+        let r = reflect(d, n);
+
+        assert_relative_eq!(r.dot(Vector3::new(1.0, 1.0, 0.0).normalize()), 1.0, epsilon = 1e-6);
+    }
+
+    #[test]
+    fn should_only_be_dielectric_when_transmissive() {
+        let mut material = Material::default();
+        assert!(!material.is_dielectric());
+
+        material.transmission = Vector3::new(1.0, 1.0, 1.0);
+        assert!(material.is_dielectric());
+    }
+
+    #[test]
+    fn should_see_through_glass_sphere() {
+        // given a clear glass sphere in front of a red one
+        let scene = read_scene("tests/fixtures/glass.test".to_string());
+        assert_eq!(scene.materials.len(), 2);
+        assert_eq!(scene.spheres[1].g_shape.material, 1);
+
+        // when tracing straight through both centers
+        let ray = Ray::new(Vector3::new(0.0, 0.0, 5.0), Vector3::new(0.0, 0.0, -1.0), 10000.0);
+        let radiance = FlatColor.radiance(&ray, &scene, &mut Rng::new(0, 0));
+
+        // then red arrives, minus what both glass boundaries reflect back. Light bouncing
+        // inside the sphere adds the series (1 - r0)^2 * (1 + r0^2 + r0^4 + ...)
+        let r0 = ((1.0 - GLASS) / (1.0 + GLASS)).powi(2);
+        assert_relative_eq!(radiance.x, (1.0 - r0) * (1.0 - r0) / (1.0 - r0 * r0), epsilon = 1e-5);
+        assert_relative_eq!(radiance.y, 0.0);
+    }
+
+    fn flat_point() -> SurfacePoint {
+        let origin = Vector3::new(0.0, 0.0, 0.0);
+        SurfacePoint::new(Vector2::new(0.5, 0.5), origin, origin)
+    }
+
+    fn image(width: usize, texels: Vec<Vector3<Float>>) -> Texture {
+        Texture::Image(ImageTexture::from_texels(width, 1, texels, WrapMode::Clamp))
+    }
+
+    #[test]
+    fn should_bend_normal_with_normal_map() {
+        // given a flat surface with u along x and v along y
+        let textures = vec![
+            image(1, vec![Vector3::new(0.5, 0.5, 1.0)]),
+            image(1, vec![Vector3::new(1.0, 0.5, 0.5)]),
+        ];
+        let mut material = Material { normal_map: Some(0), ..Default::default() };
+
+        // then the flat normal color leaves the normal alone
+        assert_eq!(material.shading_normal(&flat_point(), &textures), Vector3::unit_z());
+
+        // and full red turns it to +u
+        material.normal_map = Some(1);
+        assert_eq!(material.shading_normal(&flat_point(), &textures), Vector3::unit_x());
+
+        // with v running the other way the bitangent follows
+        let textures = vec![image(1, vec![Vector3::new(0.5, 1.0, 0.5)])];
+        material.normal_map = Some(0);
+        let point = SurfacePoint { dpdv: -Vector3::unit_y(), ..flat_point() };
+        assert_eq!(material.shading_normal(&point, &textures), -Vector3::unit_y());
+    }
+
+    #[test]
+    fn should_tilt_normal_down_bump_slope() {
+        // given a height going linearly from 0 to 1 between the texel centers at u = 1/4 and 3/4
+        let textures =
+            vec![image(2, vec![Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 1.0)])];
+        let material = Material { bump_map: Some(0), bump_scale: 0.5, ..Default::default() };
+
+        let n = material.shading_normal(&flat_point(), &textures);
+
+        // then the slope of 2 * 0.5 tilts the normal 45 degrees away from the rise
+        let expected = Vector3::new(-1.0, 0.0, 1.0).normalize();
+        assert_relative_eq!(n.x, expected.x, epsilon = 1e-4);
+        assert_relative_eq!(n.y, expected.y, epsilon = 1e-4);
+        assert_relative_eq!(n.z, expected.z, epsilon = 1e-4);
+
+        // and no bump map keeps the surface normal
+        assert_eq!(Material::default().shading_normal(&flat_point(), &textures), Vector3::unit_z());
+    }
+
+    #[test]
+    fn should_shade_with_normal_map() {
+        // given a quad facing the light with normals tilted 45 degrees
+        let scene = read_scene("tests/fixtures/normal-map.test".to_string());
+        assert!(scene.materials.iter().any(Material::perturbs_normal));
+
+        // when looking at it head on
+        let ray = Ray::new(Vector3::new(0.3, 0.2, 5.0), Vector3::new(0.0, 0.0, -1.0), 10000.0);
+        let radiance = Whitted.radiance(&ray, &scene, &mut Rng::new(0, 0));
+
+        // then it gets the light at 45 degrees
+        assert_relative_eq!(radiance.x, (0.5 as Float).sqrt(), epsilon = 1e-4);
+    }
+}
@@ -0,0 +1,110 @@
+#[cfg(test)]
+mod test;
+
+pub mod media {
+    use cgmath::{Array, ElementWise, InnerSpace, Vector3, Zero};
+
+    use crate::sampling::samplers::Frame;
+    use crate::{float_consts::PI, Float};
+
+    /// Participating medium with the same density everywhere. `sigma_a` absorbs and `sigma_s`
+    /// scatters, per unit of distance and color channel; scattered light leaves in a direction
+    /// picked by the Henyey-Greenstein phase function with asymmetry `g`.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct HomogeneousMedium {
+        pub sigma_a: Vector3<Float>,
+        pub sigma_s: Vector3<Float>,
+        pub g: Float,
+    }
+
+    /// Where a ray crossing a medium interacts with it
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum MediumSample {
+        /// Scatters `distance` along the ray
+        Scatter { distance: Float, weight: Vector3<Float> },
+        /// Makes it through to the end of the segment
+        Pass { weight: Vector3<Float> },
+    }
+
+    impl HomogeneousMedium {
+        pub fn sigma_t(&self) -> Vector3<Float> {
+            self.sigma_a + self.sigma_s
+        }
+
+        pub fn phase(&self) -> HenyeyGreenstein {
+            HenyeyGreenstein { g: self.g }
+        }
+
+        /// Fraction of light surviving `distance` through the medium, Beer-Lambert law. Nothing
+        /// gets through an infinite distance unless the medium is clear.
+        pub fn transmittance(&self, distance: Float) -> Vector3<Float> {
+            self.sigma_t().map(|sigma| if sigma > 0.0 { (-sigma * distance).exp() } else { 1.0 })
+        }
+
+        /// Free flight sampling over a segment `max_distance` long: the distance to the next
+        /// scattering, drawn proportionally to transmittance in a color channel picked at
+        /// random, or passing through. The weight is what the path throughput gets multiplied
+        /// by, transmittance and scattering over the probability of the outcome.
+        pub fn sample(&self, max_distance: Float, (u1, u2): (Float, Float)) -> MediumSample {
+            let sigma_t = self.sigma_t();
+            let channel = ((u1 * 3.0) as usize).min(2);
+            let distance = if sigma_t[channel] > 0.0 {
+                -(1.0 - u2).ln() / sigma_t[channel]
+            } else {
+                Float::INFINITY
+            };
+
+            if distance < max_distance {
+                let transmittance = self.transmittance(distance);
+                let pdf = sigma_t.mul_element_wise(transmittance).sum() / 3.0;
+                let weight = if pdf > 0.0 {
+                    self.sigma_s.mul_element_wise(transmittance) / pdf
+                } else {
+                    Vector3::zero()
+                };
+                MediumSample::Scatter { distance, weight }
+            } else {
+                let transmittance = self.transmittance(max_distance);
+                let pdf = transmittance.sum() / 3.0;
+                let weight = if pdf > 0.0 { transmittance / pdf } else { Vector3::zero() };
+                MediumSample::Pass { weight }
+            }
+        }
+    }
+
+    /// Phase function of a single parameter `g` in (-1, 1): 0 scatters evenly, toward 1 mostly
+    /// forward like fog droplets, toward -1 mostly back
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct HenyeyGreenstein {
+        pub g: Float,
+    }
+
+    impl HenyeyGreenstein {
+        /// Density over the sphere of turning by an angle with cosine `cos_theta` from the
+        /// direction of travel
+        pub fn eval(&self, cos_theta: Float) -> Float {
+            let g = self.g;
+            let denominator = 1.0 + g * g - 2.0 * g * cos_theta;
+            (1.0 - g * g) / (4.0 * PI * denominator * denominator.max(0.0).sqrt())
+        }
+
+        /// New unit direction for light travelling along unit `d`, distributed exactly like
+        /// `eval` so the sample weight is one
+        pub fn sample(&self, d: Vector3<Float>, (u1, u2): (Float, Float)) -> Vector3<Float> {
+            let g = self.g;
+            let cos_theta = if g.abs() < 1e-3 {
+                1.0 - 2.0 * u1
+            } else {
+                let s = (1.0 - g * g) / (1.0 - g + 2.0 * g * u1);
+                ((1.0 + g * g - s * s) / (2.0 * g)).clamp(-1.0, 1.0)
+            };
+            let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+            let phi = 2.0 * PI * u2;
+            Frame::from(d.normalize()).to_world(Vector3::new(
+                sin_theta * phi.cos(),
+                sin_theta * phi.sin(),
+                cos_theta,
+            ))
+        }
+    }
+}
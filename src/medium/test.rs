@@ -0,0 +1,150 @@
+#[cfg(test)]
+mod medium_test {
+
+    use cgmath::{InnerSpace, Vector3};
+
+    use crate::camera::camera_view::Ray;
+    use crate::integrator::integrators::{Integrator, PathTracing, Whitted};
+    use crate::medium::media::{HenyeyGreenstein, HomogeneousMedium, MediumSample};
+    use crate::sampling::samplers::{Rng, Sampler};
+    use crate::{float_consts::PI, read_scene, Float};
+    use approx::assert_relative_eq;
+
+    fn grey(sigma_a: Float, sigma_s: Float) -> HomogeneousMedium {
+        HomogeneousMedium {
+            sigma_a: Vector3::new(sigma_a, sigma_a, sigma_a),
+            sigma_s: Vector3::new(sigma_s, sigma_s, sigma_s),
+            g: 0.0,
+        }
+    }
+
+    #[test]
+    fn should_follow_beer_lambert() {
+        let medium = HomogeneousMedium {
+            sigma_a: Vector3::new(0.5, 0.0, 0.0),
+            sigma_s: Vector3::new(0.5, 1.0, 0.0),
+            g: 0.0,
+        };
+
+        let transmittance = medium.transmittance(2.0);
+        assert_relative_eq!(transmittance.x, (-2.0 as Float).exp());
+        assert_relative_eq!(transmittance.y, (-2.0 as Float).exp());
+        assert_relative_eq!(transmittance.z, 1.0);
+
+        // an infinite path only gets through the clear channel
+        assert_eq!(medium.transmittance(Float::INFINITY), Vector3::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn should_sample_free_flights_without_bias() {
+        // given a colored medium and a segment 1.5 long
+        let medium = HomogeneousMedium {
+            sigma_a: Vector3::new(0.2, 0.5, 1.0),
+            sigma_s: Vector3::new(0.6, 0.3, 0.4),
+            g: 0.0,
+        };
+        let mut rng = Rng::new(1, 0);
+
+        // when averaging the weights of passing and scattering separately
+        let samples = 100000;
+        let (mut passed, mut scattered) = (Vector3::new(0.0, 0.0, 0.0), 0.0);
+        for _ in 0..samples {
+            match medium.sample(1.5, rng.next_2d()) {
+                MediumSample::Pass { weight } => passed += weight,
+                MediumSample::Scatter { distance, weight } => {
+                    assert!(distance < 1.5);
+                    scattered += weight.x;
+                },
+            }
+        }
+
+        // then passing estimates the transmittance
+        let passed = passed / samples as Float;
+        let expected = medium.transmittance(1.5);
+        assert_relative_eq!(passed.x, expected.x, epsilon = 0.01);
+        assert_relative_eq!(passed.y, expected.y, epsilon = 0.01);
+        assert_relative_eq!(passed.z, expected.z, epsilon = 0.01);
+        // and scattering the albedo of what got stopped
+        let stopped = 1.0 - expected.x;
+        assert_relative_eq!(scattered / samples as Float, 0.75 * stopped, epsilon = 0.01);
+    }
+
+    #[test]
+    fn should_normalize_phase_function() {
+        for g in [-0.7, 0.0, 0.3, 0.9] {
+            let phase = HenyeyGreenstein { g };
+            // integrate over cos theta, the azimuth gives 2 pi
+            let steps = 20000;
+            let integral: Float = (0..steps)
+                .map(|i| {
+                    let cos_theta = -1.0 + 2.0 * (i as Float + 0.5) / steps as Float;
+                    phase.eval(cos_theta) * 2.0 * PI * 2.0 / steps as Float
+                })
+                .sum();
+            assert_relative_eq!(integral, 1.0, epsilon = 1e-3);
+        }
+        assert_relative_eq!(HenyeyGreenstein { g: 0.0 }.eval(0.3), 1.0 / (4.0 * PI));
+    }
+
+    #[test]
+    fn should_sample_phase_function_with_mean_cosine_g() {
+        let d = Vector3::new(1.0, 2.0, -0.5).normalize();
+        let mut rng = Rng::new(4, 0);
+
+        for g in [-0.5, 0.0, 0.8] {
+            let phase = HenyeyGreenstein { g };
+            let samples = 50000;
+            let mean: Float =
+                (0..samples).map(|_| phase.sample(d, rng.next_2d()).dot(d)).sum::<Float>()
+                    / samples as Float;
+            assert_relative_eq!(mean, g, epsilon = 0.01);
+        }
+    }
+
+    #[test]
+    fn should_read_fog_and_media() {
+        let scene = read_scene("tests/fixtures/fog.test".to_string());
+
+        assert_eq!(scene.fog, Some(grey(0.1, 0.0)));
+        assert_eq!(scene.media, vec![grey(0.5, 0.0)]);
+        let smoke = scene.materials.iter().find(|it| it.medium.is_some()).unwrap();
+        assert!(smoke.is_medium_boundary());
+    }
+
+    #[test]
+    fn should_dim_light_through_fog_and_smoke() {
+        // given a glowing wall 9 units away through absorbing fog
+        let scene = read_scene("tests/fixtures/fog.test".to_string());
+        let mut rng = Rng::new(5, 0);
+        let average = |integrator: &dyn Integrator, x: Float, rng: &mut Rng| {
+            let ray = Ray::new(Vector3::new(x, 0.0, 5.0), Vector3::new(0.0, 0.0, -1.0), 10000.0);
+            let samples = 20000;
+            (0..samples).map(|_| integrator.radiance(&ray, &scene, rng).x).sum::<Float>()
+                / samples as Float
+        };
+
+        // then both integrators see it dimmed by the fog
+        let fog = (-0.1 * 9.0 as Float).exp();
+        assert_relative_eq!(average(&Whitted, 0.0, &mut rng), fog, epsilon = 0.01);
+        assert_relative_eq!(average(&PathTracing, 0.0, &mut rng), fog, epsilon = 0.01);
+
+        // and the path tracer trades 2 units of fog for smoke through the sphere
+        let smoke = (-0.1 * 7.0 - 0.5 * 2.0 as Float).exp();
+        assert_relative_eq!(average(&PathTracing, 5.0, &mut rng), smoke, epsilon = 0.01);
+    }
+
+    #[test]
+    fn should_conserve_energy_in_scattering_medium() {
+        // given a sphere of smoke that only scatters under a white sky
+        let scene = read_scene("tests/fixtures/medium-furnace.test".to_string());
+        let ray = Ray::new(Vector3::new(0.2, 0.1, 5.0), Vector3::new(0.0, 0.0, -1.0), 10000.0);
+        let mut rng = Rng::new(6, 0);
+
+        // when averaging many paths through it
+        let samples = 20000;
+        let sum: Float = (0..samples).map(|_| PathTracing.radiance(&ray, &scene, &mut rng).x).sum();
+
+        // then all the sky light comes out again
+        assert_relative_eq!(sum / samples as Float, 1.0, epsilon = 0.02);
+    }
+}
@@ -0,0 +1,200 @@
+#[cfg(test)]
+mod test;
+
+pub mod triangle_mesh {
+    use crate::{
+        camera::camera_view::Ray,
+        ray_tracer::tracer::{Color, HitInfo, TestHit},
+        Float,
+    };
+    use cgmath::{InnerSpace, Matrix, Matrix3, Matrix4, SquareMatrix, Vector2, Vector3, Zero};
+
+    // determinants below this are treated as rays parallel to the triangle
+    const PARALLEL_EPSILON: Float = 1e-12;
+
+    /// Indexed triangle soup. Vertex attributes live in shared buffers, a triangle is just three
+    /// consecutive entries of `indices`. `normals` and `uvs` are either empty or have one entry per
+    /// position.
+    #[derive(Debug, Default)]
+    pub struct TriangleMesh {
+        pub positions: Vec<Vector3<Float>>,
+        pub normals: Vec<Vector3<Float>>,
+        pub uvs: Vec<Vector2<Float>>,
+        pub indices: Vec<u32>,
+        /// Scene material of the whole mesh, unless `material_ids` has one per triangle
+        pub material: u32,
+        pub material_ids: Vec<u32>,
+        /// Ignore hits on the side opposite to the normal (counter clockwise winding faces the
+        /// viewer)
+        pub cull_backfaces: bool,
+        /// Edges ab and ac of each triangle, reused by every intersection test. `push_triangle`
+        /// and `transform` keep them up to date, positions used by triangles must not change
+        /// otherwise.
+        pub edges: Vec<[Vector3<Float>; 2]>,
+    }
+
+    /// Lightweight handle to one triangle of one mesh, what acceleration structures store instead
+    /// of the triangle itself
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct TriangleRef {
+        pub mesh: u32,
+        pub triangle: u32,
+    }
+
+    impl TriangleMesh {
+        pub fn push_triangle(&mut self, a: u32, b: u32, c: u32) {
+            self.indices.extend_from_slice(&[a, b, c]);
+            self.edges.push(self.triangle_edges(self.triangle_count() as u32 - 1));
+        }
+
+        fn triangle_edges(&self, triangle: u32) -> [Vector3<Float>; 2] {
+            let [a, b, c] = self.triangle_vertices(triangle);
+            [b - a, c - a]
+        }
+
+        /// Bakes a transform into the vertex buffers. A transform flattening the mesh has no
+        /// matching normal transform, the vertex normals are dropped for the face normals then.
+        pub fn transform(&mut self, transform: Matrix4<Float>) {
+            for p in self.positions.iter_mut() {
+                *p = (transform * p.extend(1.0)).truncate();
+            }
+            self.edges =
+                (0..self.triangle_count() as u32).map(|it| self.triangle_edges(it)).collect();
+
+            let linear = Matrix3::from_cols(
+                transform.x.truncate(),
+                transform.y.truncate(),
+                transform.z.truncate(),
+            );
+            match linear.invert() {
+                Some(inverse) => {
+                    let normal_matrix = inverse.transpose();
+                    for n in self.normals.iter_mut() {
+                        *n = (normal_matrix * *n).normalize();
+                    }
+                },
+                None => self.normals.clear(),
+            }
+        }
+
+        pub fn triangle_material(&self, triangle: u32) -> u32 {
+            self.material_ids.get(triangle as usize).copied().unwrap_or(self.material)
+        }
+
+        pub fn triangle_count(&self) -> usize {
+            self.indices.len() / 3
+        }
+
+        pub fn triangle_indices(&self, triangle: u32) -> [usize; 3] {
+            let first = triangle as usize * 3;
+            [
+                self.indices[first] as usize,
+                self.indices[first + 1] as usize,
+                self.indices[first + 2] as usize,
+            ]
+        }
+
+        pub fn triangle_vertices(&self, triangle: u32) -> [Vector3<Float>; 3] {
+            self.triangle_indices(triangle).map(|i| self.positions[i])
+        }
+
+        /// Axis aligned bounds (min, max) of one triangle
+        pub fn triangle_bounds(&self, triangle: u32) -> (Vector3<Float>, Vector3<Float>) {
+            let [a, b, c] = self.triangle_vertices(triangle);
+            (
+                Vector3::new(a.x.min(b.x).min(c.x), a.y.min(b.y).min(c.y), a.z.min(b.z).min(c.z)),
+                Vector3::new(a.x.max(b.x).max(c.x), a.y.max(b.y).max(c.y), a.z.max(b.z).max(c.z)),
+            )
+        }
+
+        /// References to every triangle, tagged with the index this mesh has in the scene
+        pub fn triangle_refs(&self, mesh: u32) -> impl Iterator<Item = TriangleRef> {
+            (0..self.triangle_count() as u32).map(move |triangle| TriangleRef { mesh, triangle })
+        }
+
+        /// Möller–Trumbore ray triangle intersection. When the mesh has vertex normals the hit
+        /// normal is interpolated from them, otherwise the face normal is used.
+        pub fn intersection(&self, triangle: u32, ray: &Ray) -> TestHit {
+            let indices = self.triangle_indices(triangle);
+            let a = self.positions[indices[0]];
+            let [edge_ab, edge_ac] = self.edges[triangle as usize];
+
+            let p = ray.d.cross(edge_ac);
+            let det = edge_ab.dot(p);
+
+            // det < 0 means the ray comes from behind the triangle
+            if (self.cull_backfaces && det < PARALLEL_EPSILON) || det.abs() < PARALLEL_EPSILON {
+                return TestHit::NoHit;
+            }
+            let inv_det = 1.0 / det;
+
+            // barycentric coordinates
+            let v_ao = ray.o - a;
+            let beta = v_ao.dot(p) * inv_det;
+            if !(0.0..=1.0).contains(&beta) {
+                return TestHit::NoHit;
+            }
+
+            let q = v_ao.cross(edge_ab);
+            let gamma = ray.d.dot(q) * inv_det;
+            if gamma < 0.0 || beta + gamma > 1.0 {
+                return TestHit::NoHit;
+            }
+
+            let t_value = edge_ac.dot(q) * inv_det;
+            if t_value < 0.0 {
+                return TestHit::NoHit;
+            }
+
+            let normal = if self.normals.is_empty() {
+                edge_ab.cross(edge_ac).normalize()
+            } else {
+                (self.normals[indices[0]] * (1.0 - beta - gamma)
+                    + self.normals[indices[1]] * beta
+                    + self.normals[indices[2]] * gamma)
+                    .normalize()
+            };
+
+            // without texture coordinates the barycentrics stand in for them
+            let corner_uvs = if self.uvs.is_empty() {
+                [Vector2::new(0.0, 0.0), Vector2::new(1.0, 0.0), Vector2::new(0.0, 1.0)]
+            } else {
+                indices.map(|it| self.uvs[it])
+            };
+            let uv =
+                corner_uvs[0] * (1.0 - beta - gamma) + corner_uvs[1] * beta + corner_uvs[2] * gamma;
+            let (dpdu, dpdv) = uv_derivatives(edge_ab, edge_ac, corner_uvs);
+
+            // its a hit
+            TestHit::Hit(
+                HitInfo::from(
+                    t_value,
+                    ray.o + t_value * ray.d,
+                    normal,
+                    *ray,
+                    Color { r: 0, g: 255, b: 255 },
+                    self.triangle_material(triangle),
+                )
+                .with_uv(uv)
+                .with_tangents(dpdu, dpdv),
+            )
+        }
+    }
+
+    /// Derivatives of the position along u and v over a triangle, from two of its edges and
+    /// its corner uvs. Zero when the uvs don't span an area.
+    fn uv_derivatives(
+        edge_ab: Vector3<Float>,
+        edge_ac: Vector3<Float>,
+        [uv_a, uv_b, uv_c]: [Vector2<Float>; 3],
+    ) -> (Vector3<Float>, Vector3<Float>) {
+        let (duv_ab, duv_ac) = (uv_b - uv_a, uv_c - uv_a);
+        let det = duv_ab.x * duv_ac.y - duv_ab.y * duv_ac.x;
+        if det.abs() < 1e-12 {
+            return (Vector3::zero(), Vector3::zero());
+        }
+        let dpdu = (edge_ab * duv_ac.y - edge_ac * duv_ab.y) / det;
+        let dpdv = (edge_ac * duv_ab.x - edge_ab * duv_ac.x) / det;
+        (dpdu, dpdv)
+    }
+}
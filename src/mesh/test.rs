@@ -0,0 +1,271 @@
+#[cfg(test)]
+mod mesh_test {
+
+    use std::mem::size_of;
+    use std::time::Instant;
+
+    use cgmath::{InnerSpace, Matrix4, Vector2, Vector3};
+
+    use crate::camera::camera_view::Ray;
+    use crate::mesh::triangle_mesh::{TriangleMesh, TriangleRef};
+    use crate::ray_tracer::tracer::TestHit;
+    use crate::{read_scene, Float};
+    use approx::assert_relative_eq;
+    use log::info;
+
+    // both algorithms round differently, single precision leaves far fewer digits in common
+    const T_TOLERANCE: Float = if cfg!(feature = "f32") { 1e-3 } else { 1e-9 };
+
+    /// Plane intersection + barycentric test triangles used before Möller–Trumbore, kept as the
+    /// reference the mesh intersection is checked against
+    fn reference_intersection(mesh: &TriangleMesh, triangle: u32, ray: &Ray) -> Option<Float> {
+        let [a, b, c] = mesh.triangle_vertices(triangle);
+        let v_ab = b - a;
+        let v_ac = c - a;
+        let norm = v_ab.cross(v_ac).normalize();
+
+        // rays lying on the triangle plane used to leak through as NaN hits, count them as misses
+        let t_value = norm.dot(a - ray.o) / norm.dot(ray.d);
+        if !t_value.is_finite() {
+            return None;
+        }
+        let v_aq = ray.o + t_value * ray.d - a;
+        let dab_ab = v_ab.dot(v_ab);
+        let dab_ac = v_ab.dot(v_ac);
+        let dac_ac = v_ac.dot(v_ac);
+        let daq_ab = v_aq.dot(v_ab);
+        let daq_ac = v_aq.dot(v_ac);
+
+        let d = dab_ab * dac_ac - dab_ac * dab_ac;
+        if d == 0.0 {
+            return None;
+        }
+
+        let beta = (dac_ac * daq_ab - dab_ac * daq_ac) / d;
+        let gamma = (dab_ab * daq_ac - dab_ac * daq_ab) / d;
+        if beta < 0.0 || gamma < 0.0 || beta + gamma > 1.0 || t_value < 0.0 {
+            return None;
+        }
+        Some(t_value)
+    }
+
+    /// Rays from the scene camera towards every triangle: one through the centroid and one past
+    /// two of its vertices, so both hits and misses get compared
+    fn rays_at_scene_triangles(scene_file: &str) -> Vec<(TriangleRef, Ray)> {
+        let scene = read_scene(scene_file.to_string());
+        let eye = scene.cams.first().unwrap().look_from;
+        let mut rays = vec![];
+
+        for it in &scene.triangle_refs {
+            let [a, b, c] = scene.meshes[it.mesh as usize].triangle_vertices(it.triangle);
+            if (b - a).cross(c - a).magnitude() < 1e-12 {
+                continue; // degenerate, no meaningful reference
+            }
+            let centroid = (a + b + c) / 3.0;
+            for target in [centroid, a + (a - centroid) * 0.5, b + (b - centroid) * 0.5] {
+                rays.push((*it, Ray::new(eye, (target - eye).normalize(), 10000.0)));
+            }
+        }
+        rays
+    }
+
+    fn assert_matches_reference(scene_file: &str) {
+        let scene = read_scene(scene_file.to_string());
+        let rays = rays_at_scene_triangles(scene_file);
+        assert!(!rays.is_empty());
+
+        for (it, ray) in rays {
+            let mesh = &scene.meshes[it.mesh as usize];
+            match (
+                mesh.intersection(it.triangle, &ray),
+                reference_intersection(mesh, it.triangle, &ray),
+            ) {
+                (TestHit::Hit(hit), Some(t_value)) => {
+                    assert_relative_eq!(hit.t_value(), t_value, max_relative = T_TOLERANCE)
+                },
+                (TestHit::NoHit, None) => {},
+                (hit, reference) => {
+                    panic!("Triangle {:?} disagrees: {:?} vs {:?}", it, hit, reference)
+                },
+            }
+        }
+    }
+
+    fn unit_triangle_mesh() -> TriangleMesh {
+        let mut mesh = TriangleMesh {
+            positions: vec![
+                Vector3::new(0.0, 0.0, 0.0),
+                Vector3::new(1.0, 0.0, 0.0),
+                Vector3::new(0.0, 1.0, 0.0),
+            ],
+            ..Default::default()
+        };
+        mesh.push_triangle(0, 1, 2);
+        mesh
+    }
+
+    #[test]
+    fn should_intersect_triangle() {
+        let mesh = unit_triangle_mesh();
+        let ray = Ray::new(Vector3::new(0.25, 0.25, 2.0), Vector3::new(0.0, 0.0, -1.0), 10000.0);
+
+        let TestHit::Hit(hit) = mesh.intersection(0, &ray) else {
+            panic!("Should hit");
+        };
+        assert_relative_eq!(hit.t_value(), 2.0);
+        assert_relative_eq!((hit.point() - Vector3::new(0.25, 0.25, 0.0)).magnitude(), 0.0);
+        assert_relative_eq!(hit.normal().z, 1.0);
+    }
+
+    #[test]
+    fn should_not_intersect_parallel_or_outside_triangle() {
+        let mesh = unit_triangle_mesh();
+        let parallel =
+            Ray::new(Vector3::new(0.25, 0.25, 2.0), Vector3::new(1.0, 0.0, 0.0), 10000.0);
+        let outside =
+            Ray::new(Vector3::new(0.75, 0.75, 2.0), Vector3::new(0.0, 0.0, -1.0), 10000.0);
+        let behind = Ray::new(Vector3::new(0.25, 0.25, 2.0), Vector3::new(0.0, 0.0, 1.0), 10000.0);
+
+        for ray in [parallel, outside, behind] {
+            if let TestHit::Hit(_) = mesh.intersection(0, &ray) {
+                panic!("Should not hit {:?}", ray);
+            }
+        }
+    }
+
+    #[test]
+    fn should_cull_backfaces_only_when_enabled() {
+        let mut mesh = unit_triangle_mesh();
+        // looking at the back side, the normal points to +z
+        let ray = Ray::new(Vector3::new(0.25, 0.25, -2.0), Vector3::new(0.0, 0.0, 1.0), 10000.0);
+
+        assert!(matches!(mesh.intersection(0, &ray), TestHit::Hit(_)));
+        mesh.cull_backfaces = true;
+        assert!(matches!(mesh.intersection(0, &ray), TestHit::NoHit));
+    }
+
+    #[test]
+    fn should_interpolate_vertex_normals() {
+        let mut mesh = unit_triangle_mesh();
+        let tilted = Vector3::new(1.0, 0.0, 1.0).normalize();
+        mesh.normals = vec![Vector3::unit_z(), tilted, tilted];
+        let ray = Ray::new(Vector3::new(0.0, 0.0, 2.0), Vector3::new(0.0, 0.0, -1.0), 10000.0);
+
+        let TestHit::Hit(hit) = mesh.intersection(0, &ray) else {
+            panic!("Should hit");
+        };
+        // hit right on the first vertex
+        assert_relative_eq!(hit.normal().dot(Vector3::unit_z()), 1.0);
+    }
+
+    #[test]
+    fn should_drop_normals_under_flattening_transform() {
+        // given a triangle with vertex normals
+        let mut mesh = unit_triangle_mesh();
+        mesh.normals = vec![Vector3::unit_z(); 3];
+
+        // when a transform squashes it onto a line
+        mesh.transform(Matrix4::from_nonuniform_scale(0.0, 1.0, 1.0));
+
+        // then it has no normals left to transform and rays miss it
+        assert!(mesh.normals.is_empty());
+        assert_eq!(mesh.triangle_vertices(0)[1], Vector3::new(0.0, 0.0, 0.0));
+        let ray = Ray::new(Vector3::new(0.0, 0.25, 2.0), Vector3::new(0.0, 0.0, -1.0), 10000.0);
+        assert!(matches!(mesh.intersection(0, &ray), TestHit::NoHit));
+    }
+
+    #[test]
+    fn should_interpolate_uvs() {
+        let mut mesh = unit_triangle_mesh();
+        let ray = Ray::new(Vector3::new(0.25, 0.5, 2.0), Vector3::new(0.0, 0.0, -1.0), 10000.0);
+        let uv = |mesh: &TriangleMesh| match mesh.intersection(0, &ray) {
+            TestHit::Hit(hit) => hit.uv(),
+            TestHit::NoHit => panic!("Should hit"),
+        };
+
+        // without uvs the barycentrics of the second and third vertices
+        assert_relative_eq!(uv(&mesh).x, 0.25);
+        assert_relative_eq!(uv(&mesh).y, 0.5);
+
+        // with them, the blend of the vertex uvs
+        mesh.uvs = vec![Vector2::new(0.5, 0.5), Vector2::new(1.0, 0.5), Vector2::new(0.5, 0.0)];
+        assert_relative_eq!(uv(&mesh).x, 0.625);
+        assert_relative_eq!(uv(&mesh).y, 0.25);
+    }
+
+    #[test]
+    fn should_derive_tangents_from_uvs() {
+        let mut mesh = unit_triangle_mesh();
+        let ray = Ray::new(Vector3::new(0.25, 0.25, 2.0), Vector3::new(0.0, 0.0, -1.0), 10000.0);
+        let tangents = |mesh: &TriangleMesh| match mesh.intersection(0, &ray) {
+            TestHit::Hit(hit) => (hit.dpdu(), hit.dpdv()),
+            TestHit::NoHit => panic!("Should hit"),
+        };
+
+        // without uvs u and v follow the two edges
+        assert_eq!(tangents(&mesh), (Vector3::unit_x(), Vector3::unit_y()));
+
+        // u doubles speed along x, v runs backwards along y
+        mesh.uvs = vec![Vector2::new(0.5, 0.5), Vector2::new(1.0, 0.5), Vector2::new(0.5, 0.0)];
+        assert_eq!(tangents(&mesh), (Vector3::new(2.0, 0.0, 0.0), Vector3::new(0.0, -2.0, 0.0)));
+
+        // and a triangle squashed in uv space has none
+        mesh.uvs = vec![Vector2::new(0.0, 0.0), Vector2::new(0.5, 0.5), Vector2::new(1.0, 1.0)];
+        assert_eq!(tangents(&mesh), (Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn should_keep_triangles_compact() {
+        let scene = read_scene("src/scene3.test".to_string());
+        let mesh = &scene.meshes[0];
+
+        assert_eq!(size_of::<TriangleRef>(), 8);
+        assert_eq!(scene.triangle_refs.len(), 60);
+        assert_eq!(mesh.triangle_count(), 60);
+        assert_eq!(mesh.positions.len(), 8);
+        assert_eq!(mesh.triangle_refs(0).count(), scene.triangle_refs.len());
+    }
+
+    // scene5 is only spheres, the meshes live in scene3, scene6 and scene7. The old algorithm is
+    // too fragile in single precision to serve as reference there.
+    #[test]
+    #[cfg_attr(feature = "f32", ignore)]
+    fn should_match_reference_on_scene_meshes() {
+        assert_matches_reference("src/scene3.test");
+        assert_matches_reference("src/scene6.test");
+        assert_matches_reference("src/scene7.test");
+    }
+
+    /// cargo test --release bench_triangle_intersection -- --ignored
+    #[test]
+    #[ignore]
+    fn bench_triangle_intersection() {
+        log4rs::init_file("src/log4rs.yml", Default::default()).unwrap();
+        let scene = read_scene("src/scene7.test".to_string());
+        let mesh = &scene.meshes[0];
+        let rays = rays_at_scene_triangles("src/scene7.test");
+        let triangles = mesh.triangle_count().min(1000) as u32;
+        let tests = (rays.len() * triangles as usize) as f64;
+
+        let now = Instant::now();
+        let mut hits = 0;
+        for (_, ray) in &rays {
+            for triangle in 0..triangles {
+                hits += reference_intersection(mesh, triangle, ray).is_some() as usize;
+            }
+        }
+        let reference_ns = now.elapsed().as_nanos() as f64 / tests;
+
+        let now = Instant::now();
+        let mut mt_hits = 0;
+        for (_, ray) in &rays {
+            for triangle in 0..triangles {
+                mt_hits += matches!(mesh.intersection(triangle, ray), TestHit::Hit(_)) as usize;
+            }
+        }
+        let mt_ns = now.elapsed().as_nanos() as f64 / tests;
+
+        info!("reference {:.2} ns/test, möller-trumbore {:.2} ns/test", reference_ns, mt_ns);
+        assert_eq!(hits, mt_hits);
+    }
+}
@@ -0,0 +1,363 @@
+#[cfg(test)]
+mod test;
+
+pub mod mesh_loaders {
+    use std::{
+        collections::HashMap,
+        fs,
+        io::{self, Error, ErrorKind},
+        path::Path,
+    };
+
+    use cgmath::{Vector2, Vector3};
+
+    use crate::{mesh::triangle_mesh::TriangleMesh, Float};
+
+    fn invalid(msg: String) -> Error {
+        Error::new(ErrorKind::InvalidData, msg)
+    }
+
+    pub fn load_ply(path: &Path) -> io::Result<TriangleMesh> {
+        parse_ply(&fs::read(path)?)
+    }
+
+    pub fn load_stl(path: &Path) -> io::Result<TriangleMesh> {
+        parse_stl(&fs::read(path)?)
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum PlyFormat {
+        Ascii,
+        BinaryLittleEndian,
+        BinaryBigEndian,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum PlyType {
+        Int8,
+        UInt8,
+        Int16,
+        UInt16,
+        Int32,
+        UInt32,
+        Float32,
+        Float64,
+    }
+
+    impl PlyType {
+        fn parse(name: &str) -> io::Result<PlyType> {
+            match name {
+                "char" | "int8" => Ok(PlyType::Int8),
+                "uchar" | "uint8" => Ok(PlyType::UInt8),
+                "short" | "int16" => Ok(PlyType::Int16),
+                "ushort" | "uint16" => Ok(PlyType::UInt16),
+                "int" | "int32" => Ok(PlyType::Int32),
+                "uint" | "uint32" => Ok(PlyType::UInt32),
+                "float" | "float32" => Ok(PlyType::Float32),
+                "double" | "float64" => Ok(PlyType::Float64),
+                _ => Err(invalid(format!("Unknown ply type {}", name))),
+            }
+        }
+
+        fn size(&self) -> usize {
+            match self {
+                PlyType::Int8 | PlyType::UInt8 => 1,
+                PlyType::Int16 | PlyType::UInt16 => 2,
+                PlyType::Int32 | PlyType::UInt32 | PlyType::Float32 => 4,
+                PlyType::Float64 => 8,
+            }
+        }
+    }
+
+    #[derive(Debug)]
+    enum PlyProperty {
+        Scalar { name: String, ty: PlyType },
+        List { name: String, count_ty: PlyType, item_ty: PlyType },
+    }
+
+    #[derive(Debug)]
+    struct PlyElement {
+        name: String,
+        count: usize,
+        properties: Vec<PlyProperty>,
+    }
+
+    /// Reads the values following the header, one at a time, whatever the encoding
+    struct PlyValues<'a> {
+        format: PlyFormat,
+        body: &'a [u8],
+        offset: usize,
+        tokens: std::str::SplitAsciiWhitespace<'a>,
+    }
+
+    impl<'a> PlyValues<'a> {
+        fn new(format: PlyFormat, body: &'a [u8]) -> io::Result<PlyValues<'a>> {
+            let text = if format == PlyFormat::Ascii {
+                std::str::from_utf8(body).map_err(|e| invalid(e.to_string()))?
+            } else {
+                ""
+            };
+            Ok(PlyValues { format, body, offset: 0, tokens: text.split_ascii_whitespace() })
+        }
+
+        fn next(&mut self, ty: PlyType) -> io::Result<f64> {
+            if self.format == PlyFormat::Ascii {
+                let token = self.tokens.next().ok_or_else(|| invalid("Truncated ply".into()))?;
+                return token.parse::<f64>().map_err(|e| invalid(format!("{}: {}", token, e)));
+            }
+
+            let size = ty.size();
+            let bytes = self
+                .body
+                .get(self.offset..self.offset + size)
+                .ok_or_else(|| invalid("Truncated ply".into()))?;
+            self.offset += size;
+
+            let mut raw = [0u8; 8];
+            raw[..size].copy_from_slice(bytes);
+            if self.format == PlyFormat::BinaryBigEndian {
+                raw[..size].reverse();
+            }
+
+            // raw is little endian from here on
+            Ok(match ty {
+                PlyType::Int8 => raw[0] as i8 as f64,
+                PlyType::UInt8 => raw[0] as f64,
+                PlyType::Int16 => i16::from_le_bytes([raw[0], raw[1]]) as f64,
+                PlyType::UInt16 => u16::from_le_bytes([raw[0], raw[1]]) as f64,
+                PlyType::Int32 => i32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]) as f64,
+                PlyType::UInt32 => u32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]) as f64,
+                PlyType::Float32 => f32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]) as f64,
+                PlyType::Float64 => f64::from_le_bytes(raw),
+            })
+        }
+    }
+
+    /// Splits the header from the body, returning the format, the declared elements and where
+    /// the body starts
+    fn parse_ply_header(bytes: &[u8]) -> io::Result<(PlyFormat, Vec<PlyElement>, usize)> {
+        // only a whole end_header line ends the header, comments may mention it. The body
+        // starts after its line break (\n or \r\n).
+        let mut line_start = 0;
+        let mut header_end = None;
+        for line in bytes.split(|b| *b == b'\n') {
+            let next_line = (line_start + line.len() + 1).min(bytes.len());
+            if line.trim_ascii() == b"end_header" {
+                header_end = Some((line_start, next_line));
+                break;
+            }
+            line_start = next_line;
+        }
+        let (end, body_start) =
+            header_end.ok_or_else(|| invalid("Missing ply end_header".into()))?;
+
+        let header = std::str::from_utf8(&bytes[..end]).map_err(|e| invalid(e.to_string()))?;
+        let mut lines = header.lines();
+        if lines.next().map(str::trim) != Some("ply") {
+            return Err(invalid("Not a ply file".into()));
+        }
+
+        let mut format = None;
+        let mut elements: Vec<PlyElement> = vec![];
+        for line in lines {
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words.as_slice() {
+                ["format", "ascii", ..] => format = Some(PlyFormat::Ascii),
+                ["format", "binary_little_endian", ..] => {
+                    format = Some(PlyFormat::BinaryLittleEndian)
+                },
+                ["format", "binary_big_endian", ..] => format = Some(PlyFormat::BinaryBigEndian),
+                ["element", name, count] => elements.push(PlyElement {
+                    name: name.to_string(),
+                    count: count.parse().map_err(|_| invalid(format!("Bad count {}", line)))?,
+                    properties: vec![],
+                }),
+                ["property", "list", count_ty, item_ty, name] => elements
+                    .last_mut()
+                    .ok_or_else(|| invalid(format!("Property outside element: {}", line)))?
+                    .properties
+                    .push(PlyProperty::List {
+                        name: name.to_string(),
+                        count_ty: PlyType::parse(count_ty)?,
+                        item_ty: PlyType::parse(item_ty)?,
+                    }),
+                ["property", ty, name] => elements
+                    .last_mut()
+                    .ok_or_else(|| invalid(format!("Property outside element: {}", line)))?
+                    .properties
+                    .push(PlyProperty::Scalar { name: name.to_string(), ty: PlyType::parse(ty)? }),
+                // comment, obj_info and blank lines
+                _ => {},
+            }
+        }
+
+        let format = format.ok_or_else(|| invalid("Missing ply format".into()))?;
+        Ok((format, elements, body_start))
+    }
+
+    /// Reads the `vertex` and `face` elements of a ply file, ascii or binary. Positions are
+    /// required, normals (nx ny nz) and texture coordinates (u v or s t) are kept when present,
+    /// other properties like colors are skipped. Polygons are split into triangle fans.
+    pub fn parse_ply(bytes: &[u8]) -> io::Result<TriangleMesh> {
+        let (format, elements, body_start) = parse_ply_header(bytes)?;
+        let mut values = PlyValues::new(format, &bytes[body_start..])?;
+        let mut mesh = TriangleMesh::default();
+
+        for element in &elements {
+            let position = |name: &str| {
+                element.properties.iter().position(|p| match p {
+                    PlyProperty::Scalar { name: n, .. } | PlyProperty::List { name: n, .. } => {
+                        n == name
+                    },
+                })
+            };
+            let all = |names: [&str; 3]| {
+                let found = names.map(position);
+                found.iter().all(Option::is_some).then(|| found.map(Option::unwrap))
+            };
+            let xyz = all(["x", "y", "z"]);
+            let normals = all(["nx", "ny", "nz"]);
+            let uvs = position("u").zip(position("v")).or_else(|| position("s").zip(position("t")));
+
+            let mut row: Vec<f64> = vec![0.0; element.properties.len()];
+            let mut face: Vec<f64> = vec![];
+            for _ in 0..element.count {
+                face.clear();
+                for (i, property) in element.properties.iter().enumerate() {
+                    match property {
+                        PlyProperty::Scalar { ty, .. } => row[i] = values.next(*ty)?,
+                        PlyProperty::List { name, count_ty, item_ty } => {
+                            let count = values.next(*count_ty)? as usize;
+                            for _ in 0..count {
+                                let item = values.next(*item_ty)?;
+                                if name == "vertex_indices" || name == "vertex_index" {
+                                    face.push(item);
+                                }
+                            }
+                        },
+                    }
+                }
+
+                let vector = |[x, y, z]: [usize; 3]| {
+                    Vector3::new(row[x] as Float, row[y] as Float, row[z] as Float)
+                };
+                match element.name.as_str() {
+                    "vertex" => {
+                        let xyz = xyz.ok_or_else(|| invalid("Vertex without x y z".into()))?;
+                        mesh.positions.push(vector(xyz));
+                        if let Some(normals) = normals {
+                            mesh.normals.push(vector(normals));
+                        }
+                        if let Some((u, v)) = uvs {
+                            mesh.uvs.push(Vector2::new(row[u] as Float, row[v] as Float));
+                        }
+                    },
+                    "face" => {
+                        if face.len() < 3 {
+                            return Err(invalid(format!("Face with {} vertices", face.len())));
+                        }
+                        // faces come after the vertices they use
+                        let vertices = mesh.positions.len() as f64;
+                        if let Some(i) = face.iter().find(|i| !(0.0..vertices).contains(*i)) {
+                            return Err(invalid(format!("Vertex index {} out of range", i)));
+                        }
+                        for i in 1..face.len() - 1 {
+                            mesh.push_triangle(face[0] as u32, face[i] as u32, face[i + 1] as u32);
+                        }
+                    },
+                    // other elements (edges, materials...) are read and dropped
+                    _ => {},
+                }
+            }
+        }
+
+        validate(&mesh)?;
+        Ok(mesh)
+    }
+
+    /// Reads binary or ascii stl. Stl stores every triangle with its own copy of the vertices, so
+    /// identical positions are welded back together to get a watertight indexed mesh.
+    pub fn parse_stl(bytes: &[u8]) -> io::Result<TriangleMesh> {
+        let mut welder = VertexWelder::default();
+
+        // ascii files also start with "solid", but their size never matches the binary layout
+        let binary_size = bytes
+            .get(80..84)
+            .map(|c| 84 + 50 * u32::from_le_bytes([c[0], c[1], c[2], c[3]]) as usize);
+        if binary_size == Some(bytes.len()) {
+            let count = (bytes.len() - 84) / 50;
+            for i in 0..count {
+                // 12 bytes of normal, 3 vertices of 12 bytes, 2 bytes of attributes
+                let triangle = &bytes[84 + i * 50..84 + (i + 1) * 50];
+                let float = |at: usize| {
+                    f32::from_le_bytes([
+                        triangle[at],
+                        triangle[at + 1],
+                        triangle[at + 2],
+                        triangle[at + 3],
+                    ])
+                };
+                let vertex = |at: usize| [float(at), float(at + 4), float(at + 8)];
+                welder.push_triangle([vertex(12), vertex(24), vertex(36)]);
+            }
+        } else {
+            let text = std::str::from_utf8(bytes).map_err(|_| invalid("Not an stl file".into()))?;
+            if !text.trim_start().starts_with("solid") {
+                return Err(invalid("Not an stl file".into()));
+            }
+
+            let mut corners: Vec<[f32; 3]> = vec![];
+            for line in text.lines() {
+                let words: Vec<&str> = line.split_whitespace().collect();
+                if let ["vertex", x, y, z] = words.as_slice() {
+                    let parse =
+                        |v: &str| v.parse::<f32>().map_err(|e| invalid(format!("{}: {}", v, e)));
+                    corners.push([parse(x)?, parse(y)?, parse(z)?]);
+                }
+                if words.first() == Some(&"endfacet") {
+                    if corners.len() != 3 {
+                        return Err(invalid(format!("Facet with {} vertices", corners.len())));
+                    }
+                    welder.push_triangle([corners[0], corners[1], corners[2]]);
+                    corners.clear();
+                }
+            }
+        }
+
+        let mesh = welder.mesh;
+        validate(&mesh)?;
+        Ok(mesh)
+    }
+
+    /// Builds an indexed mesh out of loose triangles, sharing bitwise identical positions
+    #[derive(Default)]
+    struct VertexWelder {
+        mesh: TriangleMesh,
+        indices: HashMap<[u32; 3], u32>,
+    }
+
+    impl VertexWelder {
+        fn push_triangle(&mut self, corners: [[f32; 3]; 3]) {
+            let [a, b, c] = corners.map(|corner| {
+                // +0.0 and -0.0 are the same point
+                let key = corner.map(|v| if v == 0.0 { 0 } else { v.to_bits() });
+                *self.indices.entry(key).or_insert_with(|| {
+                    self.mesh.positions.push(Vector3::new(
+                        corner[0] as Float,
+                        corner[1] as Float,
+                        corner[2] as Float,
+                    ));
+                    self.mesh.positions.len() as u32 - 1
+                })
+            });
+            self.mesh.push_triangle(a, b, c);
+        }
+    }
+
+    fn validate(mesh: &TriangleMesh) -> io::Result<()> {
+        match mesh.indices.iter().find(|i| **i as usize >= mesh.positions.len()) {
+            Some(i) => Err(invalid(format!("Vertex index {} out of range", i))),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,125 @@
+#[cfg(test)]
+mod mesh_io_test {
+
+    use std::path::{Path, PathBuf};
+
+    use cgmath::{InnerSpace, Vector3};
+
+    use crate::camera::camera_view::Ray;
+    use crate::mesh::triangle_mesh::TriangleMesh;
+    use crate::mesh_io::mesh_loaders::{load_ply, load_stl, parse_ply, parse_stl};
+    use crate::ray_tracer::tracer::TestHit;
+    use crate::read_scene;
+    use approx::assert_relative_eq;
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new("tests/fixtures").join(name)
+    }
+
+    fn assert_tetrahedron(mesh: &TriangleMesh) {
+        assert_eq!(mesh.positions.len(), 4);
+        assert_eq!(mesh.triangle_count(), 4);
+        assert_eq!(
+            mesh.triangle_vertices(3),
+            [Vector3::new(1.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0), Vector3::new(0.0, 0.0, 1.0)]
+        );
+    }
+
+    fn assert_quad(mesh: &TriangleMesh) {
+        assert_eq!(mesh.positions.len(), 4);
+        assert_eq!(mesh.indices, vec![0, 1, 2, 0, 2, 3]);
+        assert_eq!(mesh.positions[2], Vector3::new(1.0, 1.0, 0.0));
+        assert!(mesh.normals.is_empty());
+    }
+
+    #[test]
+    fn should_load_ascii_ply_with_normals() {
+        let mesh = load_ply(&fixture("tetra_ascii.ply")).unwrap();
+
+        assert_tetrahedron(&mesh);
+        assert_eq!(mesh.normals.len(), 4);
+        assert_relative_eq!(mesh.normals[1].magnitude(), 1.0, epsilon = 1e-5);
+    }
+
+    #[test]
+    fn should_load_binary_ply_in_both_endians() {
+        assert_quad(&load_ply(&fixture("quad_le.ply")).unwrap());
+        assert_quad(&load_ply(&fixture("quad_be.ply")).unwrap());
+    }
+
+    #[test]
+    fn should_weld_stl_vertices() {
+        // every stl facet repeats its corners, welding brings 12 corners back to 4 vertices
+        assert_tetrahedron(&load_stl(&fixture("tetra.stl")).unwrap());
+        assert_tetrahedron(&load_stl(&fixture("tetra_ascii.stl")).unwrap());
+    }
+
+    #[test]
+    fn should_reject_broken_files() {
+        let truncated = std::fs::read(fixture("quad_le.ply")).unwrap();
+        assert!(parse_ply(&truncated[..truncated.len() - 10]).is_err());
+        assert!(parse_ply(b"ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\n").is_err());
+        assert!(parse_ply(
+            b"ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\nproperty float y\nproperty \
+              float z\nelement face 1\nproperty list uchar int vertex_indices\nend_header\n0 0 0\n3 0 \
+              1 2\n"
+        )
+        .is_err());
+        assert!(parse_stl(b"not a mesh").is_err());
+    }
+
+    #[test]
+    fn should_reject_face_indices_out_of_range() {
+        // given three vertices and faces pointing before and past them
+        let header = "ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\n\
+                      property float y\nproperty float z\nelement face 1\n\
+                      property list uchar int vertex_indices\nend_header\n0 0 0\n1 0 0\n0 1 0\n";
+
+        // then neither is read as another vertex
+        for (face, index) in [("3 0 -1 2", "-1"), ("3 0 1 3", "3")] {
+            let error = parse_ply(format!("{}{}\n", header, face).as_bytes()).unwrap_err();
+            assert_eq!(error.to_string(), format!("Vertex index {} out of range", index));
+        }
+    }
+
+    #[test]
+    fn should_end_ply_header_on_its_own_line_only() {
+        // given a header whose comment and property names mention end_header
+        let ply = b"ply\nformat ascii 1.0\ncomment written before end_header\nelement vertex 3\n\
+                    property float x\nproperty float y\nproperty float z\n\
+                    property float end_header_weight\nelement face 1\n\
+                    property list uchar int vertex_indices\nend_header\r\n\
+                    0 0 0 1\n1 0 0 1\n0 1 0 1\n3 0 1 2\n";
+
+        // then the header runs up to the end_header line
+        let mesh = parse_ply(ply).unwrap();
+        assert_eq!(mesh.positions.len(), 3);
+        assert_eq!(mesh.triangle_count(), 1);
+    }
+
+    #[test]
+    fn should_load_meshes_from_scene_commands() {
+        let scene = read_scene("tests/fixtures/meshes.test".to_string());
+
+        // inline triangles stay in the first mesh, each file gets its own
+        assert_eq!(scene.meshes.len(), 3);
+        assert_eq!(scene.triangle_refs.len(), 1 + 4 + 4);
+
+        // the stl was loaded under a translation
+        let stl = &scene.meshes[2];
+        assert_eq!(stl.positions[0], Vector3::new(3.0, 0.0, 0.0));
+
+        let ray = Ray::new(Vector3::new(3.1, 0.1, 5.0), Vector3::new(0.0, 0.0, -1.0), 10000.0);
+        let hits = scene
+            .triangle_refs
+            .iter()
+            .filter(|it| {
+                matches!(
+                    scene.meshes[it.mesh as usize].intersection(it.triangle, &ray),
+                    TestHit::Hit(_)
+                )
+            })
+            .count();
+        assert_eq!(hits, 2);
+    }
+}
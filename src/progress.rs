@@ -0,0 +1,120 @@
+#[cfg(test)]
+mod test;
+
+pub mod reporting {
+    use std::cell::Cell;
+    use std::io::{self, IsTerminal, Write};
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Mutex;
+    use std::time::{Duration, Instant};
+
+    use crate::Float;
+
+    // rays traced by the current thread and not reported yet
+    thread_local! {
+        static RAYS: Cell<u64> = const { Cell::new(0) };
+    }
+
+    /// Counts a ray toward the throughput the progress bar shows
+    pub fn count_ray() {
+        RAYS.with(|it| it.set(it.get() + 1));
+    }
+
+    /// Counts rays traced elsewhere, by worker processes, as if the current thread traced them
+    pub fn count_rays(rays: u64) {
+        RAYS.with(|it| it.set(it.get() + rays));
+    }
+
+    /// Takes the rays the current thread traced and didn't report yet, for workers to send
+    pub fn take_rays() -> u64 {
+        RAYS.with(|it| it.replace(0))
+    }
+
+    // how often the bar is redrawn at most
+    const REDRAW: Duration = Duration::from_millis(200);
+
+    /// Progress bar on stderr that any thread can advance. It only draws to a terminal, and
+    /// never under tests.
+    #[derive(Debug)]
+    pub struct ProgressBar {
+        visible: bool,
+        total: u64,
+        done: AtomicU64,
+        rays: AtomicU64,
+        start: Instant,
+        last_draw: Mutex<Instant>,
+    }
+
+    impl ProgressBar {
+        /// Bar for `total` units of work, pixel samples for renders
+        pub fn new(total: u64) -> ProgressBar {
+            let start = Instant::now();
+            ProgressBar {
+                visible: io::stderr().is_terminal() && !cfg!(test),
+                total: total.max(1),
+                done: AtomicU64::new(0),
+                rays: AtomicU64::new(0),
+                start,
+                last_draw: Mutex::new(start),
+            }
+        }
+
+        /// Records `work` more units done, along with the rays the calling thread traced since
+        /// it last reported, and redraws unless another thread is at it or it just did
+        pub fn advance(&self, work: u64) {
+            let done = self.done.fetch_add(work, Ordering::Relaxed) + work;
+            self.rays.fetch_add(take_rays(), Ordering::Relaxed);
+
+            if let Ok(mut last_draw) = self.last_draw.try_lock() {
+                if last_draw.elapsed() >= REDRAW || done >= self.total {
+                    *last_draw = Instant::now();
+                    self.draw(done);
+                }
+            }
+        }
+
+        /// Draws the bar as it ends and moves on to the next line
+        pub fn finish(&self) {
+            if self.visible {
+                self.draw(self.done.load(Ordering::Relaxed));
+                eprintln!();
+            }
+        }
+
+        fn draw(&self, done: u64) {
+            if !self.visible {
+                return;
+            }
+            let status = status(
+                done,
+                self.total,
+                self.start.elapsed().as_secs_f64() as Float,
+                self.rays.load(Ordering::Relaxed),
+            );
+            let mut stderr = io::stderr().lock();
+            let _ = write!(stderr, "\r{}", status);
+            let _ = stderr.flush();
+        }
+    }
+
+    /// One line status: bar, percentage, estimated time left and rays per second
+    pub fn status(done: u64, total: u64, seconds: Float, rays: u64) -> String {
+        const WIDTH: usize = 30;
+        let fraction = (done as Float / total as Float).min(1.0);
+        let filled = (fraction * WIDTH as Float) as usize;
+        let eta = if done > 0 {
+            seconds * (total - done.min(total)) as Float / done as Float
+        } else {
+            0.0
+        };
+        let rate = if seconds > 0.0 { rays as Float / seconds } else { 0.0 };
+        format!(
+            "[{}{}] {:5.1}% ETA {:.0}s {:.2} Mrays/s",
+            "#".repeat(filled),
+            " ".repeat(WIDTH - filled),
+            fraction * 100.0,
+            eta,
+            rate / 1e6
+        )
+    }
+}
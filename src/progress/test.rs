@@ -0,0 +1,24 @@
+#[cfg(test)]
+mod progress_test {
+
+    use crate::progress::reporting::status;
+
+    #[test]
+    fn should_report_percentage_eta_and_ray_rate() {
+        // given a quarter of the work done in 10 seconds and 5 million rays
+        let line = status(250, 1000, 10.0, 5_000_000);
+
+        // then the rest takes three times as long
+        assert_eq!(
+            line,
+            format!("[{}{}]  25.0% ETA 30s 0.50 Mrays/s", "#".repeat(7), " ".repeat(23))
+        );
+    }
+
+    #[test]
+    fn should_fill_bar_when_done() {
+        let line = status(1000, 1000, 4.0, 0);
+
+        assert!(line.starts_with(&format!("[{}] 100.0% ETA 0s", "#".repeat(30))));
+    }
+}
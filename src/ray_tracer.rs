@@ -1,12 +1,35 @@
+#[cfg(test)]
+mod test;
+
 pub mod tracer {
 
     use std::fmt::{self};
+    use std::io::{self, Read, Write};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+    use std::time::Instant;
 
-    use crate::Scene;
+    use crate::bsdf::bsdfs::luminance;
+    use crate::checkpoint::checkpoints::{read_float, read_u32, write_float, write_u32};
+    use crate::filter::filters::Filter;
+    use crate::mesh::triangle_mesh::TriangleRef;
+    use crate::progress::reporting::{count_ray, ProgressBar};
+    use crate::sampling::samplers::{RunningStats, Sampler};
+    use crate::tile::tiles::{self, Tile};
     use crate::{camera::camera_view::Ray, shapes::shape_components::AsGShape};
-    use cgmath::{Vector3, Zero};
-    use rayon::prelude::*;
+    use crate::{Float, Scene};
+    use cgmath::{Vector2, Vector3, Zero};
+
+    /// Pixels around a tile its samples can reach through the filter
+    pub fn tile_padding(scene: &Scene) -> u32 {
+        scene.settings.filter.radius().ceil() as u32
+    }
 
+    /// Offset applied to the origin of secondary rays so they don't hit the surface they leave
+    pub const RAY_EPSILON: Float = if cfg!(feature = "f32") { 1e-3 } else { 1e-4 };
+
+    // boxing the hit would allocate for every intersection test
+    #[allow(clippy::large_enum_variant)]
     #[derive(Debug, Clone, Copy)]
     pub enum TestHit {
         Hit(HitInfo),
@@ -15,22 +38,101 @@ pub mod tracer {
 
     #[derive(Debug, Clone, Copy)]
     pub struct HitInfo {
-        t_value: f64,
-        _p: Vector3<f64>,
-        _n: Vector3<f64>,
+        t_value: Float,
+        _p: Vector3<Float>,
+        _n: Vector3<Float>,
         ray: Ray,
         color: Color,
+        material: u32,
+        /// Set when a mesh triangle was hit
+        triangle: Option<TriangleRef>,
+        /// Texture coordinates, zero for shapes without a mapping
+        uv: Vector2<Float>,
+        /// Hit point before the shape's transform, the world point unless the shape sets it
+        object_point: Vector3<Float>,
+        /// World space derivatives of the hit point along u and v, zero without a mapping
+        dpdu: Vector3<Float>,
+        dpdv: Vector3<Float>,
     }
 
     impl HitInfo {
         pub fn from(
-            t_value: f64,
-            p: Vector3<f64>,
-            n: Vector3<f64>,
+            t_value: Float,
+            p: Vector3<Float>,
+            n: Vector3<Float>,
             ray: Ray,
             color: Color,
+            material: u32,
         ) -> HitInfo {
-            HitInfo { t_value, _p: p, _n: n, ray, color }
+            HitInfo {
+                t_value,
+                _p: p,
+                _n: n,
+                ray,
+                color,
+                material,
+                triangle: None,
+                uv: Vector2::zero(),
+                object_point: p,
+                dpdu: Vector3::zero(),
+                dpdv: Vector3::zero(),
+            }
+        }
+
+        pub fn with_normal(self, n: Vector3<Float>) -> HitInfo {
+            HitInfo { _n: n, ..self }
+        }
+
+        pub fn with_tangents(self, dpdu: Vector3<Float>, dpdv: Vector3<Float>) -> HitInfo {
+            HitInfo { dpdu, dpdv, ..self }
+        }
+
+        pub fn with_object_point(self, object_point: Vector3<Float>) -> HitInfo {
+            HitInfo { object_point, ..self }
+        }
+
+        pub fn with_uv(self, uv: Vector2<Float>) -> HitInfo {
+            HitInfo { uv, ..self }
+        }
+
+        pub fn t_value(&self) -> Float {
+            self.t_value
+        }
+
+        pub fn point(&self) -> Vector3<Float> {
+            self._p
+        }
+
+        pub fn normal(&self) -> Vector3<Float> {
+            self._n
+        }
+
+        pub fn material(&self) -> u32 {
+            self.material
+        }
+
+        pub fn color(&self) -> Color {
+            self.color
+        }
+
+        pub fn triangle(&self) -> Option<TriangleRef> {
+            self.triangle
+        }
+
+        pub fn uv(&self) -> Vector2<Float> {
+            self.uv
+        }
+
+        pub fn object_point(&self) -> Vector3<Float> {
+            self.object_point
+        }
+
+        pub fn dpdu(&self) -> Vector3<Float> {
+            self.dpdu
+        }
+
+        pub fn dpdv(&self) -> Vector3<Float> {
+            self.dpdv
         }
 
         pub fn new() -> HitInfo {
@@ -40,6 +142,12 @@ pub mod tracer {
                 _n: Vector3::zero(),
                 ray: Ray::new(Vector3::zero(), Vector3::zero(), 0.0),
                 color: Color { r: 0, g: 0, b: 0 },
+                material: 0,
+                triangle: None,
+                uv: Vector2::zero(),
+                object_point: Vector3::zero(),
+                dpdu: Vector3::zero(),
+                dpdv: Vector3::zero(),
             }
         }
     }
@@ -57,110 +165,518 @@ pub mod tracer {
         pub b: i32,
     }
 
+    impl Color {
+        pub fn to_radiance(self) -> Vector3<Float> {
+            Vector3::new(self.r as Float, self.g as Float, self.b as Float) / 255.0
+        }
+
+        /// Clamps radiance to [0, 1] and quantizes it
+        pub fn from_radiance(radiance: Vector3<Float>) -> Color {
+            let channel = |v: Float| (v.clamp(0.0, 1.0) * 255.0).round() as i32;
+            Color { r: channel(radiance.x), g: channel(radiance.y), b: channel(radiance.z) }
+        }
+    }
+
     impl fmt::Display for Color {
         fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
             write!(f, "[r{}g{}b{}]", self.r, self.g, self.b)
         }
     }
 
-    #[derive(Debug)]
+    // pixels weighing less than this are too close to cancelling out to divide by
+    const MIN_PIXEL_WEIGHT: Float = 1e-3;
+
+    /// Samples splatted through a reconstruction filter. Each pixel keeps the filter weighted
+    /// sum of the radiance of the samples around it and the sum of their weights, its value is
+    /// the ratio of the two.
+    #[derive(Debug, Clone)]
     pub struct Image {
         pub width: u32,
         pub height: u32,
-        pub matrix: Vec<Vec<Color>>,
+        /// Row major, h x w
+        sums: Vec<Vector3<Float>>,
+        weights: Vec<Float>,
+        /// Camera samples taken in each pixel
+        samples: Vec<u32>,
     }
 
     impl Image {
         pub fn new(width: u32, height: u32) -> Image {
+            let size = width as usize * height as usize;
             Image {
                 width,
                 height,
-                // matrix h x w
-                matrix: vec![vec![Color { r: 0, g: 0, b: 0 }; width as usize]; height as usize],
+                sums: vec![Vector3::zero(); size],
+                weights: vec![0.0; size],
+                samples: vec![0; size],
             }
         }
 
-        pub fn convert_to_one_row_array(&self) -> Vec<u8> {
-            self.matrix
+        /// Adds a sample taken at `x`, `y` in pixel units to every pixel whose center is within
+        /// the filter radius. The footprint is half open so a box of radius 1/2 gives each
+        /// sample to exactly one pixel.
+        pub fn splat(&mut self, filter: &Filter, x: Float, y: Float, radiance: Vector3<Float>) {
+            let radius = filter.radius();
+            let first = |p: Float| ((p - radius - 0.5).floor() + 1.0).max(0.0) as usize;
+            let last = |p: Float, size: u32| (p + radius - 0.5).floor().min(size as Float - 1.0);
+
+            let (x_last, y_last) = (last(x, self.width), last(y, self.height));
+            if x_last < 0.0 || y_last < 0.0 {
+                return;
+            }
+            for j in first(y)..=y_last as usize {
+                for i in first(x)..=x_last as usize {
+                    let weight = filter.eval(i as Float + 0.5 - x, j as Float + 0.5 - y);
+                    let index = j * self.width as usize + i;
+                    self.sums[index] += radiance * weight;
+                    self.weights[index] += weight;
+                }
+            }
+        }
+
+        /// Adds the samples of `tile`, an image whose top left pixel is `(left, top)` here.
+        /// Pixels falling outside this image are dropped.
+        pub fn add_tile(&mut self, tile: &Image, (left, top): (i64, i64)) {
+            for row in 0..tile.height as i64 {
+                let y = top + row;
+                if y < 0 || y >= self.height as i64 {
+                    continue;
+                }
+                for column in 0..tile.width as i64 {
+                    let x = left + column;
+                    if x < 0 || x >= self.width as i64 {
+                        continue;
+                    }
+                    let from = (row * tile.width as i64 + column) as usize;
+                    let to = (y * self.width as i64 + x) as usize;
+                    self.sums[to] += tile.sums[from];
+                    self.weights[to] += tile.weights[from];
+                    self.samples[to] += tile.samples[from];
+                }
+            }
+        }
+
+        /// Writes the size and the accumulated samples, floats in double precision whatever
+        /// the build uses
+        pub fn write(&self, out: &mut impl Write) -> io::Result<()> {
+            write_u32(out, self.width)?;
+            write_u32(out, self.height)?;
+            for i in 0..self.sums.len() {
+                for channel in 0..3 {
+                    write_float(out, self.sums[i][channel])?;
+                }
+                write_float(out, self.weights[i])?;
+                write_u32(out, self.samples[i])?;
+            }
+            Ok(())
+        }
+
+        /// Reads back what `write` wrote. An image of any other size than `size` is refused
+        /// before anything gets allocated for it.
+        pub fn read(input: &mut impl Read, size: (u32, u32)) -> io::Result<Image> {
+            let (width, height) = (read_u32(input)?, read_u32(input)?);
+            if (width, height) != size {
+                let message =
+                    format!("image is {}x{}, expected {}x{}", width, height, size.0, size.1);
+                return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+            }
+            let mut image = Image::new(width, height);
+            for i in 0..image.sums.len() {
+                for channel in 0..3 {
+                    image.sums[i][channel] = read_float(input)?;
+                }
+                image.weights[i] = read_float(input)?;
+                image.samples[i] = read_u32(input)?;
+            }
+            Ok(image)
+        }
+
+        /// Filtered radiance of a pixel, black where no sample weighs in. Negative lobes can
+        /// leave a pixel with next to no weight, or less than none, which counts as none.
+        pub fn radiance(&self, x: u32, y: u32) -> Vector3<Float> {
+            let index = y as usize * self.width as usize + x as usize;
+            let weight = self.weights[index];
+            if weight > MIN_PIXEL_WEIGHT {
+                self.sums[index] / weight
+            } else {
+                Vector3::zero()
+            }
+        }
+
+        pub fn color(&self, x: u32, y: u32) -> Color {
+            Color::from_radiance(self.radiance(x, y))
+        }
+
+        pub fn add_samples(&mut self, x: u32, y: u32, count: u32) {
+            self.samples[y as usize * self.width as usize + x as usize] += count;
+        }
+
+        #[allow(dead_code)]
+        pub fn samples(&self, x: u32, y: u32) -> u32 {
+            self.samples[y as usize * self.width as usize + x as usize]
+        }
+
+        pub fn average_samples(&self) -> Float {
+            self.samples.iter().map(|it| *it as Float).sum::<Float>() / self.samples.len() as Float
+        }
+
+        /// Samples per pixel as RGB bytes, blue for the fewest samples through green to red
+        /// for the most
+        pub fn samples_heatmap(&self) -> Vec<u8> {
+            let fewest = self.samples.iter().copied().min().unwrap_or(0);
+            let most = self.samples.iter().copied().max().unwrap_or(0);
+            let range = (most - fewest).max(1) as Float;
+            self.samples
                 .iter()
-                .flatten() // from 2d matrix to 1d array
-                // .rev() // because pngs have left top origin 
+                .flat_map(|it| {
+                    let t = (it - fewest) as Float / range;
+                    let ramp = |center: Float| (1.5 - (4.0 * t - center).abs()).clamp(0.0, 1.0);
+                    [ramp(3.0), ramp(2.0), ramp(1.0)].map(|c| (c * 255.0).round() as u8)
+                })
+                .collect()
+        }
+
+        pub fn convert_to_one_row_array(&self) -> Vec<u8> {
+            (0..self.height)
+                .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+                .map(|(x, y)| self.color(x, y))
                 .flat_map(|c| [c.r as u8, c.g as u8, c.b as u8]) // color into u8 array into
                 .collect::<Vec<u8>>()
         }
     }
 
+    /// How often progressive renders hand out a snapshot
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum SnapshotInterval {
+        Seconds(Float),
+        Passes(u32),
+    }
+
+    /// Renders the whole frame one sample per pixel at a time, so the image is usable at any
+    /// point, until it has the samples per pixel of the settings
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct Progressive {
+        pub snapshots: SnapshotInterval,
+        /// Seconds after which to stop short of the samples per pixel
+        pub time_budget: Option<Float>,
+        /// Stops once the average relative error of the pixel luminances drops below it
+        pub noise_threshold: Option<Float>,
+    }
+
+    impl Progressive {
+        /// Reads `<interval> <seconds|passes> [budget <seconds>] [noise <threshold>]`
+        pub fn parse(words: &[&str]) -> Option<Progressive> {
+            let interval = words.first()?.parse::<Float>().ok()?;
+            let snapshots = match *words.get(1)? {
+                "seconds" => SnapshotInterval::Seconds(interval),
+                "passes" => SnapshotInterval::Passes(interval as u32),
+                _ => return None,
+            };
+            let mut progressive =
+                Progressive { snapshots, time_budget: None, noise_threshold: None };
+            for option in words[2..].chunks(2) {
+                let value = option.get(1)?.parse::<Float>().ok()?;
+                match option[0] {
+                    "budget" => progressive.time_budget = Some(value),
+                    "noise" => progressive.noise_threshold = Some(value),
+                    _ => return None,
+                }
+            }
+            Some(progressive)
+        }
+    }
+
+    /// Everything a progressive render needs to carry on: the image, the luminance statistics
+    /// of its pixels over the passes and how many passes it took. Samplers draw each sample
+    /// from its index alone, so the pass count is all of their state.
+    #[derive(Debug, Clone)]
+    pub struct RenderState {
+        pub image: Image,
+        pub stats: Vec<RunningStats>,
+        pub passes: u32,
+    }
+
+    impl RenderState {
+        pub fn new(width: u32, height: u32) -> RenderState {
+            RenderState {
+                image: Image::new(width, height),
+                stats: vec![RunningStats::default(); width as usize * height as usize],
+                passes: 0,
+            }
+        }
+
+        /// Average relative error of the pixel luminances
+        pub fn noise(&self) -> Float {
+            self.stats.iter().map(|it| it.relative_error()).sum::<Float>()
+                / self.stats.len() as Float
+        }
+    }
+
+    /// Where a progressive render stands
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct Progress {
+        /// Samples per pixel so far
+        pub passes: u32,
+        pub seconds: Float,
+        /// Average relative error of the pixel luminances
+        pub noise: Float,
+    }
+
     #[derive(Debug)]
     pub struct RayTracer {}
 
     impl RayTracer {
         pub fn ray_trace_par(&self, scene: &Scene) -> Image {
             let cam = scene.cams.first().unwrap();
-            let image = Image::new(cam.width, cam.height);
-
-            let matrix: Vec<Vec<Color>> = (0..cam.height)
-                .into_par_iter()
-                .enumerate()
-                .map(|(i, row)| {
-                    {
-                        (0..cam.width)
-                            .into_par_iter()
-                            .enumerate()
-                            .map(|(j, _)| {
-                                let y_mid = i as f64 + 0.5;
-                                let x_mid = j as f64 + 0.5;
-
-                                let ray = cam.ray_thru_pixel(x_mid, y_mid);
-                                let hit = self.intersect(&ray, scene);
-                                match hit {
-                                    TestHit::Hit(info) => info.color,
-                                    TestHit::NoHit => Color { r: 0, g: 0, b: 0 },
-                                }
-                            })
-                            .collect::<Vec<Color>>()
-                    }
-                })
-                .collect();
+            let progress = ProgressBar::new(cam.width as u64 * cam.height as u64);
+            let image = self.render_tiles(scene, &progress, |x, y, tile, origin| {
+                self.pixel(scene, x, y, tile, origin)
+            });
+            progress.finish();
+            image
+        }
+
+        /// Renders the frame in passes of one sample per pixel up to the samples per pixel of
+        /// the settings, handing the render so far to `snapshot` as often as asked. Stops early
+        /// once out of time or when the image is clean enough. Carries on from `resume` when
+        /// given, the time budget counts from now.
+        pub fn ray_trace_progressive(
+            &self,
+            scene: &Scene,
+            progressive: &Progressive,
+            resume: Option<RenderState>,
+            mut snapshot: impl FnMut(&RenderState, &Progress),
+        ) -> RenderState {
+            let cam = scene.cams.first().unwrap();
+            let passes = scene.settings.spp.max(1);
+            let start = Instant::now();
+
+            let mut state = resume.unwrap_or_else(|| RenderState::new(cam.width, cam.height));
+            assert_eq!(
+                (state.image.width, state.image.height),
+                (cam.width, cam.height),
+                "resumed render size mismatch"
+            );
+            let mut last_snapshot = (start, state.passes);
+            let bar = ProgressBar::new(
+                cam.width as u64 * cam.height as u64 * (passes.saturating_sub(state.passes)) as u64,
+            );
+            for pass in state.passes..passes {
+                let frame = self.render_tiles(scene, &bar, |x, y, tile, origin| {
+                    let pixel = y as u64 * cam.width as u64 + x as u64;
+                    let mut sampler = scene.settings.sampler.for_pixel(pixel, passes);
+                    self.sample(scene, sampler.as_mut(), pass, (x, y), tile, origin);
+                    tile.add_samples((x as i64 - origin.0) as u32, (y as i64 - origin.1) as u32, 1);
+                });
+                state.image.add_tile(&frame, (0, 0));
+                state.passes = pass + 1;
 
-            Image { width: cam.width, height: cam.height, matrix }
+                for (i, it) in state.stats.iter_mut().enumerate() {
+                    let (x, y) = (i as u32 % cam.width, i as u32 / cam.width);
+                    it.add(luminance(frame.radiance(x, y)));
+                }
+                let progress = Progress {
+                    passes: state.passes,
+                    seconds: start.elapsed().as_secs_f64() as Float,
+                    noise: state.noise(),
+                };
+
+                let out_of_time = progressive.time_budget.is_some_and(|it| progress.seconds >= it);
+                let clean = progressive
+                    .noise_threshold
+                    .is_some_and(|it| progress.passes >= 2 && progress.noise <= it);
+                if progress.passes == passes || out_of_time || clean {
+                    break;
+                }
+                let snapshot_due = match progressive.snapshots {
+                    SnapshotInterval::Seconds(seconds) => {
+                        last_snapshot.0.elapsed().as_secs_f64() as Float >= seconds
+                    },
+                    SnapshotInterval::Passes(every) => progress.passes - last_snapshot.1 >= every,
+                };
+                if snapshot_due {
+                    snapshot(&state, &progress);
+                    last_snapshot = (Instant::now(), progress.passes);
+                }
+            }
+            bar.finish();
+            state
         }
 
+        /// Renders tiles on every thread of the pool, taking them in the order of the settings,
+        /// each into its own image padded by the filter radius. The tiles are added up in that
+        /// order too, so the result doesn't depend on the schedule. `pixel` splats the samples
+        /// of a camera pixel into a tile given the camera pixel of the tile's top left corner.
+        fn render_tiles(
+            &self,
+            scene: &Scene,
+            progress: &ProgressBar,
+            pixel: impl Fn(u32, u32, &mut Image, (i64, i64)) + Sync,
+        ) -> Image {
+            let settings = &scene.settings;
+            let cam = scene.cams.first().unwrap();
+            let tiles =
+                tiles::split(cam.width, cam.height, settings.tile_size, settings.tile_order);
+
+            let next = AtomicUsize::new(0);
+            let rendered: Vec<Mutex<Option<Image>>> =
+                tiles.iter().map(|_| Mutex::new(None)).collect();
+            rayon::broadcast(|_| {
+                let mut index = next.fetch_add(1, Ordering::Relaxed);
+                while let Some(tile) = tiles.get(index) {
+                    *rendered[index].lock().unwrap() = Some(self.fill_tile(scene, tile, &pixel));
+                    progress.advance(tile.width as u64 * tile.height as u64);
+                    index = next.fetch_add(1, Ordering::Relaxed);
+                }
+            });
+
+            let rendered = rendered.into_iter().map(|it| it.into_inner().unwrap().unwrap());
+            self.merge_tiles(scene, &tiles, rendered)
+        }
+
+        /// Takes the samples of the pixels of `tile` into an image of their own, padded by the
+        /// filter radius, for `merge_tiles` to add up. Workers render their jobs through it.
+        pub fn render_tile(&self, scene: &Scene, tile: &Tile) -> Image {
+            self.fill_tile(scene, tile, |x, y, image, origin| {
+                self.pixel(scene, x, y, image, origin)
+            })
+        }
+
+        fn fill_tile(
+            &self,
+            scene: &Scene,
+            tile: &Tile,
+            pixel: impl Fn(u32, u32, &mut Image, (i64, i64)),
+        ) -> Image {
+            let padding = tile_padding(scene);
+            let mut image = Image::new(tile.width + 2 * padding, tile.height + 2 * padding);
+            let origin = (tile.x as i64 - padding as i64, tile.y as i64 - padding as i64);
+            for y in tile.y..tile.y + tile.height {
+                for x in tile.x..tile.x + tile.width {
+                    pixel(x, y, &mut image, origin);
+                }
+            }
+            image
+        }
+
+        /// Adds up the images `render_tile` made of `tiles` into the frame, in the order given
+        pub fn merge_tiles(
+            &self,
+            scene: &Scene,
+            tiles: &[Tile],
+            rendered: impl IntoIterator<Item = Image>,
+        ) -> Image {
+            let cam = scene.cams.first().unwrap();
+            let padding = tile_padding(scene) as i64;
+            let mut image = Image::new(cam.width, cam.height);
+            for (tile, rendered) in tiles.iter().zip(rendered) {
+                image.add_tile(&rendered, (tile.x as i64 - padding, tile.y as i64 - padding));
+            }
+            image
+        }
+
+        #[allow(dead_code)]
         pub fn ray_trace(&self, scene: &Scene) -> Image {
-            let cam = scene.cams.get(0).unwrap();
+            let cam = scene.cams.first().unwrap();
             let mut image = Image::new(cam.width, cam.height);
+            let progress = ProgressBar::new(cam.width as u64 * cam.height as u64);
             // println!["Scene: {:?}", scene];
 
             for j in 0..cam.height {
                 for i in 0..cam.width {
-                    let x_mid = i as f64 + 0.5;
-                    let y_mid = j as f64 + 0.5;
-
-                    let ray = cam.ray_thru_pixel(x_mid, y_mid);
-                    let hit = self.intersect(&ray, scene);
-
-                    image.matrix[j as usize][i as usize] = match hit {
-                        TestHit::Hit(info) => info.color,
-                        TestHit::NoHit => Color { r: 0, g: 0, b: 0 },
-                    };
+                    self.pixel(scene, i, j, &mut image, (0, 0));
                 }
-                println!("Progress {:.2}%", j as f64 / cam.height as f64 * 100.0);
+                progress.advance(cam.width as u64);
             }
+            progress.finish();
 
             image
         }
 
-        fn intersect(&self, ray: &Ray, scene: &Scene) -> TestHit {
-            let mut t_min = f64::MAX;
+        /// Takes `spp` samples of camera pixel `(x, y)` and splats them into `image`, whose top
+        /// left pixel is camera pixel `origin`. With adaptive sampling the pixel keeps getting
+        /// samples until its luminance converges.
+        fn pixel(&self, scene: &Scene, x: u32, y: u32, image: &mut Image, origin: (i64, i64)) {
+            let cam = scene.cams.first().unwrap();
+            let spp = scene.settings.spp.max(1);
+            let adaptive = scene.settings.adaptive;
+            let max_spp = adaptive.map_or(spp, |it| it.max_spp.max(spp));
+            let pixel = y as u64 * cam.width as u64 + x as u64;
+            let mut sampler = scene.settings.sampler.for_pixel(pixel, spp);
+            let mut stats = RunningStats::default();
+
+            let mut s = 0;
+            while s < max_spp && (s < spp || adaptive.is_some_and(|it| !it.is_converged(&stats))) {
+                let radiance = self.sample(scene, sampler.as_mut(), s, (x, y), image, origin);
+                stats.add(luminance(radiance));
+                s += 1;
+            }
+            image.add_samples((x as i64 - origin.0) as u32, (y as i64 - origin.1) as u32, s);
+        }
+
+        /// Takes sample `index` of camera pixel `(x, y)`, splats it into `image` whose top left
+        /// pixel is camera pixel `origin` and returns its radiance. Pixels that only ever get one
+        /// sample are sampled through their center, the others are spread inside them by the
+        /// sampler of the render settings.
+        fn sample(
+            &self,
+            scene: &Scene,
+            sampler: &mut dyn Sampler,
+            index: u32,
+            (x, y): (u32, u32),
+            image: &mut Image,
+            (left, top): (i64, i64),
+        ) -> Vector3<Float> {
+            let cam = scene.cams.first().unwrap();
+            let single =
+                scene.settings.spp <= 1 && scene.settings.adaptive.is_none_or(|it| it.max_spp <= 1);
+
+            sampler.start_sample(index);
+            let (dx, dy) = if single { (0.5, 0.5) } else { sampler.next_2d() };
+            let (px, py) = (x as Float + dx, y as Float + dy);
+            let ray = cam.ray_thru_pixel(px, py);
+            let radiance = scene.settings.integrator.radiance(&ray, scene, sampler);
+            image.splat(&scene.settings.filter, px - left as Float, py - top as Float, radiance);
+            radiance
+        }
+
+        /// Whether anything blocks the unit `direction` from `origin` closer than `distance`.
+        /// Stops at the first blocker instead of looking for the closest one.
+        pub fn occluded(
+            &self,
+            origin: Vector3<Float>,
+            direction: Vector3<Float>,
+            distance: Float,
+            scene: &Scene,
+        ) -> bool {
+            count_ray();
+            let ray = Ray::new(origin, direction, distance);
+            let blocks = |hit: TestHit| matches!(hit, TestHit::Hit(info) if info.t_value > 0.0 && info.t_value < distance);
+
+            scene
+                .triangle_refs
+                .iter()
+                .any(|it| blocks(scene.meshes[it.mesh as usize].intersection(it.triangle, &ray)))
+                || scene.spheres.iter().any(|it| blocks(it.intersection(&ray)))
+                || scene.sdfs.iter().any(|it| blocks(it.intersection(&ray)))
+        }
+
+        /// Closest hit along `ray`
+        pub fn intersect(&self, ray: &Ray, scene: &Scene) -> TestHit {
+            count_ray();
+            let mut t_min = Float::MAX;
             let mut closest_intersection = HitInfo::new();
-            closest_intersection.t_value = f64::MAX;
+            closest_intersection.t_value = Float::MAX;
 
-            for it in &scene.triangles {
-                if let TestHit::Hit(test) = it.intersection(ray) {
+            for it in &scene.triangle_refs {
+                let mesh = &scene.meshes[it.mesh as usize];
+                if let TestHit::Hit(test) = mesh.intersection(it.triangle, ray) {
                     // println!["Hit Triangle {:?}", ray];
                     if test.t_value < t_min && test.t_value > 0.0 {
                         t_min = test.t_value;
                         closest_intersection = test;
                         closest_intersection.ray = *ray;
+                        closest_intersection.triangle = Some(*it);
                     }
                 }
             }
@@ -175,7 +691,17 @@ pub mod tracer {
                 }
             }
 
-            if closest_intersection.t_value == f64::MAX {
+            for it in &scene.sdfs {
+                if let TestHit::Hit(test) = it.intersection(ray) {
+                    if test.t_value < t_min && test.t_value > 0.0 {
+                        t_min = test.t_value;
+                        closest_intersection = test;
+                        closest_intersection.ray = *ray;
+                    }
+                }
+            }
+
+            if closest_intersection.t_value == Float::MAX {
                 TestHit::NoHit
             } else {
                 TestHit::Hit(closest_intersection)
@@ -0,0 +1,270 @@
+#[cfg(test)]
+mod ray_tracer_test {
+
+    use approx::assert_relative_eq;
+    use cgmath::Vector3;
+
+    use crate::camera::camera_view::Ray;
+    use crate::filter::filters::Filter;
+    use crate::integrator::integrators::from_name;
+    use crate::ray_tracer::tracer::{Image, Progressive, RayTracer, SnapshotInterval, TestHit};
+    use crate::read_scene;
+    use crate::sampling::samplers::SamplerKind;
+    use crate::sampling::samplers::{AdaptiveSampling, Rng};
+    use crate::tile::tiles::TileOrder;
+
+    #[test]
+    fn should_read_lights_and_integrator() {
+        let scene = read_scene("tests/fixtures/furnace.test".to_string());
+        assert_eq!(format!("{:?}", scene.settings.integrator), "PathTracing");
+
+        let scene = read_scene("tests/fixtures/lit-sphere.test".to_string());
+        assert_eq!(format!("{:?}", scene.settings.integrator), "FlatColor");
+        assert_eq!(scene.lights.len(), 1);
+    }
+
+    #[test]
+    fn should_render_same_image_on_every_run() {
+        // given a path traced scene with several samples per pixel on the thread pool
+        let mut scene = read_scene("tests/fixtures/area-lights.test".to_string());
+        scene.settings.spp = 8;
+        scene.settings.integrator = from_name("path", &[]).unwrap();
+
+        for kind in [SamplerKind::Random, SamplerKind::Sobol] {
+            scene.settings.sampler = kind;
+
+            // then whichever threads pick the pixels up, the image comes out the same
+            let first = RayTracer {}.ray_trace_par(&scene).convert_to_one_row_array();
+            let second = RayTracer {}.ray_trace_par(&scene).convert_to_one_row_array();
+            assert_eq!(first, second, "{:?}", kind);
+        }
+    }
+
+    #[test]
+    fn should_stop_occlusion_at_distance() {
+        // given the lower sphere top and the upper sphere 2 units above
+        let scene = read_scene("tests/fixtures/lit-sphere.test".to_string());
+        let origin = Vector3::new(0.0, 1.0001, 0.0);
+        let up = Vector3::new(0.0, 1.0, 0.0);
+        let tracer = RayTracer {};
+
+        // then the blocker counts only inside the distance
+        assert!(tracer.occluded(origin, up, 5.0, &scene));
+        assert!(!tracer.occluded(origin, up, 1.5, &scene));
+        assert!(matches!(tracer.intersect(&Ray::new(origin, up, 10.0), &scene), TestHit::Hit(_)));
+    }
+
+    #[test]
+    fn should_average_samples_of_pixel_with_box_filter() {
+        // given two samples inside pixel (1, 2) and one on the corner of its neighbor
+        let mut image = Image::new(4, 4);
+        let filter = Filter::default();
+        image.splat(&filter, 1.2, 2.7, Vector3::new(1.0, 0.0, 0.0));
+        image.splat(&filter, 1.9, 2.1, Vector3::new(0.0, 1.0, 0.0));
+        image.splat(&filter, 2.0, 2.0, Vector3::new(0.0, 0.0, 1.0));
+
+        // then each pixel gets the plain average of its own samples
+        assert_eq!(image.radiance(1, 2), Vector3::new(0.5, 0.5, 0.0));
+        assert_eq!(image.radiance(2, 2), Vector3::new(0.0, 0.0, 1.0));
+        assert_eq!(image.radiance(1, 1), Vector3::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn should_keep_flat_image_flat_under_every_filter() {
+        for name in ["box", "tent", "gaussian", "mitchell", "lanczos"] {
+            // given gray samples scattered over the whole image
+            let filter = Filter::from_name(name, &[]).unwrap();
+            let mut image = Image::new(6, 5);
+            let mut rng = Rng::new(1, 0);
+            for _ in 0..600 {
+                let (x, y) = (rng.next_float() * 6.0, rng.next_float() * 5.0);
+                image.splat(&filter, x, y, Vector3::new(0.5, 0.5, 0.5));
+            }
+
+            // then normalizing by the weights gives gray back, borders included
+            for (x, y) in [(0, 0), (5, 4), (2, 3)] {
+                assert_relative_eq!(image.radiance(x, y).y, 0.5, epsilon = 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn should_spread_samples_over_filter_footprint() {
+        // given a red and a green sample a pixel apart through a tent of radius 1.5
+        let mut image = Image::new(5, 5);
+        let filter = Filter::Tent { radius: 1.5 };
+        image.splat(&filter, 2.5, 2.5, Vector3::new(1.0, 0.0, 0.0));
+        image.splat(&filter, 3.5, 2.5, Vector3::new(0.0, 1.0, 0.0));
+
+        // then pixels blend them by distance to their centers, weights 2.25 and 0.75
+        assert_relative_eq!(image.radiance(2, 2).x, 0.75);
+        assert_relative_eq!(image.radiance(3, 2).y, 0.75);
+        // and only see the samples within the radius
+        assert_eq!(image.radiance(4, 2), Vector3::new(0.0, 1.0, 0.0));
+        assert_eq!(image.radiance(0, 2), Vector3::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn should_leave_pixel_black_under_negative_lobe() {
+        // given a single sample 1.5 pixels right of pixel (0, 0), in a Mitchell negative lobe
+        let mut image = Image::new(4, 1);
+        let filter = Filter::from_name("mitchell", &[]).unwrap();
+        image.splat(&filter, 2.0, 0.5, Vector3::new(1.0, 1.0, 1.0));
+
+        // then the pixel it only takes weight away from stays black
+        assert!(filter.eval(1.5, 0.0) < 0.0);
+        assert_eq!(image.radiance(0, 0), Vector3::new(0.0, 0.0, 0.0));
+        // while the pixel it falls in gets it
+        assert_relative_eq!(image.radiance(2, 0).x, 1.0);
+    }
+
+    #[test]
+    fn should_add_tiles_at_their_place() {
+        // given a tile whose top left pixel sits at (2, -1), outside the image
+        let mut tile = Image::new(3, 3);
+        tile.splat(&Filter::default(), 0.5, 2.5, Vector3::new(1.0, 0.0, 0.0));
+        tile.splat(&Filter::default(), 1.5, 2.5, Vector3::new(0.0, 1.0, 0.0));
+        tile.splat(&Filter::default(), 2.5, 2.5, Vector3::new(0.0, 0.0, 1.0));
+        tile.splat(&Filter::default(), 1.5, 0.5, Vector3::new(1.0, 1.0, 1.0));
+        let mut image = Image::new(4, 2);
+
+        // when adding it
+        image.add_tile(&tile, (2, -1));
+
+        // then pixels move by the offset and those landing outside the image are dropped
+        assert_eq!(image.radiance(2, 1), Vector3::new(1.0, 0.0, 0.0));
+        assert_eq!(image.radiance(3, 1), Vector3::new(0.0, 1.0, 0.0));
+        assert_eq!(image.radiance(3, 0), Vector3::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn should_render_same_image_with_any_tiling() {
+        // given a path traced scene rendered with big scanline tiles, its filter reaching into
+        // the neighbouring tiles
+        let mut scene = read_scene("tests/fixtures/area-lights.test".to_string());
+        scene.settings.integrator = from_name("path", &[]).unwrap();
+        scene.settings.filter = Filter::Gaussian { radius: 1.0, alpha: 2.0 };
+        scene.settings.tile_size = 64;
+        let reference = RayTracer {}.ray_trace_par(&scene);
+
+        for (size, order) in [(1, TileOrder::Spiral), (3, TileOrder::Hilbert)] {
+            // when tiles get small and come in other orders
+            scene.settings.tile_size = size;
+            scene.settings.tile_order = order;
+            let image = RayTracer {}.ray_trace_par(&scene);
+
+            // then pixels only differ by the order their samples were added in
+            for (x, y) in (0..4).flat_map(|y| (0..4).map(move |x| (x, y))) {
+                assert_relative_eq!(
+                    image.radiance(x, y).y,
+                    reference.radiance(x, y).y,
+                    epsilon = 1e-4
+                );
+                assert_eq!(image.samples(x, y), reference.samples(x, y));
+            }
+        }
+    }
+
+    #[test]
+    fn should_spend_extra_samples_on_noisy_pixels_only() {
+        // given a flat shaded scene, noisy only along edges, and a path traced one
+        let mut flat = read_scene("tests/fixtures/lit-sphere.test".to_string());
+        flat.settings.spp = 4;
+        flat.settings.adaptive = Some(AdaptiveSampling { threshold: 0.05, max_spp: 32 });
+        let mut noisy = read_scene("tests/fixtures/area-lights.test".to_string());
+        noisy.settings.spp = 4;
+        noisy.settings.adaptive = Some(AdaptiveSampling { threshold: 0.05, max_spp: 32 });
+        noisy.settings.integrator = from_name("path", &[]).unwrap();
+
+        // when rendering them
+        let flat = RayTracer {}.ray_trace_par(&flat);
+        let noisy = RayTracer {}.ray_trace_par(&noisy);
+
+        // then pixels of flat background stop at the base count and noisy ones take more, up to
+        // the maximum
+        assert_eq!(flat.samples(0, 0), 4);
+        assert_eq!(flat.samples(3, 3), 4);
+        assert!(noisy.average_samples() > 4.0);
+        for (x, y) in (0..4).flat_map(|y| (0..4).map(move |x| (x, y))) {
+            assert!((4..=32).contains(&noisy.samples(x, y)));
+        }
+    }
+
+    #[test]
+    fn should_color_heatmap_from_fewest_to_most_samples() {
+        // given pixels with 4, 10 and 16 samples
+        let mut image = Image::new(3, 1);
+        image.add_samples(0, 0, 4);
+        image.add_samples(1, 0, 10);
+        image.add_samples(2, 0, 16);
+
+        // then they go from blue through green to red
+        assert_eq!(image.samples_heatmap(), vec![0, 0, 128, 128, 255, 128, 128, 0, 0]);
+    }
+
+    #[test]
+    fn should_parse_progressive_options() {
+        let parse = |spec: &str| Progressive::parse(&spec.split_whitespace().collect::<Vec<_>>());
+
+        assert_eq!(
+            parse("4 passes noise 0.05"),
+            Some(Progressive {
+                snapshots: SnapshotInterval::Passes(4),
+                time_budget: None,
+                noise_threshold: Some(0.05),
+            })
+        );
+        assert_eq!(parse("4"), None);
+        assert_eq!(parse("4 minutes"), None);
+        assert_eq!(parse("4 seconds budget"), None);
+        assert_eq!(parse("4 seconds speed 2"), None);
+    }
+
+    #[test]
+    fn should_add_up_passes_to_batch_render() {
+        // given a path traced scene with 6 samples per pixel
+        let mut scene = read_scene("tests/fixtures/area-lights.test".to_string());
+        scene.settings.spp = 6;
+        scene.settings.integrator = from_name("path", &[]).unwrap();
+        let progressive = Progressive::parse(&["2", "passes"]).unwrap();
+
+        for kind in [SamplerKind::Random, SamplerKind::Stratified, SamplerKind::Sobol] {
+            scene.settings.sampler = kind;
+
+            // when rendering it in passes, snapshots every 2 of them, and all at once
+            let mut snapshots = vec![];
+            let passes = RayTracer {}
+                .ray_trace_progressive(&scene, &progressive, None, |state, at| {
+                    snapshots.push((at.passes, state.image.average_samples()))
+                })
+                .image;
+            let batch = RayTracer {}.ray_trace_par(&scene);
+
+            // then snapshots come on time and the passes take the very same samples
+            assert_eq!(snapshots, vec![(2, 2.0), (4, 4.0)]);
+            assert_eq!(passes.average_samples(), 6.0);
+            for (x, y) in (0..4).flat_map(|y| (0..4).map(move |x| (x, y))) {
+                let (a, b) = (passes.radiance(x, y), batch.radiance(x, y));
+                assert_relative_eq!(a.x, b.x, epsilon = 1e-4);
+                assert_relative_eq!(a.z, b.z, epsilon = 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn should_stop_passes_early() {
+        // given a scene that would take 64 passes
+        let mut scene = read_scene("tests/fixtures/lit-sphere.test".to_string());
+        scene.settings.spp = 64;
+        let render = |spec: &str| {
+            let progressive = Progressive::parse(&spec.split_whitespace().collect::<Vec<_>>());
+            RayTracer {}.ray_trace_progressive(&scene, &progressive.unwrap(), None, |_, _| {}).image
+        };
+
+        // then a spent time budget stops after the first pass, a loose noise threshold after
+        // the two needed to measure noise
+        assert_eq!(render("1 passes budget 0").average_samples(), 1.0);
+        assert_eq!(render("1 passes noise 10").average_samples(), 2.0);
+        assert_eq!(render("1 passes").average_samples(), 64.0);
+    }
+}
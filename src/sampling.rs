@@ -0,0 +1,542 @@
+#[cfg(test)]
+mod test;
+
+pub mod samplers {
+    use std::io::{self, Read, Write};
+
+    use cgmath::{InnerSpace, Vector3};
+
+    use crate::checkpoint::checkpoints::{read_float, read_u32, write_float, write_u32};
+    use crate::{float_consts::PI, Float};
+
+    // largest float below 1
+    const ONE_MINUS_EPSILON: Float = 1.0 - Float::EPSILON / 2.0;
+
+    /// PCG32 random number generator. Small and fast enough to keep one per pixel, and seeding
+    /// it with the pixel index makes renders reproducible whatever the thread schedule.
+    #[derive(Debug, Clone)]
+    pub struct Rng {
+        state: u64,
+        inc: u64,
+    }
+
+    impl Rng {
+        pub fn new(seed: u64, stream: u64) -> Rng {
+            let mut rng = Rng { state: 0, inc: (stream << 1) | 1 };
+            rng.next_u32();
+            rng.state = rng.state.wrapping_add(seed);
+            rng.next_u32();
+            rng
+        }
+
+        pub fn next_u32(&mut self) -> u32 {
+            let old = self.state;
+            self.state = old.wrapping_mul(6364136223846793005).wrapping_add(self.inc);
+            let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+            xorshifted.rotate_right((old >> 59) as u32)
+        }
+
+        /// Uniform in [0, 1)
+        pub fn next_float(&mut self) -> Float {
+            // 24 bits so the result stays below 1 in f32 too
+            (self.next_u32() >> 8) as Float / (1u32 << 24) as Float
+        }
+    }
+
+    /// Source of the random numbers integrators consume. Every call moves on to the next
+    /// dimension of the current sample, so low discrepancy samplers can spread each dimension
+    /// well across the samples of a pixel.
+    pub trait Sampler {
+        /// Starts sample `index` of the pixel back at its first dimension
+        fn start_sample(&mut self, _index: u32) {}
+
+        /// Uniform in [0, 1)
+        fn next_1d(&mut self) -> Float;
+
+        fn next_2d(&mut self) -> (Float, Float) {
+            (self.next_1d(), self.next_1d())
+        }
+    }
+
+    impl Sampler for Rng {
+        fn next_1d(&mut self) -> Float {
+            self.next_float()
+        }
+    }
+
+    /// Sample sequence a render uses, picked with the sampler command or --sampler
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub enum SamplerKind {
+        /// Independent random numbers
+        #[default]
+        Random,
+        /// One jittered sample per stratum, strata shuffled independently in each dimension
+        Stratified,
+        /// Halton sequence, shifted at random per pixel
+        Halton,
+        /// Sobol sequence with Owen scrambling per pixel
+        Sobol,
+    }
+
+    impl SamplerKind {
+        pub fn from_name(name: &str) -> Option<SamplerKind> {
+            match name {
+                "random" => Some(SamplerKind::Random),
+                "stratified" => Some(SamplerKind::Stratified),
+                "halton" => Some(SamplerKind::Halton),
+                "sobol" => Some(SamplerKind::Sobol),
+                _ => None,
+            }
+        }
+
+        /// Sampler for the `spp` samples of one pixel. It only depends on the pixel index, so
+        /// renders come out the same whichever thread takes the pixel, and every sample only on
+        /// its index, so samples can be taken in any order or a few at a time.
+        pub fn for_pixel(self, pixel: u64, spp: u32) -> Box<dyn Sampler> {
+            match self {
+                SamplerKind::Random => Box::new(RandomSampler::new(pixel)),
+                SamplerKind::Stratified => Box::new(StratifiedSampler::new(pixel, spp)),
+                SamplerKind::Halton => Box::new(HaltonSampler::new(pixel)),
+                SamplerKind::Sobol => Box::new(SobolSampler::new(pixel)),
+            }
+        }
+    }
+
+    /// Independent random numbers, from a stream of its own for every sample of the pixel
+    #[derive(Debug, Clone)]
+    pub struct RandomSampler {
+        seed: u64,
+        rng: Rng,
+    }
+
+    impl RandomSampler {
+        pub fn new(seed: u64) -> RandomSampler {
+            RandomSampler { seed, rng: Rng::new(seed, 0) }
+        }
+    }
+
+    impl Sampler for RandomSampler {
+        fn start_sample(&mut self, index: u32) {
+            self.rng = Rng::new(mix(self.seed, index as u64) as u64, self.seed);
+        }
+
+        fn next_1d(&mut self) -> Float {
+            self.rng.next_float()
+        }
+    }
+
+    // luminance below which pixel errors are measured in absolute rather than relative terms
+    const DARK_LUMINANCE: Float = 0.01;
+
+    /// Keeps sampling a pixel past the base samples per pixel until the estimated error of its
+    /// luminance drops below `threshold`, relative to the luminance itself, or the pixel gets
+    /// `max_spp` samples
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct AdaptiveSampling {
+        pub threshold: Float,
+        pub max_spp: u32,
+    }
+
+    impl AdaptiveSampling {
+        pub fn is_converged(&self, stats: &RunningStats) -> bool {
+            stats.count() >= 2 && stats.relative_error() <= self.threshold
+        }
+    }
+
+    /// Running mean and variance of a stream of values, Welford's algorithm
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct RunningStats {
+        count: u32,
+        mean: Float,
+        /// Sum of squared differences from the mean
+        m2: Float,
+    }
+
+    impl RunningStats {
+        pub fn add(&mut self, value: Float) {
+            self.count += 1;
+            let delta = value - self.mean;
+            self.mean += delta / self.count as Float;
+            self.m2 += delta * (value - self.mean);
+        }
+
+        pub fn count(&self) -> u32 {
+            self.count
+        }
+
+        pub fn write(&self, out: &mut impl Write) -> io::Result<()> {
+            write_u32(out, self.count)?;
+            write_float(out, self.mean)?;
+            write_float(out, self.m2)
+        }
+
+        pub fn read(input: &mut impl Read) -> io::Result<RunningStats> {
+            Ok(RunningStats {
+                count: read_u32(input)?,
+                mean: read_float(input)?,
+                m2: read_float(input)?,
+            })
+        }
+
+        pub fn mean(&self) -> Float {
+            self.mean
+        }
+
+        /// Unbiased sample variance, 0 until there are two values
+        pub fn variance(&self) -> Float {
+            if self.count < 2 {
+                0.0
+            } else {
+                self.m2 / (self.count - 1) as Float
+            }
+        }
+
+        /// Estimated standard deviation of the mean
+        pub fn standard_error(&self) -> Float {
+            if self.count == 0 {
+                0.0
+            } else {
+                (self.variance() / self.count as Float).sqrt()
+            }
+        }
+
+        /// Standard error over the mean, taken as luminance, which dark values don't go below
+        pub fn relative_error(&self) -> Float {
+            self.standard_error() / self.mean().max(DARK_LUMINANCE)
+        }
+    }
+
+    /// Splits each dimension into `spp` strata and gives every sample of the pixel its own
+    /// stratum, in an order shuffled per dimension. Pairs are stratified over a grid when
+    /// `spp` is a square.
+    #[derive(Debug, Clone)]
+    pub struct StratifiedSampler {
+        seed: u64,
+        spp: u32,
+        index: u32,
+        dimension: u32,
+    }
+
+    impl StratifiedSampler {
+        pub fn new(seed: u64, spp: u32) -> StratifiedSampler {
+            StratifiedSampler { seed, spp: spp.max(1), index: 0, dimension: 0 }
+        }
+
+        /// Stratum of the current sample and a jitter inside it, for the current dimension
+        fn stratum(&mut self, strata: u32) -> (u32, Float) {
+            let hash = mix(self.seed, self.dimension as u64);
+            let stratum = permute(self.index % strata, strata, hash);
+            let jitter = to_unit(mix(hash as u64, self.index as u64 + 1));
+            self.dimension += 1;
+            (stratum, jitter)
+        }
+    }
+
+    impl Sampler for StratifiedSampler {
+        fn start_sample(&mut self, index: u32) {
+            self.index = index;
+            self.dimension = 0;
+        }
+
+        fn next_1d(&mut self) -> Float {
+            let (stratum, jitter) = self.stratum(self.spp);
+            ((stratum as Float + jitter) / self.spp as Float).min(ONE_MINUS_EPSILON)
+        }
+
+        fn next_2d(&mut self) -> (Float, Float) {
+            let side = (self.spp as Float).sqrt().round() as u32;
+            if side * side != self.spp {
+                return (self.next_1d(), self.next_1d());
+            }
+            // y jitters with the hash of this dimension too, mixed differently from x
+            let hash = mix(self.seed, self.dimension as u64);
+            let (cell, jitter_x) = self.stratum(self.spp);
+            let jitter_y = to_unit(mix(hash as u64 ^ 0x5bd1_e995, self.index as u64 + 1));
+            let x = ((cell % side) as Float + jitter_x) / side as Float;
+            let y = ((cell / side) as Float + jitter_y) / side as Float;
+            (x.min(ONE_MINUS_EPSILON), y.min(ONE_MINUS_EPSILON))
+        }
+    }
+
+    // bases of the Halton dimensions, random numbers take over past them
+    const PRIMES: [u32; 32] = [
+        2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89,
+        97, 101, 103, 107, 109, 113, 127, 131,
+    ];
+
+    /// Halton sequence, dimension `d` is the radical inverse of the sample index in the `d`th
+    /// prime. Each pixel shifts every dimension by its own random offset, modulo 1, so pixels
+    /// don't share the same pattern (Cranley-Patterson rotation).
+    #[derive(Debug, Clone)]
+    pub struct HaltonSampler {
+        seed: u64,
+        index: u32,
+        dimension: u32,
+    }
+
+    impl HaltonSampler {
+        pub fn new(seed: u64) -> HaltonSampler {
+            HaltonSampler { seed, index: 0, dimension: 0 }
+        }
+    }
+
+    impl Sampler for HaltonSampler {
+        fn start_sample(&mut self, index: u32) {
+            self.index = index;
+            self.dimension = 0;
+        }
+
+        fn next_1d(&mut self) -> Float {
+            let hash = mix(self.seed, self.dimension as u64);
+            let value = match PRIMES.get(self.dimension as usize) {
+                Some(&base) => {
+                    let shifted = radical_inverse(base, self.index) + to_unit(hash);
+                    shifted - shifted.floor()
+                },
+                None => to_unit(mix(hash as u64, self.index as u64)),
+            };
+            self.dimension += 1;
+            value.min(ONE_MINUS_EPSILON)
+        }
+    }
+
+    /// Sobol sequence scrambled the way Burley describes in "Practical Hash-based Owen
+    /// Scrambling": every pair of dimensions reads the first two Sobol dimensions at a sample
+    /// index shuffled for that pair, and Owen scrambles the result with hashes of the pixel and
+    /// the pair. Any number of dimensions stays well stratified without direction number
+    /// tables, and a power of two number of samples forms a (0, 2) net in each pair.
+    #[derive(Debug, Clone)]
+    pub struct SobolSampler {
+        seed: u64,
+        index: u32,
+        dimension: u32,
+    }
+
+    impl SobolSampler {
+        pub fn new(seed: u64) -> SobolSampler {
+            SobolSampler { seed, index: 0, dimension: 0 }
+        }
+    }
+
+    impl Sampler for SobolSampler {
+        fn start_sample(&mut self, index: u32) {
+            self.index = index;
+            self.dimension = 0;
+        }
+
+        fn next_1d(&mut self) -> Float {
+            self.next_2d().0
+        }
+
+        fn next_2d(&mut self) -> (Float, Float) {
+            let hash = mix(self.seed, self.dimension as u64);
+            self.dimension += 1;
+            let index = nested_uniform_scramble(self.index, hash);
+            let x = nested_uniform_scramble(sobol(index, 0), mix(hash as u64, 1));
+            let y = nested_uniform_scramble(sobol(index, 1), mix(hash as u64, 2));
+            (to_unit(x), to_unit(y))
+        }
+    }
+
+    /// First two dimensions of the Sobol sequence as 32 bit fractions
+    fn sobol(index: u32, dimension: u32) -> u32 {
+        if dimension == 0 {
+            return index.reverse_bits();
+        }
+        // direction numbers of the polynomial x + 1
+        let (mut result, mut direction, mut index) = (0, 1u32 << 31, index);
+        while index != 0 {
+            if index & 1 != 0 {
+                result ^= direction;
+            }
+            index >>= 1;
+            direction ^= direction >> 1;
+        }
+        result
+    }
+
+    /// Owen scrambling of a 32 bit fraction: flips each bit depending on the bits above it
+    fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+        laine_karras_permutation(x.reverse_bits(), seed).reverse_bits()
+    }
+
+    // Laine and Karras hash, each bit only depends on the bits below it
+    fn laine_karras_permutation(mut x: u32, seed: u32) -> u32 {
+        x = x.wrapping_add(seed);
+        x ^= x.wrapping_mul(0x6c50_b47c);
+        x ^= x.wrapping_mul(0xb82f_1e52);
+        x ^= x.wrapping_mul(0xc7af_e638);
+        x ^= x.wrapping_mul(0x8d22_f6e6);
+        x
+    }
+
+    /// Digits of `index` in `base` mirrored around the radix point
+    fn radical_inverse(base: u32, mut index: u32) -> Float {
+        let inverse_base = 1.0 / base as Float;
+        let (mut reversed, mut scale) = (0.0, inverse_base);
+        while index > 0 {
+            reversed += (index % base) as Float * scale;
+            index /= base;
+            scale *= inverse_base;
+        }
+        reversed
+    }
+
+    /// Kensler's hashed permutation of [0, `length`), element `i` of permutation `seed`
+    fn permute(mut i: u32, length: u32, seed: u32) -> u32 {
+        let mut w = length - 1;
+        w |= w >> 1;
+        w |= w >> 2;
+        w |= w >> 4;
+        w |= w >> 8;
+        w |= w >> 16;
+        loop {
+            i ^= seed;
+            i = i.wrapping_mul(0xe170_893d);
+            i ^= seed >> 16;
+            i ^= (i & w) >> 4;
+            i ^= seed >> 8;
+            i = i.wrapping_mul(0x0929_eb3f);
+            i ^= seed >> 23;
+            i ^= (i & w) >> 1;
+            i = i.wrapping_mul(1 | seed >> 27);
+            i = i.wrapping_mul(0x6935_fa69);
+            i ^= (i & w) >> 11;
+            i = i.wrapping_mul(0x74dc_b303);
+            i ^= (i & w) >> 2;
+            i = i.wrapping_mul(0x9e50_1cc3);
+            i ^= (i & w) >> 2;
+            i = i.wrapping_mul(0xc860_a3df);
+            i &= w;
+            i ^= i >> 5;
+            if i < length {
+                break;
+            }
+        }
+        (i + seed) % length
+    }
+
+    /// Hash of two numbers, the 64 bit finalizer of MurmurHash3
+    fn mix(a: u64, b: u64) -> u32 {
+        let mut h = a ^ b.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        h ^= h >> 33;
+        h = h.wrapping_mul(0xff51_afd7_ed55_8ccd);
+        h ^= h >> 33;
+        h = h.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+        (h ^ (h >> 33)) as u32
+    }
+
+    /// 32 random bits to [0, 1), 24 of them so f32 stays below 1
+    fn to_unit(bits: u32) -> Float {
+        (bits >> 8) as Float / (1u32 << 24) as Float
+    }
+
+    /// Direction around +z with pdf cos(theta) / pi, from two uniform numbers
+    pub fn cosine_sample_hemisphere(u1: Float, u2: Float) -> Vector3<Float> {
+        let r = u1.sqrt();
+        let phi = 2.0 * PI * u2;
+        Vector3::new(r * phi.cos(), r * phi.sin(), (1.0 - u1).max(0.0).sqrt())
+    }
+
+    /// Two unit vectors that make an orthonormal frame with the unit vector `n` (Duff et al.)
+    pub fn orthonormal_basis(n: Vector3<Float>) -> (Vector3<Float>, Vector3<Float>) {
+        let sign = (1.0 as Float).copysign(n.z);
+        let a = -1.0 / (sign + n.z);
+        let b = n.x * n.y * a;
+        (
+            Vector3::new(1.0 + sign * n.x * n.x * a, sign * b, -sign * n.x),
+            Vector3::new(b, sign + n.y * n.y * a, -n.y),
+        )
+    }
+
+    /// Takes a direction from the frame where `n` is +z to world space
+    pub fn to_world(local: Vector3<Float>, n: Vector3<Float>) -> Vector3<Float> {
+        Frame::from(n).to_world(local)
+    }
+
+    /// Orthonormal shading frame around a normal, the local space of bsdfs
+    #[derive(Debug, Clone, Copy)]
+    pub struct Frame {
+        pub t: Vector3<Float>,
+        pub b: Vector3<Float>,
+        pub n: Vector3<Float>,
+    }
+
+    impl Frame {
+        pub fn from(n: Vector3<Float>) -> Frame {
+            let (t, b) = orthonormal_basis(n);
+            Frame { t, b, n }
+        }
+
+        pub fn to_local(&self, w: Vector3<Float>) -> Vector3<Float> {
+            Vector3::new(w.dot(self.t), w.dot(self.b), w.dot(self.n))
+        }
+
+        pub fn to_world(&self, local: Vector3<Float>) -> Vector3<Float> {
+            self.t * local.x + self.b * local.y + self.n * local.z
+        }
+    }
+
+    /// Piecewise constant distribution over [0, 1) with one bin per weight, the building block
+    /// of importance sampling tabulated functions like environment maps
+    #[derive(Debug, Clone)]
+    pub struct Distribution1D {
+        weights: Vec<Float>,
+        /// Running sums normalized to end at 1, one more entry than `weights`
+        cdf: Vec<Float>,
+        total: Float,
+    }
+
+    impl Distribution1D {
+        /// Negative weights count as zero. When everything is zero the bins are uniform.
+        pub fn new(weights: &[Float]) -> Distribution1D {
+            let weights: Vec<Float> = weights.iter().map(|w| w.max(0.0)).collect();
+            let mut cdf = Vec::with_capacity(weights.len() + 1);
+            let mut total = 0.0;
+            cdf.push(total);
+            for w in &weights {
+                total += w;
+                cdf.push(total);
+            }
+
+            let count = weights.len().max(1) as Float;
+            for (i, c) in cdf.iter_mut().enumerate() {
+                *c = if total > 0.0 { *c / total } else { i as Float / count };
+            }
+            Distribution1D { weights, cdf, total }
+        }
+
+        pub fn len(&self) -> usize {
+            self.weights.len()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.weights.is_empty()
+        }
+
+        /// Sum of the weights
+        pub fn total(&self) -> Float {
+            self.total
+        }
+
+        /// Position in [0, 1) from a uniform number, the bin it falls in and the density there
+        pub fn sample(&self, u: Float) -> (Float, usize, Float) {
+            // last bin whose cdf is <= u, skipping empty bins
+            let bin = self.cdf.partition_point(|c| *c <= u).clamp(1, self.len()) - 1;
+            let width = self.cdf[bin + 1] - self.cdf[bin];
+            let offset = if width > 0.0 { (u - self.cdf[bin]) / width } else { 0.0 };
+            let x = ((bin as Float + offset.clamp(0.0, 1.0)) / self.len() as Float)
+                .min(ONE_MINUS_EPSILON);
+            (x, bin, self.pdf(bin))
+        }
+
+        /// Density of the bin, relative to the [0, 1) domain
+        pub fn pdf(&self, bin: usize) -> Float {
+            if self.total > 0.0 {
+                self.weights[bin] * self.len() as Float / self.total
+            } else {
+                1.0
+            }
+        }
+    }
+}
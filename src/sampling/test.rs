@@ -0,0 +1,275 @@
+#[cfg(test)]
+mod sampling_test {
+
+    use cgmath::{InnerSpace, Vector3};
+
+    use crate::sampling::samplers::{
+        cosine_sample_hemisphere, orthonormal_basis, to_world, AdaptiveSampling, Distribution1D,
+        Rng, RunningStats, SamplerKind,
+    };
+    use crate::Float;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn should_repeat_sequence_for_same_seed() {
+        let mut a = Rng::new(42, 0);
+        let mut b = Rng::new(42, 0);
+        let mut c = Rng::new(43, 0);
+
+        let first: Vec<u32> = (0..8).map(|_| a.next_u32()).collect();
+        assert_eq!(first, (0..8).map(|_| b.next_u32()).collect::<Vec<u32>>());
+        assert_ne!(first, (0..8).map(|_| c.next_u32()).collect::<Vec<u32>>());
+    }
+
+    #[test]
+    fn should_be_uniform_in_unit_interval() {
+        // given many draws
+        let mut rng = Rng::new(7, 0);
+        let samples: Vec<Float> = (0..100000).map(|_| rng.next_float()).collect();
+
+        // then they stay in [0, 1) with mean 1/2 and variance 1/12
+        assert!(samples.iter().all(|u| (0.0..1.0).contains(u)));
+        let mean = samples.iter().sum::<Float>() / samples.len() as Float;
+        let variance =
+            samples.iter().map(|u| (u - mean) * (u - mean)).sum::<Float>() / samples.len() as Float;
+        assert_relative_eq!(mean, 0.5, epsilon = 5e-3);
+        assert_relative_eq!(variance, 1.0 / 12.0, epsilon = 2e-3);
+    }
+
+    #[test]
+    fn should_sample_cosine_weighted_hemisphere() {
+        // given cosine weighted directions
+        let mut rng = Rng::new(1, 0);
+        let samples: Vec<Vector3<Float>> = (0..100000)
+            .map(|_| cosine_sample_hemisphere(rng.next_float(), rng.next_float()))
+            .collect();
+
+        // then they are unit vectors above the surface and E[cos] = 2/3
+        assert!(samples.iter().all(|d| d.z >= 0.0));
+        assert!(samples.iter().all(|d| (d.magnitude() - 1.0).abs() < 1e-4));
+        let mean_cos = samples.iter().map(|d| d.z).sum::<Float>() / samples.len() as Float;
+        assert_relative_eq!(mean_cos, 2.0 / 3.0, epsilon = 5e-3);
+    }
+
+    #[test]
+    fn should_build_orthonormal_frame() {
+        for n in [
+            Vector3::new(0.0, 0.0, 1.0),
+            Vector3::new(0.0, 0.0, -1.0),
+            Vector3::new(1.0, 2.0, -3.0).normalize(),
+        ] {
+            let (t, b) = orthonormal_basis(n);
+            assert_relative_eq!(t.magnitude(), 1.0, epsilon = 1e-6);
+            assert_relative_eq!(b.magnitude(), 1.0, epsilon = 1e-6);
+            assert_relative_eq!(t.dot(b), 0.0, epsilon = 1e-6);
+            assert_relative_eq!(t.dot(n), 0.0, epsilon = 1e-6);
+            assert_relative_eq!(b.dot(n), 0.0, epsilon = 1e-6);
+            assert_relative_eq!(
+                to_world(Vector3::new(0.0, 0.0, 1.0), n).dot(n),
+                1.0,
+                epsilon = 1e-6
+            );
+        }
+    }
+
+    #[test]
+    fn should_sample_bins_by_weight() {
+        // given bins weighted 1, 0, 3
+        let distribution = Distribution1D::new(&[1.0, 0.0, 3.0]);
+
+        // then a quarter of [0, 1) lands in the first bin and the rest in the last
+        let (x, bin, pdf) = distribution.sample(0.1);
+        assert_eq!(bin, 0);
+        assert_relative_eq!(x, 0.4 / 3.0, epsilon = 1e-6);
+        assert_relative_eq!(pdf, 0.75, epsilon = 1e-6);
+
+        let (x, bin, pdf) = distribution.sample(0.625);
+        assert_eq!(bin, 2);
+        assert_relative_eq!(x, 2.5 / 3.0, epsilon = 1e-6);
+        assert_relative_eq!(pdf, 2.25, epsilon = 1e-6);
+
+        // and the empty bin is never picked
+        let mut rng = Rng::new(3, 0);
+        assert!((0..10000).all(|_| distribution.sample(rng.next_float()).1 != 1));
+        assert_relative_eq!(distribution.pdf(1), 0.0);
+    }
+
+    #[test]
+    fn should_sample_uniformly_without_weights() {
+        let distribution = Distribution1D::new(&[0.0, 0.0]);
+
+        let (x, bin, pdf) = distribution.sample(0.7);
+
+        assert_eq!(bin, 1);
+        assert_relative_eq!(x, 0.7, epsilon = 1e-6);
+        assert_relative_eq!(pdf, 1.0);
+    }
+
+    const KINDS: [SamplerKind; 4] =
+        [SamplerKind::Random, SamplerKind::Stratified, SamplerKind::Halton, SamplerKind::Sobol];
+
+    /// The first `dimensions` numbers of each of the `spp` samples of a pixel
+    fn draw(kind: SamplerKind, pixel: u64, spp: u32, dimensions: usize) -> Vec<Vec<Float>> {
+        let mut sampler = kind.for_pixel(pixel, spp);
+        (0..spp)
+            .map(|s| {
+                sampler.start_sample(s);
+                (0..dimensions / 2)
+                    .flat_map(|_| {
+                        let (u, v) = sampler.next_2d();
+                        [u, v]
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// How many of `points` fall in each of the `columns` by `rows` cells of the unit square
+    fn cell_counts(points: &[(Float, Float)], columns: usize, rows: usize) -> Vec<usize> {
+        let mut counts = vec![0; columns * rows];
+        for (x, y) in points {
+            let column = (x * columns as Float) as usize;
+            let row = (y * rows as Float) as usize;
+            counts[row * columns + column] += 1;
+        }
+        counts
+    }
+
+    #[test]
+    fn should_repeat_samples_per_pixel() {
+        for kind in KINDS {
+            // given the samples of the same pixel drawn twice, and of the next pixel
+            let samples = draw(kind, 5, 16, 8);
+
+            // then they stay in [0, 1), repeat exactly and differ between pixels
+            assert!(samples.iter().flatten().all(|u| (0.0..1.0).contains(u)), "{:?}", kind);
+            assert_eq!(samples, draw(kind, 5, 16, 8), "{:?}", kind);
+            assert_ne!(samples, draw(kind, 6, 16, 8), "{:?}", kind);
+        }
+    }
+
+    #[test]
+    fn should_put_one_sample_in_each_stratum() {
+        // given 16 stratified samples, so a 4 by 4 grid for pairs
+        let mut sampler = SamplerKind::Stratified.for_pixel(9, 16);
+        let (mut pairs, mut singles) = (vec![], vec![]);
+        for s in 0..16 {
+            sampler.start_sample(s);
+            pairs.push(sampler.next_2d());
+            singles.push((sampler.next_1d(), 0.0));
+        }
+
+        // then every cell and every 1/16 of the single dimension gets one sample
+        assert!(cell_counts(&pairs, 4, 4).iter().all(|it| *it == 1));
+        assert!(cell_counts(&singles, 16, 1).iter().all(|it| *it == 1));
+    }
+
+    #[test]
+    fn should_jitter_each_stratified_pair_on_its_own() {
+        // given one sample per pixel, so every pair falls in the same single cell
+        let mut sampler = SamplerKind::Stratified.for_pixel(9, 1);
+        sampler.start_sample(0);
+
+        // when drawing two pairs of the same sample
+        let (x1, y1) = sampler.next_2d();
+        let (x2, y2) = sampler.next_2d();
+
+        // then neither coordinate repeats
+        assert_ne!(x1, x2);
+        assert_ne!(y1, y2);
+    }
+
+    #[test]
+    fn should_form_sobol_nets() {
+        // given 16 scrambled Sobol samples of 4 dimensions
+        let samples = draw(SamplerKind::Sobol, 11, 16, 4);
+
+        for pair in [0, 2] {
+            let points: Vec<(Float, Float)> =
+                samples.iter().map(|it| (it[pair], it[pair + 1])).collect();
+
+            // then each pair is a (0, 4, 2)-net, one point in every elementary interval
+            for (columns, rows) in [(16, 1), (8, 2), (4, 4), (2, 8), (1, 16)] {
+                assert!(
+                    cell_counts(&points, columns, rows).iter().all(|it| *it == 1),
+                    "pair {} in {}x{}",
+                    pair,
+                    columns,
+                    rows
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn should_stratify_halton_dimensions() {
+        // given as many Halton samples as a power of the base of the first two dimensions
+        let base_two = draw(SamplerKind::Halton, 2, 8, 2);
+        let base_three = draw(SamplerKind::Halton, 2, 9, 2);
+
+        // then the random shift still leaves one sample in each interval
+        let first: Vec<(Float, Float)> = base_two.iter().map(|it| (it[0], 0.0)).collect();
+        let second: Vec<(Float, Float)> = base_three.iter().map(|it| (it[1], 0.0)).collect();
+        assert!(cell_counts(&first, 8, 1).iter().all(|it| *it == 1));
+        assert!(cell_counts(&second, 9, 1).iter().all(|it| *it == 1));
+    }
+
+    #[test]
+    fn should_integrate_with_less_error_than_random() {
+        // given x * y over the unit square, 1/4, estimated with 64 samples in many pixels
+        let error = |kind: SamplerKind| {
+            let pixels = 200;
+            let squared: Float = (0..pixels)
+                .map(|pixel| {
+                    let samples = draw(kind, pixel, 64, 2);
+                    let estimate = samples.iter().map(|it| it[0] * it[1]).sum::<Float>()
+                        / samples.len() as Float;
+                    (estimate - 0.25) * (estimate - 0.25)
+                })
+                .sum();
+            (squared / pixels as Float).sqrt()
+        };
+
+        // then the low discrepancy samplers land well closer than independent samples
+        let random = error(SamplerKind::Random);
+        for kind in [SamplerKind::Stratified, SamplerKind::Halton, SamplerKind::Sobol] {
+            assert!(error(kind) < random / 3.0, "{:?} {} vs {}", kind, error(kind), random);
+        }
+    }
+
+    #[test]
+    fn should_track_running_mean_and_variance() {
+        let mut stats = RunningStats::default();
+        for value in [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0] {
+            stats.add(value);
+        }
+
+        assert_eq!(stats.count(), 8);
+        assert_relative_eq!(stats.mean(), 5.0, epsilon = 1e-6);
+        assert_relative_eq!(stats.variance(), 32.0 / 7.0, epsilon = 1e-5);
+        assert_relative_eq!(stats.standard_error(), (4.0 as Float / 7.0).sqrt(), epsilon = 1e-5);
+    }
+
+    #[test]
+    fn should_converge_once_error_is_below_threshold() {
+        let adaptive = AdaptiveSampling { threshold: 0.05, max_spp: 64 };
+
+        // given a pixel seeing the same radiance every time, converged from two samples on
+        let mut flat = RunningStats::default();
+        flat.add(0.5);
+        assert!(!adaptive.is_converged(&flat));
+        flat.add(0.5);
+        assert!(adaptive.is_converged(&flat));
+
+        // and one flipping between black and white, which needs about 400 samples
+        let mut noisy = RunningStats::default();
+        for i in 0..300 {
+            noisy.add((i % 2) as Float);
+        }
+        assert!(!adaptive.is_converged(&noisy));
+        for i in 0..200 {
+            noisy.add((i % 2) as Float);
+        }
+        assert!(adaptive.is_converged(&noisy));
+    }
+}
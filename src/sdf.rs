@@ -0,0 +1,229 @@
+#[cfg(test)]
+mod test;
+
+pub mod sdf_shapes {
+    use crate::{
+        camera::camera_view::Ray,
+        ray_tracer::tracer::{Color, HitInfo, TestHit},
+        shapes::shape_components::{AsGShape, GeometricShape},
+        Float,
+    };
+    use cgmath::{InnerSpace, Matrix4, Vector2, Vector3};
+
+    const MAX_STEPS: u32 = 256;
+    // hit threshold, scaled by the distance travelled so far
+    const HIT_EPSILON: Float = 1e-6;
+    // central differences lose most digits in single precision with a tiny step
+    const NORMAL_EPSILON: Float = if cfg!(feature = "f32") { 1e-3 } else { 1e-5 };
+
+    /// Node of a distance function tree. Leaves are primitives placed at `center` in object space,
+    /// inner nodes combine the distances of their two children.
+    #[derive(Debug, Clone)]
+    pub enum SdfNode {
+        Sphere {
+            center: Vector3<Float>,
+            radius: Float,
+        },
+        /// Axis aligned box given by its half extents
+        Cuboid {
+            center: Vector3<Float>,
+            half_size: Vector3<Float>,
+        },
+        /// Torus lying on the xz plane
+        Torus {
+            center: Vector3<Float>,
+            major_radius: Float,
+            minor_radius: Float,
+        },
+        /// Capped cylinder along the y axis
+        Cylinder {
+            center: Vector3<Float>,
+            radius: Float,
+            half_height: Float,
+        },
+        Union(Box<SdfNode>, Box<SdfNode>),
+        /// Union with a polynomial smooth minimum, `k` is the blending radius
+        SmoothUnion(Box<SdfNode>, Box<SdfNode>, Float),
+        Intersection(Box<SdfNode>, Box<SdfNode>),
+        /// Carves the second node out of the first one
+        Subtraction(Box<SdfNode>, Box<SdfNode>),
+        /// Linear interpolation of both distances, 0 -> first node, 1 -> second node
+        Blend(Box<SdfNode>, Box<SdfNode>, Float),
+    }
+
+    impl SdfNode {
+        pub fn distance(&self, p: Vector3<Float>) -> Float {
+            match self {
+                SdfNode::Sphere { center, radius } => (p - center).magnitude() - radius,
+                SdfNode::Cuboid { center, half_size } => {
+                    let local = p - center;
+                    let q = Vector3::new(
+                        local.x.abs() - half_size.x,
+                        local.y.abs() - half_size.y,
+                        local.z.abs() - half_size.z,
+                    );
+                    let outside = Vector3::new(q.x.max(0.0), q.y.max(0.0), q.z.max(0.0));
+                    outside.magnitude() + q.x.max(q.y.max(q.z)).min(0.0)
+                },
+                SdfNode::Torus { center, major_radius, minor_radius } => {
+                    let local = p - center;
+                    let q = Vector2::new(
+                        Vector2::new(local.x, local.z).magnitude() - major_radius,
+                        local.y,
+                    );
+                    q.magnitude() - minor_radius
+                },
+                SdfNode::Cylinder { center, radius, half_height } => {
+                    let local = p - center;
+                    let d = Vector2::new(
+                        Vector2::new(local.x, local.z).magnitude() - radius,
+                        local.y.abs() - half_height,
+                    );
+                    d.x.max(d.y).min(0.0) + Vector2::new(d.x.max(0.0), d.y.max(0.0)).magnitude()
+                },
+                SdfNode::Union(a, b) => a.distance(p).min(b.distance(p)),
+                SdfNode::SmoothUnion(a, b, k) => smooth_min(a.distance(p), b.distance(p), *k),
+                SdfNode::Intersection(a, b) => a.distance(p).max(b.distance(p)),
+                SdfNode::Subtraction(a, b) => a.distance(p).max(-b.distance(p)),
+                SdfNode::Blend(a, b, t) => {
+                    let t = t.clamp(0.0, 1.0);
+                    a.distance(p) * (1.0 - t) + b.distance(p) * t
+                },
+            }
+        }
+
+        /// Bounding sphere (center, radius) of the node in object space
+        pub fn bounds(&self) -> (Vector3<Float>, Float) {
+            match self {
+                SdfNode::Sphere { center, radius } => (*center, *radius),
+                SdfNode::Cuboid { center, half_size } => (*center, half_size.magnitude()),
+                SdfNode::Torus { center, major_radius, minor_radius } => {
+                    (*center, major_radius + minor_radius)
+                },
+                SdfNode::Cylinder { center, radius, half_height } => {
+                    (*center, Vector2::new(*radius, *half_height).magnitude())
+                },
+                SdfNode::Union(a, b) | SdfNode::Intersection(a, b) | SdfNode::Blend(a, b, _) => {
+                    enclose(a.bounds(), b.bounds())
+                },
+                // the smooth minimum pushes the surface out by at most k / 4
+                SdfNode::SmoothUnion(a, b, k) => {
+                    let (center, radius) = enclose(a.bounds(), b.bounds());
+                    (center, radius + k.max(0.0) / 4.0)
+                },
+                SdfNode::Subtraction(a, _) => a.bounds(),
+            }
+        }
+    }
+
+    fn smooth_min(a: Float, b: Float, k: Float) -> Float {
+        if k <= 0.0 {
+            return a.min(b);
+        }
+        let h = (k - (a - b).abs()).max(0.0) / k;
+        a.min(b) - h * h * k * 0.25
+    }
+
+    fn enclose(a: (Vector3<Float>, Float), b: (Vector3<Float>, Float)) -> (Vector3<Float>, Float) {
+        let dist = (b.0 - a.0).magnitude();
+        if dist + b.1 <= a.1 {
+            return a;
+        }
+        if dist + a.1 <= b.1 {
+            return b;
+        }
+        let radius = (dist + a.1 + b.1) / 2.0;
+        let center = a.0 + (b.0 - a.0) * ((radius - a.1) / dist);
+        (center, radius)
+    }
+
+    /// Shape defined by a distance function, rendered by sphere tracing
+    #[derive(Debug)]
+    pub struct Sdf {
+        pub root: SdfNode,
+        pub g_shape: GeometricShape,
+        bounds_center: Vector3<Float>,
+        bounds_radius: Float,
+    }
+
+    impl Sdf {
+        pub fn from(root: SdfNode, transform: Matrix4<Float>) -> Sdf {
+            let (bounds_center, bounds_radius) = root.bounds();
+            Sdf { root, g_shape: GeometricShape::from(transform), bounds_center, bounds_radius }
+        }
+
+        /// Normal estimated with central differences of the distance function
+        pub fn normal(&self, p: Vector3<Float>) -> Vector3<Float> {
+            let dx = Vector3::new(NORMAL_EPSILON, 0.0, 0.0);
+            let dy = Vector3::new(0.0, NORMAL_EPSILON, 0.0);
+            let dz = Vector3::new(0.0, 0.0, NORMAL_EPSILON);
+            Vector3::new(
+                self.root.distance(p + dx) - self.root.distance(p - dx),
+                self.root.distance(p + dy) - self.root.distance(p - dy),
+                self.root.distance(p + dz) - self.root.distance(p - dz),
+            )
+            .normalize()
+        }
+
+        /// Part of the ray inside the bounding sphere, the only range worth marching
+        fn bounds_span(&self, ray: &Ray) -> Option<(Float, Float)> {
+            let oc = ray.o - self.bounds_center;
+            let b = oc.dot(ray.d);
+            let c = oc.dot(oc) - self.bounds_radius * self.bounds_radius;
+            let discriminant = b * b - c;
+            if discriminant < 0.0 {
+                return None;
+            }
+            let sqrt_discriminant = discriminant.sqrt();
+            let t_exit = -b + sqrt_discriminant;
+            if t_exit < 0.0 {
+                return None;
+            }
+            Some(((-b - sqrt_discriminant).max(0.0), t_exit))
+        }
+    }
+
+    impl AsGShape for Sdf {
+        fn as_g_shape(&self) -> &GeometricShape {
+            &self.g_shape
+        }
+
+        fn intersection(&self, ray: &Ray) -> TestHit {
+            let obj_ray = self.g_shape.ray_to_object(ray);
+            let Some((t_enter, t_exit)) = self.bounds_span(&obj_ray) else {
+                return TestHit::NoHit;
+            };
+
+            let mut t = t_enter;
+            for _ in 0..MAX_STEPS {
+                if t > t_exit {
+                    return TestHit::NoHit;
+                }
+
+                let p = obj_ray.o + obj_ray.d * t;
+                let distance = self.root.distance(p).abs();
+
+                if distance < HIT_EPSILON * t.max(1.0) {
+                    let intersection_p = self.g_shape.point_to_world(p);
+                    let normal = self.g_shape.normal_to_world(self.normal(p));
+
+                    return TestHit::Hit(
+                        HitInfo::from(
+                            (intersection_p - ray.o).magnitude(),
+                            intersection_p,
+                            normal,
+                            *ray,
+                            Color { r: 255, g: 255, b: 0 },
+                            self.g_shape.material,
+                        )
+                        .with_object_point(p),
+                    );
+                }
+
+                t += distance;
+            }
+
+            TestHit::NoHit
+        }
+    }
+}
@@ -0,0 +1,116 @@
+#[cfg(test)]
+mod sdf_test {
+
+    use cgmath::{InnerSpace, Matrix4, One, Vector3};
+
+    use crate::camera::camera_view::Ray;
+    use crate::ray_tracer::tracer::TestHit;
+    use crate::sdf::sdf_shapes::{Sdf, SdfNode};
+    use crate::shapes::shape_components::AsGShape;
+    use crate::{float_consts::PI, Float, Sphere};
+    use approx::assert_relative_eq;
+
+    fn sphere_node(x: Float, radius: Float) -> SdfNode {
+        SdfNode::Sphere { center: Vector3::new(x, 0.0, 0.0), radius }
+    }
+
+    #[test]
+    fn should_match_analytic_sphere() {
+        // given an sdf sphere and an analytic sphere at the same place
+        let transform = Matrix4::from_translation(Vector3::new(0.0, 0.5, -5.0));
+        let sdf = Sdf::from(sphere_node(0.0, 1.0), transform);
+        let sphere = Sphere::from(0.0, 0.0, 0.0, 1.0, transform);
+        let ray = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0), 10000.0);
+
+        // when intersecting both
+        let (TestHit::Hit(sdf_hit), TestHit::Hit(sphere_hit)) =
+            (sdf.intersection(&ray), sphere.intersection(&ray))
+        else {
+            panic!("Should hit");
+        };
+
+        // then hits agree
+        assert_relative_eq!(sdf_hit.t_value(), sphere_hit.t_value(), epsilon = 1e-4);
+        assert_relative_eq!(sdf_hit.normal().dot(sphere_hit.normal()), 1.0, epsilon = 1e-6);
+    }
+
+    #[test]
+    fn should_not_intersect_sdf() {
+        let sdf =
+            Sdf::from(sphere_node(0.0, 1.0), Matrix4::from_translation(Vector3::unit_y() * 5.0));
+        let ray = Ray::new(Vector3::new(0.0, 0.0, 5.0), Vector3::new(0.0, 0.0, -1.0), 10000.0);
+
+        match sdf.intersection(&ray) {
+            TestHit::NoHit => println!("No Hit"),
+            TestHit::Hit(_) => panic!("Should not hit"),
+        }
+    }
+
+    #[test]
+    fn should_estimate_box_face_normal() {
+        let cuboid = SdfNode::Cuboid {
+            center: Vector3::new(0.0, 0.0, 0.0),
+            half_size: Vector3::new(1.0, 2.0, 3.0),
+        };
+        let sdf = Sdf::from(cuboid, Matrix4::one());
+        let ray = Ray::new(Vector3::new(0.2, 10.0, 0.3), Vector3::new(0.0, -1.0, 0.0), 10000.0);
+
+        let TestHit::Hit(hit) = sdf.intersection(&ray) else {
+            panic!("Should hit");
+        };
+        assert_relative_eq!(hit.t_value(), 8.0, epsilon = 1e-4);
+        assert_relative_eq!(hit.normal().dot(Vector3::unit_y()), 1.0, epsilon = 1e-6);
+    }
+
+    #[test]
+    fn smooth_union_should_fill_the_gap() {
+        // two spheres barely apart, the smooth union bridges them
+        let union =
+            SdfNode::Union(Box::new(sphere_node(-1.05, 1.0)), Box::new(sphere_node(1.05, 1.0)));
+        let smooth = SdfNode::SmoothUnion(
+            Box::new(sphere_node(-1.05, 1.0)),
+            Box::new(sphere_node(1.05, 1.0)),
+            0.5,
+        );
+        let origin = Vector3::new(0.0, 0.0, 0.0);
+
+        assert!(union.distance(origin) > 0.0);
+        assert!(smooth.distance(origin) < 0.0);
+    }
+
+    #[test]
+    fn smooth_union_should_stay_inside_its_bounds() {
+        // given the bridged spheres, blended over a wide radius
+        let smooth = SdfNode::SmoothUnion(
+            Box::new(sphere_node(-1.05, 1.0)),
+            Box::new(sphere_node(1.05, 1.0)),
+            2.0,
+        );
+        let (center, radius) = smooth.bounds();
+
+        // then no point of the bounding sphere is inside the shape
+        for i in 0..64 {
+            let angle = i as Float / 64.0 * 2.0 * PI;
+            let p = center + Vector3::new(angle.cos(), angle.sin(), 0.0) * radius;
+            assert!(smooth.distance(p) >= -1e-6, "{:?}", p);
+        }
+        assert_relative_eq!(radius, 2.05 + 0.5, epsilon = 1e-6);
+    }
+
+    #[test]
+    fn subtraction_should_carve_hole() {
+        let carved =
+            SdfNode::Subtraction(Box::new(sphere_node(0.0, 1.0)), Box::new(sphere_node(0.0, 0.5)));
+        let ray = Ray::new(Vector3::new(0.0, 0.0, 5.0), Vector3::new(0.0, 0.0, -1.0), 10000.0);
+
+        let TestHit::Hit(hit) = Sdf::from(carved, Matrix4::one()).intersection(&ray) else {
+            panic!("Should hit");
+        };
+        assert_relative_eq!(hit.t_value(), 4.0, epsilon = 1e-4);
+        assert!(
+            SdfNode::Subtraction(Box::new(sphere_node(0.0, 1.0)), Box::new(sphere_node(0.0, 0.5)))
+                .distance(Vector3::new(0.0, 0.0, 0.0))
+                > 0.0
+        );
+    }
+}
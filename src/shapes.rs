@@ -4,21 +4,25 @@ mod test;
 pub mod shape_components {
     use crate::{
         camera::camera_view::Ray,
+        float_consts::PI,
         ray_tracer::tracer::{Color, HitInfo, TestHit},
+        Float,
     };
     use cgmath::{num_traits::pow, InnerSpace, Matrix, Matrix4, SquareMatrix};
-    use cgmath::{Matrix3, One, Zero};
-    use cgmath::{Vector3, Vector4};
+    use cgmath::{Matrix3, One};
+    use cgmath::{Vector2, Vector3, Vector4};
+    use log::trace;
 
     #[derive(Debug)]
     pub struct GeometricShape {
         //type needed?
-        _size: f64,
-        _material: Vector3<i32>,
-        transform: Matrix4<f64>,
-        inverse_transform: Matrix4<f64>,
-        // inverse_transpose_transform: Matrix4<f64>,
-        inverse_transpose_transform_3x3: Matrix3<f64>,
+        _size: Float,
+        /// Index into the scene materials
+        pub material: u32,
+        transform: Matrix4<Float>,
+        inverse_transform: Matrix4<Float>,
+        // inverse_transpose_transform: Matrix4<Float>,
+        inverse_transpose_transform_3x3: Matrix3<Float>,
     }
 
     pub trait AsGShape {
@@ -31,7 +35,7 @@ pub mod shape_components {
         fn default() -> Self {
             Self {
                 _size: 0.0,
-                _material: Vector3::zero(),
+                material: 0,
                 transform: Matrix4::one(),
                 inverse_transform: Matrix4::one(),
                 // inverse_transpose_transform: Matrix4::one(),
@@ -44,8 +48,8 @@ pub mod shape_components {
             true
         }
 
-        pub fn from(transform: Matrix4<f64>) -> GeometricShape {
-            println!("Transform is {:?} ", transform);
+        pub fn from(transform: Matrix4<Float>) -> GeometricShape {
+            trace!("Transform is {:?} ", transform);
 
             let inverse_transform = transform.invert().unwrap();
             let inverse_transpose_transform = inverse_transform.transpose();
@@ -69,19 +73,45 @@ pub mod shape_components {
                 ..Default::default()
             }
         }
+
+        /// Takes a world space ray into the shape's object space. The direction is normalized, so
+        /// t values along the returned ray are object space distances.
+        pub fn ray_to_object(&self, ray: &Ray) -> Ray {
+            // 1 -> point, 0 -> vector
+            let o =
+                (self.inverse_transform * Vector4::new(ray.o.x, ray.o.y, ray.o.z, 1.0)).truncate();
+            let d = (self.inverse_transform * Vector4::new(ray.d.x, ray.d.y, ray.d.z, 0.0))
+                .truncate()
+                .normalize();
+            Ray::new(o, d, ray.t)
+        }
+
+        pub fn point_to_world(&self, p: Vector3<Float>) -> Vector3<Float> {
+            (self.transform * Vector4::new(p.x, p.y, p.z, 1.0)).truncate()
+        }
+
+        pub fn normal_to_world(&self, n: Vector3<Float>) -> Vector3<Float> {
+            (self.inverse_transpose_transform_3x3 * n).normalize()
+        }
     }
 
     #[derive(Debug, Default)]
     pub struct Sphere {
-        pub x: f64,
-        pub y: f64,
-        pub z: f64,
-        pub radius: f64,
+        pub x: Float,
+        pub y: Float,
+        pub z: Float,
+        pub radius: Float,
         pub g_shape: GeometricShape,
     }
 
     impl Sphere {
-        pub fn from(x: f64, y: f64, z: f64, radius: f64, transform: Matrix4<f64>) -> Sphere {
+        pub fn from(
+            x: Float,
+            y: Float,
+            z: Float,
+            radius: Float,
+            transform: Matrix4<Float>,
+        ) -> Sphere {
             Sphere { x, y, z, radius, g_shape: GeometricShape::from(transform) }
         }
     }
@@ -117,12 +147,12 @@ pub mod shape_components {
                 return TestHit::NoHit;
             }
 
-            let sqrt_discriminant = f64::sqrt(discriminant);
+            let sqrt_discriminant = Float::sqrt(discriminant);
 
             // calc t0 and check if it is valid
             let t0 = (-b - sqrt_discriminant) / (2.0 * a);
 
-            let t_value: f64;
+            let t_value: Float;
 
             // t0 is valid
             if t0 > 0.0 {
@@ -151,113 +181,37 @@ pub mod shape_components {
             // Calc depth value
             let t = (intersection_obj_space.truncate() - ray.o).magnitude();
 
-            // Set output
-            TestHit::Hit(HitInfo::from(
-                t,
-                intersection_obj_space.truncate(),
-                normal_transformed,
-                *ray,
-                Color { r: 255, g: 0, b: 0 },
-            ))
-        }
-    }
-
-    #[derive(Debug)]
-    pub struct Triangle {
-        pub vertices: Vec<usize>,
-        pub a: Vector4<f64>,
-        pub b: Vector4<f64>,
-        pub c: Vector4<f64>,
-        pub g_shape: GeometricShape,
-        a_transformed: Vector4<f64>,
-        b_transformed: Vector4<f64>,
-        c_transformed: Vector4<f64>,
-    }
-
-    impl Triangle {
-        pub fn new(
-            vertices: Vec<usize>,
-            a: Vector4<f64>,
-            b: Vector4<f64>,
-            c: Vector4<f64>,
-        ) -> Triangle {
-            let mut triangle = Triangle {
-                vertices,
-                a,
-                b,
-                c,
-                g_shape: GeometricShape::default(),
-                a_transformed: Vector4::zero(),
-                b_transformed: Vector4::zero(),
-                c_transformed: Vector4::zero(),
-            };
-            triangle.pre_calc();
-            triangle
-        }
-
-        fn pre_calc(&mut self) {
-            self.g_shape.inverse_transform = self.g_shape.transform.invert().unwrap();
-            self.g_shape.inverse_transform = self.g_shape.inverse_transform.transpose();
-            self.a_transformed = self.g_shape.transform * self.a;
-            self.b_transformed = self.g_shape.transform * self.b;
-            self.c_transformed = self.g_shape.transform * self.c;
-        }
-    }
-
-    impl AsGShape for Triangle {
-        fn as_g_shape(&self) -> &GeometricShape {
-            &self.g_shape
-        }
-
-        fn intersection(&self, ray: &Ray) -> TestHit {
-            let a = self.a_transformed.truncate();
-            let b = self.b_transformed.truncate();
-            let c = self.c_transformed.truncate();
-            let t_min: f64 = 0.0;
-            let t_max: f64 = f64::MAX;
-
-            let v_ab = b - a;
-            let v_ac = c - a;
-            let norm = v_ab.cross(v_ac).normalize();
-
-            // Triangle plane intersection
-            let output_t_value = norm.dot(a - ray.o) / norm.dot(ray.d);
-            let q = ray.o + output_t_value * ray.d;
-
-            // Compute barycentric coordinates
-            let v_aq = q - a;
-            let dab_ab = v_ab.dot(v_ab);
-            let dab_ac = v_ab.dot(v_ac);
-            let dac_ac = v_ac.dot(v_ac);
-            let daq_ab = v_aq.dot(v_ab);
-            let daq_ac = v_aq.dot(v_ac);
-
-            // determinant
-            let d = dab_ab * dac_ac - dab_ac * dab_ac;
-
-            if d == 0.0 {
-                return TestHit::NoHit;
-            }
-
-            let beta = (dac_ac * daq_ab - dab_ac * daq_ac) / d;
-            let gamma = (dab_ab * daq_ac - dab_ac * daq_ab) / d;
-
-            if (beta < 0.0 || gamma < 0.0)
-                || (beta > 1.0 || gamma > 1.0)
-                || (beta + gamma > 1.0)
-                || (output_t_value < t_min || output_t_value > t_max)
-            {
-                return TestHit::NoHit;
-            }
+            // spherical mapping in object space, u around the y axis and v from the bottom pole
+            let local = (temp - Vector3::new(self.x, self.y, self.z)) / self.radius;
+            let uv = Vector2::new(
+                0.5 + local.x.atan2(local.z) / (2.0 * PI),
+                1.0 - local.y.clamp(-1.0, 1.0).acos() / PI,
+            );
+            // derivatives of that mapping, the v one degenerates at the poles
+            let sin_theta = (local.x * local.x + local.z * local.z).sqrt().max(1e-6);
+            let dpdu = Vector3::new(local.z, 0.0, -local.x) * (2.0 * PI * self.radius);
+            let dpdv = Vector3::new(
+                -local.y * local.x / sin_theta,
+                sin_theta,
+                -local.y * local.z / sin_theta,
+            ) * (PI * self.radius);
+            let to_world =
+                |it: Vector3<Float>| (self.g_shape.transform * it.extend(0.0)).truncate();
 
-            // its a hit
-            TestHit::Hit(HitInfo::from(
-                output_t_value,
-                q,
-                norm,
-                *ray,
-                Color { r: 0, g: 255, b: 255 },
-            ))
+            // Set output
+            TestHit::Hit(
+                HitInfo::from(
+                    t,
+                    intersection_obj_space.truncate(),
+                    normal_transformed,
+                    *ray,
+                    Color { r: 255, g: 0, b: 0 },
+                    self.g_shape.material,
+                )
+                .with_uv(uv)
+                .with_object_point(temp)
+                .with_tangents(to_world(dpdu), to_world(dpdv)),
+            )
         }
     }
 }
@@ -3,12 +3,12 @@ mod shapes_test {
 
     use std::f64::consts::PI;
 
-    use cgmath::{Matrix4, One, Vector3};
+    use cgmath::{InnerSpace, Matrix4, One, Vector3};
 
     use crate::camera::camera_view::Ray;
     use crate::ray_tracer::tracer::TestHit;
     use crate::shapes::shape_components::AsGShape;
-    use crate::Sphere;
+    use crate::{float_consts, Float, Sphere};
     use approx::assert_relative_eq;
 
     #[test]
@@ -44,6 +44,48 @@ mod shapes_test {
         }
     }
 
+    #[test]
+    fn should_map_sphere_uvs() {
+        // given a unit sphere moved away from the origin
+        let transform = Matrix4::from_translation(Vector3::new(3.0, 0.0, 0.0));
+        let sphere = Sphere::from(0.0, 0.0, 0.0, 1.0, transform);
+        let uv = |o: Vector3<Float>, d: Vector3<Float>| match sphere
+            .intersection(&Ray::new(o, d, 10000.0))
+        {
+            TestHit::Hit(hit) => hit.uv(),
+            TestHit::NoHit => panic!("Should hit"),
+        };
+
+        // then the point facing +z is the middle of the map
+        let front = uv(Vector3::new(3.0, 0.0, 5.0), Vector3::new(0.0, 0.0, -1.0));
+        assert_relative_eq!(front.x, 0.5, epsilon = 1e-6);
+        assert_relative_eq!(front.y, 0.5, epsilon = 1e-6);
+
+        // and v climbs to 1 at the top pole, u goes a quarter turn around to +x
+        let top = uv(Vector3::new(3.0, 5.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        assert_relative_eq!(top.y, 1.0, epsilon = 1e-6);
+        let side = uv(Vector3::new(8.0, 0.0, 0.0), Vector3::new(-1.0, 0.0, 0.0));
+        assert_relative_eq!(side.x, 0.75, epsilon = 1e-6);
+    }
+
+    #[test]
+    fn should_derive_sphere_tangents() {
+        // given a sphere of radius 2 hit in front
+        let transform = Matrix4::from_translation(Vector3::new(3.0, 0.0, 0.0));
+        let sphere = Sphere::from(0.0, 0.0, 0.0, 2.0, transform);
+        let ray = Ray::new(Vector3::new(3.0, 0.0, 5.0), Vector3::new(0.0, 0.0, -1.0), 10000.0);
+        let TestHit::Hit(hit) = sphere.intersection(&ray) else {
+            panic!("Should hit");
+        };
+
+        // then u runs around the equator toward +x and v up, as long as the arcs they span
+        assert_relative_eq!(hit.dpdu().x, 2.0 * float_consts::PI * 2.0, epsilon = 1e-4);
+        assert_relative_eq!(hit.dpdu().magnitude(), 2.0 * float_consts::PI * 2.0, epsilon = 1e-4);
+        assert_relative_eq!(hit.dpdv().y, float_consts::PI * 2.0, epsilon = 1e-4);
+        assert_relative_eq!(hit.dpdv().magnitude(), float_consts::PI * 2.0, epsilon = 1e-4);
+        assert_relative_eq!(hit.dpdu().dot(hit.normal()), 0.0, epsilon = 1e-4);
+    }
+
     #[test]
     fn test_trig_funcs() {
         // given
@@ -0,0 +1,170 @@
+#[cfg(test)]
+mod test;
+
+pub mod daylight {
+    use cgmath::{InnerSpace, Vector3};
+
+    use crate::{float_consts::PI, Float};
+
+    /// Solar illuminance above the atmosphere, in klux to match the sky's kcd/m2
+    const SUN_ILLUMINANCE: Float = 128.0;
+    // wavelengths in micrometers standing for the red, green and blue channels
+    const WAVELENGTHS: [Float; 3] = [0.68, 0.55, 0.44];
+
+    /// Preetham et al. clear sky: a fit of luminance and chromaticity over the angle to the
+    /// zenith and to the sun, driven by the atmosphere `turbidity` (2 is very clear, 10 hazy).
+    /// Radiance comes out in kcd/m2 times `scale`, nothing comes from below the horizon.
+    #[derive(Debug, Clone)]
+    pub struct PreethamSky {
+        /// Unit direction toward the sun
+        sun: Vector3<Float>,
+        turbidity: Float,
+        scale: Float,
+        /// Zenith luminance Y and chromaticity x, y
+        zenith: [Float; 3],
+        /// Distribution coefficients A to E of Y, x and y
+        coefficients: [[Float; 5]; 3],
+        /// Distribution at the zenith, what `zenith` is relative to
+        at_zenith: [Float; 3],
+    }
+
+    impl PreethamSky {
+        pub fn new(sun: Vector3<Float>, turbidity: Float, scale: Float) -> PreethamSky {
+            let sun = sun.normalize();
+            let t = turbidity.clamp(1.7, 10.0);
+            // the fit breaks down with the sun under the horizon
+            let theta_s = sun.y.clamp(0.0, 1.0).acos().min(PI / 2.0 - 1e-3);
+
+            let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+            let luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+            let cubic = |c: [Float; 4]| ((c[0] * theta_s + c[1]) * theta_s + c[2]) * theta_s + c[3];
+            let chromaticity = |t2: [Float; 4], t1: [Float; 4], t0: [Float; 4]| {
+                t * t * cubic(t2) + t * cubic(t1) + cubic(t0)
+            };
+            let zenith = [
+                luminance.max(0.0),
+                chromaticity(
+                    [0.00166, -0.00375, 0.00209, 0.0],
+                    [-0.02903, 0.06377, -0.03202, 0.00394],
+                    [0.11693, -0.21196, 0.06052, 0.25886],
+                ),
+                chromaticity(
+                    [0.00275, -0.00610, 0.00317, 0.0],
+                    [-0.04214, 0.08970, -0.04153, 0.00516],
+                    [0.15346, -0.26756, 0.06670, 0.26688],
+                ),
+            ];
+
+            let coefficients = [
+                [
+                    0.1787 * t - 1.4630,
+                    -0.3554 * t + 0.4275,
+                    -0.0227 * t + 5.3251,
+                    0.1206 * t - 2.5771,
+                    -0.0670 * t + 0.3703,
+                ],
+                [
+                    -0.0193 * t - 0.2592,
+                    -0.0665 * t + 0.0008,
+                    -0.0004 * t + 0.2125,
+                    -0.0641 * t - 0.8989,
+                    -0.0033 * t + 0.0452,
+                ],
+                [
+                    -0.0167 * t - 0.2608,
+                    -0.0950 * t + 0.0092,
+                    -0.0079 * t + 0.2102,
+                    -0.0441 * t - 1.6537,
+                    -0.0109 * t + 0.0529,
+                ],
+            ];
+            let at_zenith = coefficients.map(|c| perez(c, 0.0, theta_s));
+
+            PreethamSky { sun, turbidity: t, scale, zenith, coefficients, at_zenith }
+        }
+
+        pub fn sun(&self) -> Vector3<Float> {
+            self.sun
+        }
+
+        /// Radiance arriving along `-d`, `d` doesn't need to be unit length
+        pub fn radiance(&self, d: Vector3<Float>) -> Vector3<Float> {
+            let d = d.normalize();
+            if d.y <= 0.0 {
+                return Vector3::new(0.0, 0.0, 0.0);
+            }
+            let theta = d.y.acos();
+            let gamma = d.dot(self.sun).clamp(-1.0, 1.0).acos();
+
+            let [luminance, x, y] = [0, 1, 2].map(|i| {
+                self.zenith[i] * perez(self.coefficients[i], theta, gamma) / self.at_zenith[i]
+            });
+            yxy_to_rgb(luminance, x, y) * self.scale
+        }
+
+        /// Color of the matching directional light: the sun dimmed by Rayleigh and aerosol
+        /// extinction along its path through the atmosphere, reddening as it sets
+        pub fn sun_color(&self) -> Vector3<Float> {
+            if self.sun.y <= 0.0 {
+                return Vector3::new(0.0, 0.0, 0.0);
+            }
+            // Kasten and Young relative air mass
+            let elevation = 90.0 - self.sun.y.acos().to_degrees();
+            let air_mass = 1.0 / (self.sun.y + 0.50572 * (elevation + 6.07995).powf(-1.6364));
+            // Angstrom turbidity coefficient as in Preetham's appendix
+            let beta = 0.04608 * self.turbidity - 0.04586;
+
+            let [r, g, b] = WAVELENGTHS.map(|lambda| {
+                let rayleigh = 0.008735 * lambda.powf(-4.08);
+                let aerosol = beta * lambda.powf(-1.3);
+                (-air_mass * (rayleigh + aerosol)).exp()
+            });
+            Vector3::new(r, g, b) * (SUN_ILLUMINANCE * self.scale)
+        }
+    }
+
+    /// Unit direction toward the sun at `latitude` degrees north, on `day` of the year (1 to
+    /// 365) at `hour` of local solar time. +y is up, -z north and +x east.
+    pub fn sun_direction(latitude: Float, day: Float, hour: Float) -> Vector3<Float> {
+        let latitude = latitude.to_radians();
+        let declination = 0.4093 * (2.0 * PI * (day - 81.0) / 368.0).sin();
+        let hour_angle = PI * hour / 12.0;
+
+        let elevation = (latitude.sin() * declination.sin()
+            - latitude.cos() * declination.cos() * hour_angle.cos())
+        .clamp(-1.0, 1.0)
+        .asin();
+        // measured from the south, positive toward the west
+        let azimuth = (-declination.cos() * hour_angle.sin()).atan2(
+            latitude.cos() * declination.sin()
+                - latitude.sin() * declination.cos() * hour_angle.cos(),
+        );
+
+        Vector3::new(
+            -elevation.cos() * azimuth.sin(),
+            elevation.sin(),
+            elevation.cos() * azimuth.cos(),
+        )
+    }
+
+    /// Perez et al. sky distribution over the angle to the zenith and to the sun
+    fn perez(c: [Float; 5], theta: Float, gamma: Float) -> Float {
+        let cos_gamma = gamma.cos();
+        (1.0 + c[0] * (c[1] / theta.cos().max(1e-3)).exp())
+            * (1.0 + c[2] * (c[3] * gamma).exp() + c[4] * cos_gamma * cos_gamma)
+    }
+
+    /// Luminance and chromaticity to linear sRGB
+    fn yxy_to_rgb(luminance: Float, x: Float, y: Float) -> Vector3<Float> {
+        if y <= 0.0 {
+            return Vector3::new(0.0, 0.0, 0.0);
+        }
+        let big_x = x / y * luminance;
+        let big_z = (1.0 - x - y) / y * luminance;
+        Vector3::new(
+            (3.2406 * big_x - 1.5372 * luminance - 0.4986 * big_z).max(0.0),
+            (-0.9689 * big_x + 1.8758 * luminance + 0.0415 * big_z).max(0.0),
+            (0.0557 * big_x - 0.2040 * luminance + 1.0570 * big_z).max(0.0),
+        )
+    }
+}
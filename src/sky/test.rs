@@ -0,0 +1,110 @@
+#[cfg(test)]
+mod sky_test {
+
+    use cgmath::{InnerSpace, Vector3};
+
+    use crate::environment::environments::Environment;
+    use crate::light::lights::Light;
+    use crate::sky::daylight::{sun_direction, PreethamSky};
+    use crate::{float_consts::PI, read_scene, Float};
+    use approx::assert_relative_eq;
+
+    const TOLERANCE: Float = if cfg!(feature = "f32") { 1e-4 } else { 1e-6 };
+
+    fn elevation(sun: Vector3<Float>) -> Float {
+        sun.y.asin().to_degrees()
+    }
+
+    #[test]
+    fn should_put_sun_overhead_at_equinox_on_equator() {
+        let sun = sun_direction(0.0, 81.0, 12.0);
+
+        assert_relative_eq!(sun.magnitude(), 1.0, epsilon = TOLERANCE);
+        assert_relative_eq!(sun.y, 1.0, epsilon = TOLERANCE);
+    }
+
+    #[test]
+    fn should_follow_the_sun_through_the_day() {
+        // given the summer solstice at 40 degrees north
+        let (latitude, day) = (40.0, 172.0);
+
+        // then at noon it is 90 - 40 + 23.45 degrees high, due south
+        let noon = sun_direction(latitude, day, 12.0);
+        assert_relative_eq!(elevation(noon), 73.45, epsilon = 0.05);
+        assert_relative_eq!(noon.x, 0.0, epsilon = TOLERANCE);
+        assert!(noon.z > 0.0);
+
+        // and rises in the east, sets in the west, and is gone at midnight
+        let morning = sun_direction(latitude, day, 8.0);
+        let evening = sun_direction(latitude, day, 16.0);
+        assert!(morning.x > 0.0 && evening.x < 0.0);
+        assert_relative_eq!(morning.y, evening.y, epsilon = TOLERANCE);
+        assert!(morning.y < noon.y);
+        assert!(sun_direction(latitude, day, 0.0).y < 0.0);
+    }
+
+    #[test]
+    fn should_match_zenith_luminance() {
+        // given the sun 30 degrees from the zenith and turbidity 3
+        let theta_s: Float = (30.0 as Float).to_radians();
+        let sky = PreethamSky::new(Vector3::new(theta_s.sin(), theta_s.cos(), 0.0), 3.0, 1.0);
+
+        // then the zenith has the luminance of the fit, (4.0453 T - 4.9710) tan(chi) - 0.2155 T + 2.4192
+        let chi = (4.0 / 9.0 - 3.0 / 120.0) * (PI - 2.0 * theta_s);
+        let expected = (4.0453 * 3.0 - 4.9710) * chi.tan() - 0.2155 * 3.0 + 2.4192;
+        let zenith = sky.radiance(Vector3::unit_y());
+        let luminance = 0.2126 * zenith.x + 0.7152 * zenith.y + 0.0722 * zenith.z;
+        assert_relative_eq!(luminance, expected, max_relative = 1e-2);
+    }
+
+    #[test]
+    fn should_be_blue_and_brightest_around_the_sun() {
+        // given a clear afternoon sky
+        let sun = Vector3::new(0.5, 0.6, 0.3).normalize();
+        let sky = PreethamSky::new(sun, 2.5, 1.0);
+
+        // then the zenith is blue
+        let zenith = sky.radiance(Vector3::unit_y());
+        assert!(zenith.z > zenith.x);
+
+        // and the sky next to the sun outshines the opposite side
+        let near = sky.radiance(sun + Vector3::new(0.0, 0.05, 0.0));
+        let opposite = sky.radiance(Vector3::new(-sun.x, sun.y, -sun.z));
+        assert!(near.y > 2.0 * opposite.y);
+
+        // and nothing comes from below the horizon
+        assert_eq!(sky.radiance(Vector3::new(0.3, -0.1, 0.2)), Vector3::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn should_redden_setting_sun() {
+        let high = PreethamSky::new(Vector3::new(0.0, 1.0, 0.2), 3.0, 1.0).sun_color();
+        let low = PreethamSky::new(Vector3::new(0.0, 0.05, 1.0), 3.0, 1.0).sun_color();
+
+        // overhead it is close to white and bright
+        assert!(high.y > 80.0);
+        assert!(high.x / high.z < 1.5);
+        // near the horizon it dims and turns red
+        assert!(low.y < high.y / 2.0);
+        assert!(low.x / low.z > 2.0 * high.x / high.z);
+    }
+
+    #[test]
+    fn should_read_sun_sky() {
+        // given a sky set from a date and time, at double the default scale
+        let scene = read_scene("tests/fixtures/sun-sky.test".to_string());
+
+        // then the environment is the sky and the sun a directional light from the same place
+        let expected = sun_direction(40.0, 172.0, 9.0);
+        match (&scene.environment, &scene.lights[0]) {
+            (Environment::Sky(sky), Light::Directional { direction, color }) => {
+                assert_relative_eq!(sky.sun().dot(expected), 1.0, epsilon = TOLERANCE);
+                assert_relative_eq!(direction.dot(expected), 1.0, epsilon = TOLERANCE);
+                assert_relative_eq!(color.y, sky.sun_color().y);
+                let reference = PreethamSky::new(expected, 4.0, 0.04);
+                assert_relative_eq!(color.y, reference.sun_color().y, max_relative = 1e-4);
+            },
+            _ => unreachable!(),
+        }
+    }
+}
@@ -0,0 +1,123 @@
+#[cfg(test)]
+mod main_test {
+
+    use std::fs;
+
+    use crate::filter::filters::Filter;
+    use crate::ray_tracer::tracer::{Progressive, SnapshotInterval};
+    use crate::sampling::samplers::{AdaptiveSampling, SamplerKind};
+    use crate::tile::tiles::TileOrder;
+    use crate::{hash_scene, read_scene, scene_args, CliArgs};
+
+    #[test]
+    fn should_parse_command_line() {
+        let args = [
+            "scene.test",
+            "--integrator",
+            "ao 8",
+            "out.png",
+            "--sampler",
+            "halton",
+            "--heatmap",
+            "spp.png",
+            "--progressive",
+            "4 passes",
+            "--threads",
+            "3",
+            "--workers",
+            "2,render-box:7878",
+            "--worker",
+        ]
+        .map(String::from);
+
+        let cli = CliArgs::parse(&args);
+
+        assert_eq!(cli.positional, vec!["scene.test", "out.png"]);
+        assert_eq!(cli.integrator.as_deref(), Some("ao 8"));
+        assert_eq!(cli.sampler.as_deref(), Some("halton"));
+        assert_eq!(cli.heatmap.as_deref(), Some("spp.png"));
+        assert_eq!(cli.progressive.as_deref(), Some("4 passes"));
+        assert_eq!(cli.threads, Some(3));
+        assert_eq!(cli.workers.as_deref(), Some("2,render-box:7878"));
+        assert!(cli.worker);
+        assert_eq!(cli.listen, None);
+    }
+
+    #[test]
+    fn should_parse_arguments_strictly() {
+        // numbers follow the names their command reads
+        assert_eq!(scene_args("sphere", &["1", "0", "2", "3"]), Ok(vec![1.0, 0.0, 2.0, 3.0]));
+        assert_eq!(scene_args("filter", &["gaussian", "1", "2"]), Ok(vec![1.0, 2.0]));
+        assert_eq!(scene_args("medium", &["none"]), Ok(vec![]));
+        assert_eq!(scene_args("texture", &["diffuse", "checker.png", "clamp"]), Ok(vec![]));
+        assert_eq!(
+            scene_args("texture", &["diffuse", "noise", "4", "0", "world"]),
+            Ok(vec![4.0, 0.0])
+        );
+        assert_eq!(scene_args("progressive", &["5", "seconds", "budget", "60"]), Ok(vec![]));
+
+        // a malformed number is reported instead of shifting the ones after it
+        assert_eq!(scene_args("sphere", &["1", "x", "2", "3"]), Err("x"));
+        assert_eq!(scene_args("sphere", &["x", "1", "2", "3"]), Err("x"));
+        assert_eq!(scene_args("filter", &["gaussian", "1", "2x"]), Err("2x"));
+        assert_eq!(scene_args("texture", &["diffuse", "noise", "4", "x", "1", "world"]), Err("x"));
+    }
+
+    #[test]
+    fn should_hash_files_the_scene_loads() {
+        // given a scene loading a mesh file next to it
+        let dir = std::env::temp_dir().join(format!("my-ray-tracer-hash-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("scene.test");
+        fs::write(&file, "size 4 4\ncamera 0 0 5 0 0 0 0 1 0 45\nply quad.ply\n").unwrap();
+        fs::copy("tests/fixtures/quad_le.ply", dir.join("quad.ply")).unwrap();
+        let file = file.to_str().unwrap();
+        let cli = CliArgs::parse(&[]);
+        let scene = read_scene(file.to_string());
+        let before = hash_scene(file, &scene, &cli).unwrap();
+
+        // when the mesh file changes and the scene file doesn't
+        fs::copy("tests/fixtures/quad_be.ply", dir.join("quad.ply")).unwrap();
+        let after = hash_scene(file, &scene, &cli).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        // then the scene is another one
+        assert_eq!(scene.sources, vec![dir.join("quad.ply")]);
+        assert_ne!(before, after);
+    }
+
+    #[test]
+    fn should_read_render_settings() {
+        // given a scene setting every option and one leaving them alone
+        let set = read_scene("tests/fixtures/settings.test".to_string()).settings;
+        let default = read_scene("tests/fixtures/lit-sphere.test".to_string()).settings;
+
+        // then each option reads as written, or keeps its default
+        assert_eq!((set.sampler, default.sampler), (SamplerKind::Sobol, SamplerKind::Random));
+        assert_eq!(set.filter, Filter::Gaussian { radius: 1.0, alpha: 2.0 });
+        assert_eq!(default.filter, Filter::default());
+        assert_eq!(set.adaptive, Some(AdaptiveSampling { threshold: 0.05, max_spp: 32 }));
+        assert_eq!(default.adaptive, None);
+        assert_eq!(
+            set.progressive,
+            Some(Progressive {
+                snapshots: SnapshotInterval::Seconds(5.0),
+                time_budget: Some(600.0),
+                noise_threshold: Some(0.01),
+            })
+        );
+        assert_eq!(default.progressive, None);
+        assert_eq!((set.tile_size, set.tile_order), (2, TileOrder::Hilbert));
+        assert_eq!(default.tile_order, TileOrder::default());
+    }
+
+    #[test]
+    fn should_read_every_fixture_and_example_scene() {
+        let scenes = fs::read_dir("tests/fixtures").unwrap().chain(fs::read_dir("src").unwrap());
+        for path in scenes.map(|it| it.unwrap().path()) {
+            if path.extension().is_some_and(|it| it == "test") {
+                read_scene(path.to_str().unwrap().to_string());
+            }
+        }
+    }
+}
@@ -0,0 +1,345 @@
+#[cfg(test)]
+mod test;
+
+pub mod textures {
+    use std::path::Path;
+
+    use cgmath::{Vector2, Vector3};
+
+    use crate::Float;
+
+    /// Where a texture is looked up: surface coordinates and the hit point in world and object
+    /// space, with the surface normal and the world space derivatives of the point along u and v
+    #[derive(Debug, Clone, Copy)]
+    pub struct SurfacePoint {
+        pub uv: Vector2<Float>,
+        pub world: Vector3<Float>,
+        pub object: Vector3<Float>,
+        pub normal: Vector3<Float>,
+        pub dpdu: Vector3<Float>,
+        pub dpdv: Vector3<Float>,
+    }
+
+    impl SurfacePoint {
+        /// Lookup point with the geometry of a surface that has no mapping
+        pub fn new(uv: Vector2<Float>, world: Vector3<Float>, object: Vector3<Float>) -> Self {
+            SurfacePoint {
+                uv,
+                world,
+                object,
+                normal: Vector3::unit_z(),
+                dpdu: Vector3::unit_x(),
+                dpdv: Vector3::unit_y(),
+            }
+        }
+
+        /// The point moved by `du`, `dv` along the surface. The object space point moves by the
+        /// world space offset, which is exact for shapes that are only translated.
+        pub fn offset(&self, du: Float, dv: Float) -> SurfacePoint {
+            let delta = self.dpdu * du + self.dpdv * dv;
+            SurfacePoint {
+                uv: self.uv + Vector2::new(du, dv),
+                world: self.world + delta,
+                object: self.object + delta,
+                ..*self
+            }
+        }
+    }
+
+    /// Anything a material slot can read its value from
+    #[derive(Debug)]
+    pub enum Texture {
+        Image(ImageTexture),
+        /// Solid texture filling space, so it cuts through objects like carved material
+        Procedural {
+            pattern: Pattern,
+            space: TextureSpace,
+        },
+    }
+
+    impl Texture {
+        pub fn eval(&self, point: &SurfacePoint) -> Vector3<Float> {
+            match self {
+                Texture::Image(image) => image.sample(point.uv),
+                Texture::Procedural { pattern, space: TextureSpace::Object } => {
+                    pattern.eval(point.object)
+                },
+                Texture::Procedural { pattern, space: TextureSpace::World } => {
+                    pattern.eval(point.world)
+                },
+            }
+        }
+    }
+
+    /// Space procedural textures are evaluated in. Object space patterns move with their shape.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub enum TextureSpace {
+        #[default]
+        Object,
+        World,
+    }
+
+    /// Solid patterns blending two colors. `scale` is the pattern frequency, how many cells,
+    /// noise features or rings fit in a unit of space.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum Pattern {
+        /// Alternating unit cubes
+        Checker { scale: Float, even: Vector3<Float>, odd: Vector3<Float> },
+        /// Perlin noise
+        Noise { scale: Float, low: Vector3<Float>, high: Vector3<Float> },
+        /// Sum of `octaves` noise layers of doubling frequency and halving amplitude
+        Turbulence { scale: Float, octaves: u32, low: Vector3<Float>, high: Vector3<Float> },
+        /// Veins along x bent by turbulence, `strength` sets how much
+        Marble {
+            scale: Float,
+            octaves: u32,
+            strength: Float,
+            low: Vector3<Float>,
+            high: Vector3<Float>,
+        },
+        /// Rings around the y axis made uneven by noise, `strength` sets how much
+        Wood { scale: Float, strength: Float, low: Vector3<Float>, high: Vector3<Float> },
+    }
+
+    impl Pattern {
+        /// Builds a pattern from its name in the scene file and its numeric parameters, colors
+        /// last:
+        /// - `checker scale even odd`
+        /// - `noise scale low high`
+        /// - `fbm scale octaves low high`
+        /// - `marble scale octaves strength low high`
+        /// - `wood scale strength low high`
+        pub fn from_name(name: &str, args: &[Float]) -> Option<Pattern> {
+            let color = |i: usize| Vector3::new(args[i], args[i + 1], args[i + 2]);
+            match name {
+                "checker" => {
+                    Some(Pattern::Checker { scale: args[0], even: color(1), odd: color(4) })
+                },
+                "noise" => Some(Pattern::Noise { scale: args[0], low: color(1), high: color(4) }),
+                "fbm" => Some(Pattern::Turbulence {
+                    scale: args[0],
+                    octaves: args[1] as u32,
+                    low: color(2),
+                    high: color(5),
+                }),
+                "marble" => Some(Pattern::Marble {
+                    scale: args[0],
+                    octaves: args[1] as u32,
+                    strength: args[2],
+                    low: color(3),
+                    high: color(6),
+                }),
+                "wood" => Some(Pattern::Wood {
+                    scale: args[0],
+                    strength: args[1],
+                    low: color(2),
+                    high: color(5),
+                }),
+                _ => None,
+            }
+        }
+
+        pub fn eval(&self, p: Vector3<Float>) -> Vector3<Float> {
+            let mix = |low: Vector3<Float>, high: Vector3<Float>, t: Float| {
+                let t = t.clamp(0.0, 1.0);
+                low * (1.0 - t) + high * t
+            };
+            match *self {
+                Pattern::Checker { scale, even, odd } => {
+                    let q = p * scale;
+                    let cell = q.x.floor() as i64 + q.y.floor() as i64 + q.z.floor() as i64;
+                    if cell.rem_euclid(2) == 0 {
+                        even
+                    } else {
+                        odd
+                    }
+                },
+                Pattern::Noise { scale, low, high } => {
+                    mix(low, high, 0.5 + 0.5 * perlin(p * scale))
+                },
+                Pattern::Turbulence { scale, octaves, low, high } => {
+                    mix(low, high, turbulence(p * scale, octaves))
+                },
+                Pattern::Marble { scale, octaves, strength, low, high } => {
+                    let q = p * scale;
+                    let phase = q.x + strength * turbulence(q, octaves);
+                    mix(low, high, 0.5 + 0.5 * phase.sin())
+                },
+                Pattern::Wood { scale, strength, low, high } => {
+                    let q = p * scale;
+                    let rings = (q.x * q.x + q.z * q.z).sqrt() + strength * perlin(q);
+                    mix(low, high, rings - rings.floor())
+                },
+            }
+        }
+    }
+
+    /// Ken Perlin's improved gradient noise, in about [-1, 1] and 0 on the integer lattice
+    pub fn perlin(p: Vector3<Float>) -> Float {
+        let cell = p.map(|it| it.floor());
+        let (x, y, z) = (p.x - cell.x, p.y - cell.y, p.z - cell.z);
+        let (i, j, k) = (cell.x as i64, cell.y as i64, cell.z as i64);
+        let (u, v, w) = (fade(x), fade(y), fade(z));
+
+        let corner = |di: i64, dj: i64, dk: i64| {
+            let hash = lattice_hash(i + di, j + dj, k + dk);
+            gradient(hash, x - di as Float, y - dj as Float, z - dk as Float)
+        };
+        let lerp = |t: Float, a: Float, b: Float| a + t * (b - a);
+
+        lerp(
+            w,
+            lerp(
+                v,
+                lerp(u, corner(0, 0, 0), corner(1, 0, 0)),
+                lerp(u, corner(0, 1, 0), corner(1, 1, 0)),
+            ),
+            lerp(
+                v,
+                lerp(u, corner(0, 0, 1), corner(1, 0, 1)),
+                lerp(u, corner(0, 1, 1), corner(1, 1, 1)),
+            ),
+        )
+    }
+
+    /// Fractal sum of absolute noise, fBm turbulence in [0, 1)
+    pub fn turbulence(p: Vector3<Float>, octaves: u32) -> Float {
+        let mut sum = 0.0;
+        let mut norm = 0.0;
+        let mut amplitude = 1.0;
+        let mut q = p;
+        for _ in 0..octaves.max(1) {
+            sum += amplitude * perlin(q).abs();
+            norm += amplitude;
+            amplitude *= 0.5;
+            q *= 2.0;
+        }
+        sum / norm
+    }
+
+    fn fade(t: Float) -> Float {
+        t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+    }
+
+    /// Dot product with one of the 12 cube edge directions picked by the hash
+    fn gradient(hash: u32, x: Float, y: Float, z: Float) -> Float {
+        let h = hash & 15;
+        let u = if h < 8 { x } else { y };
+        let v = match h {
+            0..=3 => y,
+            12 | 14 => x,
+            _ => z,
+        };
+        (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+    }
+
+    /// Scrambles lattice coordinates, stands in for Perlin's permutation table
+    fn lattice_hash(i: i64, j: i64, k: i64) -> u32 {
+        let mut h = (i as u32).wrapping_mul(0x8da6_b343)
+            ^ (j as u32).wrapping_mul(0xd816_3841)
+            ^ (k as u32).wrapping_mul(0xcb1a_b31f);
+        h ^= h >> 16;
+        h = h.wrapping_mul(0x7feb_352d);
+        h ^= h >> 15;
+        h = h.wrapping_mul(0x846c_a68b);
+        h ^ (h >> 16)
+    }
+
+    /// What texture coordinates outside [0, 1] read
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub enum WrapMode {
+        /// Tile the image
+        #[default]
+        Repeat,
+        /// Stretch the border texels
+        Clamp,
+        /// Tile, flipping every other copy so the seams match
+        Mirror,
+    }
+
+    impl WrapMode {
+        pub fn from_name(name: &str) -> Option<WrapMode> {
+            match name {
+                "repeat" => Some(WrapMode::Repeat),
+                "clamp" => Some(WrapMode::Clamp),
+                "mirror" => Some(WrapMode::Mirror),
+                _ => None,
+            }
+        }
+
+        /// Texel index for a possibly out of range integer coordinate
+        fn wrap(self, i: i64, size: usize) -> usize {
+            let size = size as i64;
+            let i = match self {
+                WrapMode::Repeat => i.rem_euclid(size),
+                WrapMode::Clamp => i.clamp(0, size - 1),
+                WrapMode::Mirror => {
+                    let period = i.rem_euclid(2 * size);
+                    if period < size {
+                        period
+                    } else {
+                        2 * size - 1 - period
+                    }
+                },
+            };
+            i as usize
+        }
+    }
+
+    /// Image looked up with bilinear filtering. uv (0, 0) is the bottom left corner of the
+    /// image, v goes up. Colors are used as stored, like every other color of the renderer.
+    #[derive(Debug)]
+    pub struct ImageTexture {
+        width: usize,
+        height: usize,
+        /// Row major, top row first like the image file
+        texels: Vec<Vector3<Float>>,
+        wrap: WrapMode,
+    }
+
+    impl ImageTexture {
+        /// Reads any format the `image` crate knows, PNG and JPEG in particular
+        pub fn load(path: &Path, wrap: WrapMode) -> image::ImageResult<ImageTexture> {
+            let image = image::open(path)?.into_rgb32f();
+            let texels = image
+                .pixels()
+                .map(|it| Vector3::new(it[0] as Float, it[1] as Float, it[2] as Float))
+                .collect();
+            Ok(ImageTexture::from_texels(
+                image.width() as usize,
+                image.height() as usize,
+                texels,
+                wrap,
+            ))
+        }
+
+        pub fn from_texels(
+            width: usize,
+            height: usize,
+            texels: Vec<Vector3<Float>>,
+            wrap: WrapMode,
+        ) -> ImageTexture {
+            assert!(width > 0 && height > 0, "empty texture");
+            assert_eq!(texels.len(), width * height, "texture size mismatch");
+            ImageTexture { width, height, texels, wrap }
+        }
+
+        /// Blend of the four texels around `uv`, texel centers sit at half integer coordinates
+        pub fn sample(&self, uv: Vector2<Float>) -> Vector3<Float> {
+            let x = uv.x * self.width as Float - 0.5;
+            let y = (1.0 - uv.y) * self.height as Float - 0.5;
+            let (x0, y0) = (x.floor(), y.floor());
+            let (fx, fy) = (x - x0, y - y0);
+            let (x0, y0) = (x0 as i64, y0 as i64);
+
+            let texel = |x: i64, y: i64| {
+                let x = self.wrap.wrap(x, self.width);
+                let y = self.wrap.wrap(y, self.height);
+                self.texels[y * self.width + x]
+            };
+            let top = texel(x0, y0) * (1.0 - fx) + texel(x0 + 1, y0) * fx;
+            let bottom = texel(x0, y0 + 1) * (1.0 - fx) + texel(x0 + 1, y0 + 1) * fx;
+            top * (1.0 - fy) + bottom * fy
+        }
+    }
+}
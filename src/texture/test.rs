@@ -0,0 +1,198 @@
+#[cfg(test)]
+mod texture_test {
+
+    use std::path::Path;
+
+    use cgmath::{Vector2, Vector3};
+
+    use crate::camera::camera_view::Ray;
+    use crate::integrator::integrators::{Integrator, Whitted};
+    use crate::material::materials::Material;
+    use crate::sampling::samplers::Rng;
+    use crate::texture::textures::{
+        perlin, turbulence, ImageTexture, Pattern, SurfacePoint, Texture, WrapMode,
+    };
+    use crate::{read_scene, Float};
+    use approx::assert_relative_eq;
+
+    const RED: Vector3<Float> = Vector3::new(1.0, 0.0, 0.0);
+    const GREEN: Vector3<Float> = Vector3::new(0.0, 1.0, 0.0);
+    const BLUE: Vector3<Float> = Vector3::new(0.0, 0.0, 1.0);
+    const WHITE: Vector3<Float> = Vector3::new(1.0, 1.0, 1.0);
+
+    /// Same layout as checker.png: red, green on top of blue, white
+    fn checker(wrap: WrapMode) -> ImageTexture {
+        ImageTexture::from_texels(2, 2, vec![RED, GREEN, BLUE, WHITE], wrap)
+    }
+
+    fn assert_color(actual: Vector3<Float>, expected: Vector3<Float>) {
+        assert_relative_eq!(actual.x, expected.x, epsilon = 1e-5);
+        assert_relative_eq!(actual.y, expected.y, epsilon = 1e-5);
+        assert_relative_eq!(actual.z, expected.z, epsilon = 1e-5);
+    }
+
+    #[test]
+    fn should_read_texel_centers_exactly() {
+        let texture = checker(WrapMode::Clamp);
+
+        // v goes up, so the top row of the image is at v = 3/4
+        assert_color(texture.sample(Vector2::new(0.25, 0.75)), RED);
+        assert_color(texture.sample(Vector2::new(0.75, 0.75)), GREEN);
+        assert_color(texture.sample(Vector2::new(0.25, 0.25)), BLUE);
+        assert_color(texture.sample(Vector2::new(0.75, 0.25)), WHITE);
+    }
+
+    #[test]
+    fn should_blend_between_texels() {
+        let texture = checker(WrapMode::Clamp);
+
+        // half way between red and green, then the middle of all four
+        assert_color(texture.sample(Vector2::new(0.5, 0.75)), (RED + GREEN) / 2.0);
+        assert_color(texture.sample(Vector2::new(0.5, 0.5)), (RED + GREEN + BLUE + WHITE) / 4.0);
+        // a quarter of the way from red to blue
+        assert_color(texture.sample(Vector2::new(0.25, 0.625)), RED * 0.75 + BLUE * 0.25);
+    }
+
+    #[test]
+    fn should_wrap_outside_unit_square() {
+        // on the left border, half way to the texel on the other side
+        let uv = Vector2::new(0.0, 0.75);
+
+        // repeat blends with the right column, clamp stretches the left one
+        assert_color(checker(WrapMode::Repeat).sample(uv), (RED + GREEN) / 2.0);
+        assert_color(checker(WrapMode::Clamp).sample(uv), RED);
+        assert_color(checker(WrapMode::Mirror).sample(uv), RED);
+
+        // one tile over, repeat finds the same texel and mirror its mirror image
+        let uv = Vector2::new(1.25, 0.75);
+        assert_color(checker(WrapMode::Repeat).sample(uv), RED);
+        assert_color(checker(WrapMode::Mirror).sample(uv), GREEN);
+        assert_color(checker(WrapMode::Clamp).sample(uv), GREEN);
+    }
+
+    #[test]
+    fn should_load_png() {
+        let texture =
+            ImageTexture::load(Path::new("tests/fixtures/checker.png"), WrapMode::Clamp).unwrap();
+
+        assert_color(texture.sample(Vector2::new(0.25, 0.75)), RED);
+        assert_color(texture.sample(Vector2::new(0.75, 0.25)), WHITE);
+    }
+
+    #[test]
+    fn should_replace_material_colors_with_textures() {
+        let textures = vec![Texture::Image(checker(WrapMode::Clamp))];
+        let material = Material {
+            diffuse: Vector3::new(0.5, 0.5, 0.5),
+            specular: Vector3::new(0.1, 0.1, 0.1),
+            diffuse_texture: Some(0),
+            roughness_texture: Some(0),
+            ..Default::default()
+        };
+        let origin = Vector3::new(0.0, 0.0, 0.0);
+        let point = SurfacePoint::new(Vector2::new(0.75, 0.75), origin, origin);
+
+        let at = material.at(&point, &textures);
+
+        assert!(material.is_textured());
+        assert_color(at.diffuse, GREEN);
+        assert_color(at.specular, material.specular);
+        // scalar slots read the luminance
+        assert_relative_eq!(at.roughness, 0.7152, epsilon = 1e-4);
+    }
+
+    #[test]
+    fn should_shade_textured_quad() {
+        // given a quad with uvs over the whole checker, lit head on
+        let scene = read_scene("tests/fixtures/textured.test".to_string());
+        let mut rng = Rng::new(0, 0);
+        let see = |x: Float, y: Float, rng: &mut Rng| {
+            let ray = Ray::new(Vector3::new(x, y, 5.0), Vector3::new(0.0, 0.0, -1.0), 10000.0);
+            Whitted.radiance(&ray, &scene, rng)
+        };
+
+        // then each corner has its texel color
+        assert_color(see(-0.9, 0.9, &mut rng), RED);
+        assert_color(see(0.9, 0.9, &mut rng), GREEN);
+        assert_color(see(-0.9, -0.9, &mut rng), BLUE);
+        assert_color(see(0.9, -0.9, &mut rng), WHITE);
+    }
+
+    #[test]
+    fn should_alternate_checker_cells() {
+        let pattern = Pattern::from_name("checker", &[2.0, 1.0, 1.0, 1.0, 0.0, 0.0, 0.0]).unwrap();
+
+        // cells are half a unit wide at scale 2, and keep alternating below zero
+        assert_color(pattern.eval(Vector3::new(0.1, 0.1, 0.1)), WHITE);
+        assert_color(pattern.eval(Vector3::new(0.6, 0.1, 0.1)), Vector3::new(0.0, 0.0, 0.0));
+        assert_color(pattern.eval(Vector3::new(-0.1, 0.1, 0.1)), Vector3::new(0.0, 0.0, 0.0));
+        assert_color(pattern.eval(Vector3::new(-0.1, -0.1, 0.1)), WHITE);
+    }
+
+    #[test]
+    fn should_vanish_on_noise_lattice() {
+        for (x, y, z) in [(0.0, 0.0, 0.0), (3.0, -2.0, 7.0), (-5.0, 1.0, -1.0)] {
+            assert_relative_eq!(perlin(Vector3::new(x, y, z)), 0.0);
+        }
+    }
+
+    #[test]
+    fn should_keep_noise_smooth_and_bounded() {
+        let mut low: Float = 0.0;
+        let mut high: Float = 0.0;
+        for i in 0..1000 {
+            let i = i as Float;
+            let p = Vector3::new(i * 0.137, i * 0.071 - 20.0, i * 0.293);
+            let noise = perlin(p);
+
+            // the same point always gives the same value, a close one a close value
+            assert_eq!(noise, perlin(p));
+            assert!((noise - perlin(p + Vector3::new(1e-3, 1e-3, 1e-3))).abs() < 1e-2);
+            assert!(noise.abs() <= 1.0 + 1e-6);
+            let fbm = turbulence(p, 4);
+            assert!((0.0..1.0).contains(&fbm));
+
+            low = low.min(noise);
+            high = high.max(noise);
+        }
+
+        // and it does vary
+        assert!(low < -0.3 && high > 0.3);
+    }
+
+    #[test]
+    fn should_blend_pattern_colors() {
+        let patterns = [
+            Pattern::from_name("noise", &[3.0, 0.0, 0.0, 0.0, 1.0, 1.0, 1.0]).unwrap(),
+            Pattern::from_name("fbm", &[3.0, 5.0, 0.0, 0.0, 0.0, 1.0, 1.0, 1.0]).unwrap(),
+            Pattern::from_name("marble", &[3.0, 5.0, 4.0, 0.0, 0.0, 0.0, 1.0, 1.0, 1.0]).unwrap(),
+            Pattern::from_name("wood", &[3.0, 0.5, 0.0, 0.0, 0.0, 1.0, 1.0, 1.0]).unwrap(),
+        ];
+
+        for pattern in patterns {
+            for i in 0..200 {
+                let i = i as Float;
+                let color = pattern.eval(Vector3::new(i * 0.031, i * 0.057, -i * 0.013));
+                assert!((0.0..=1.0).contains(&color.x), "{:?} gave {:?}", pattern, color);
+                assert_eq!(color.x, color.y);
+            }
+        }
+        assert_eq!(Pattern::from_name("brick", &[]), None);
+    }
+
+    #[test]
+    fn should_evaluate_in_object_or_world_space() {
+        // given the same checker on two moved spheres, in object space then in world space
+        let scene = read_scene("tests/fixtures/procedural.test".to_string());
+        let mut rng = Rng::new(0, 0);
+        let see = |x: Float, rng: &mut Rng| {
+            let ray = Ray::new(Vector3::new(x, 0.25, 5.0), Vector3::new(0.0, 0.0, -1.0), 10000.0);
+            Whitted.radiance(&ray, &scene, rng)
+        };
+
+        // then the object space one is lit where its own cell is white, at (0.25, 0.25, 0.94)
+        assert!(see(1.25, &mut rng).x > 0.5);
+        // and the world space one falls in a black cell around x = 5.25
+        assert_color(see(5.25, &mut rng), Vector3::new(0.0, 0.0, 0.0));
+    }
+}
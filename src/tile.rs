@@ -0,0 +1,92 @@
+#[cfg(test)]
+mod test;
+
+pub mod tiles {
+    use crate::Float;
+
+    /// Rectangle of pixels rendered as one task
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Tile {
+        pub x: u32,
+        pub y: u32,
+        pub width: u32,
+        pub height: u32,
+    }
+
+    /// Order tiles are handed out to the threads in
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub enum TileOrder {
+        /// Rows of tiles from the top
+        #[default]
+        Scanline,
+        /// Outward from the middle of the image, where the subject usually is
+        Spiral,
+        /// Along a Hilbert curve, each tile next to the one before so threads share cache
+        Hilbert,
+    }
+
+    impl TileOrder {
+        pub fn from_name(name: &str) -> Option<TileOrder> {
+            match name {
+                "scanline" => Some(TileOrder::Scanline),
+                "spiral" => Some(TileOrder::Spiral),
+                "hilbert" => Some(TileOrder::Hilbert),
+                _ => None,
+            }
+        }
+    }
+
+    /// Splits a `width` by `height` image into tiles of `size` pixels square, clipped at the
+    /// right and bottom edges, in the given order
+    pub fn split(width: u32, height: u32, size: u32, order: TileOrder) -> Vec<Tile> {
+        let size = size.max(1);
+        let (columns, rows) = (width.div_ceil(size), height.div_ceil(size));
+        let mut cells: Vec<(u32, u32)> =
+            (0..rows).flat_map(|row| (0..columns).map(move |column| (column, row))).collect();
+
+        match order {
+            TileOrder::Scanline => {},
+            TileOrder::Spiral => {
+                let center = ((columns as Float - 1.0) / 2.0, (rows as Float - 1.0) / 2.0);
+                let key = |&(column, row): &(u32, u32)| {
+                    let (dx, dy) = (column as Float - center.0, row as Float - center.1);
+                    (dx.abs().max(dy.abs()), dy.atan2(dx))
+                };
+                cells.sort_by(|a, b| key(a).partial_cmp(&key(b)).unwrap());
+            },
+            TileOrder::Hilbert => {
+                let side = columns.max(rows).next_power_of_two();
+                cells.sort_by_key(|&(column, row)| hilbert_index(side, column, row));
+            },
+        }
+
+        cells
+            .into_iter()
+            .map(|(column, row)| {
+                let (x, y) = (column * size, row * size);
+                Tile { x, y, width: size.min(width - x), height: size.min(height - y) }
+            })
+            .collect()
+    }
+
+    /// Distance along the Hilbert curve filling a `side` by `side` grid, `side` a power of two
+    fn hilbert_index(side: u32, mut x: u32, mut y: u32) -> u64 {
+        let mut index = 0;
+        let mut s = side / 2;
+        while s > 0 {
+            let rx = (x & s > 0) as u32;
+            let ry = (y & s > 0) as u32;
+            index += s as u64 * s as u64 * ((3 * rx) ^ ry) as u64;
+            // rotate the quadrant so the curve inside it starts and ends next to its neighbors
+            if ry == 0 {
+                if rx == 1 {
+                    x = side - 1 - x;
+                    y = side - 1 - y;
+                }
+                std::mem::swap(&mut x, &mut y);
+            }
+            s /= 2;
+        }
+        index
+    }
+}
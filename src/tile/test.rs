@@ -0,0 +1,62 @@
+#[cfg(test)]
+mod tile_test {
+
+    use crate::tile::tiles::{split, Tile, TileOrder};
+
+    const ORDERS: [TileOrder; 3] = [TileOrder::Scanline, TileOrder::Spiral, TileOrder::Hilbert];
+
+    #[test]
+    fn should_cover_every_pixel_once() {
+        for order in ORDERS {
+            // given a 70 by 45 image split into 16 pixel tiles
+            let tiles = split(70, 45, 16, order);
+
+            // then edge tiles are clipped and each pixel belongs to exactly one tile
+            assert_eq!(tiles.len(), 5 * 3, "{:?}", order);
+            let mut covered = vec![0; 70 * 45];
+            for tile in &tiles {
+                for y in tile.y..tile.y + tile.height {
+                    for x in tile.x..tile.x + tile.width {
+                        covered[(y * 70 + x) as usize] += 1;
+                    }
+                }
+            }
+            assert!(covered.iter().all(|it| *it == 1), "{:?}", order);
+            assert!(tiles.contains(&Tile { x: 64, y: 32, width: 6, height: 13 }));
+        }
+    }
+
+    #[test]
+    fn should_order_scanline_tiles_by_rows() {
+        let tiles = split(32, 32, 16, TileOrder::Scanline);
+
+        let corners: Vec<(u32, u32)> = tiles.iter().map(|it| (it.x, it.y)).collect();
+        assert_eq!(corners, vec![(0, 0), (16, 0), (0, 16), (16, 16)]);
+    }
+
+    #[test]
+    fn should_start_spiral_in_the_middle() {
+        // given 5 by 5 tiles
+        let tiles = split(50, 50, 10, TileOrder::Spiral);
+
+        // then the middle tile comes first, then its ring of 8, then the outer ring
+        let ring = |tile: &Tile| (tile.x as i32 / 10 - 2).abs().max((tile.y as i32 / 10 - 2).abs());
+        let rings: Vec<i32> = tiles.iter().map(ring).collect();
+        assert_eq!(rings[0], 0);
+        assert!(rings[1..9].iter().all(|it| *it == 1));
+        assert!(rings[9..].iter().all(|it| *it == 2));
+    }
+
+    #[test]
+    fn should_walk_hilbert_curve_between_neighbors() {
+        // given 8 by 8 tiles
+        let tiles = split(64, 64, 8, TileOrder::Hilbert);
+
+        // then it starts in a corner and every tile touches the one before
+        assert_eq!((tiles[0].x, tiles[0].y), (0, 0));
+        for pair in tiles.windows(2) {
+            let step = pair[0].x.abs_diff(pair[1].x) + pair[0].y.abs_diff(pair[1].y);
+            assert_eq!(step, 8);
+        }
+    }
+}
@@ -0,0 +1,52 @@
+//! Renders a scene on worker processes of the binary, over pipes and over TCP, and checks the
+//! images match a render on one process
+
+use std::net::TcpListener;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+use std::{env, path::PathBuf};
+
+// scene paths are relative to src/
+const SCENE: &str = "../tests/fixtures/distributed.test";
+
+fn render(name: &str, options: &[&str]) -> image::RgbImage {
+    let output = env::temp_dir().join(format!("distributed_{}_{}.png", name, std::process::id()));
+    let status = Command::new(env!("CARGO_BIN_EXE_my-ray-tracer"))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .args([SCENE, output.to_str().unwrap()])
+        .args(options)
+        .stdout(Stdio::null())
+        .status()
+        .unwrap();
+    assert!(status.success());
+    image::open(output).unwrap().to_rgb8()
+}
+
+#[test]
+fn workers_should_render_same_image_as_one_process() {
+    let local = render("local", &[]);
+
+    // two local workers talking over pipes
+    assert_eq!(render("pipes", &["--workers", "2"]), local);
+
+    // a worker listening on a port the system picked for a moment ago
+    let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let address = format!("127.0.0.1:{}", port);
+    let mut worker = Command::new(PathBuf::from(env!("CARGO_BIN_EXE_my-ray-tracer")))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .args([SCENE, "--listen", &address])
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+    for _ in 0..100 {
+        if std::net::TcpStream::connect(&address).is_ok() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    let tcp = render("tcp", &["--workers", &format!("{},{}", address, address)]);
+    worker.kill().unwrap();
+    worker.wait().unwrap();
+    assert_eq!(tcp, local);
+}
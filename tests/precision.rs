@@ -0,0 +1,56 @@
+//! Renders the same scenes with the default f64 build and with an f32 build and checks the images
+//! stay within tolerance. It compiles a second copy of the tracer, so it only runs on demand:
+//! cargo test --test precision -- --ignored
+
+use std::{env, path::PathBuf, process::Command};
+
+use log::info;
+
+// channel difference above which a pixel counts as different
+const PIXEL_TOLERANCE: i32 = 8;
+// share of pixels allowed to differ, precision only moves silhouettes by a pixel
+const MAX_DIFFERENT_PIXELS: f64 = 0.005;
+
+fn build_f32_binary() -> PathBuf {
+    let target_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("target").join("f32");
+    let status = Command::new(env!("CARGO"))
+        .args(["build", "--features", "f32", "--target-dir"])
+        .arg(&target_dir)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    target_dir.join("debug").join("my-ray-tracer")
+}
+
+fn render(binary: &PathBuf, scene: &str, output: &PathBuf) -> image::RgbImage {
+    let status = Command::new(binary)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .args([scene, output.to_str().unwrap()])
+        .status()
+        .unwrap();
+    assert!(status.success());
+    image::open(output).unwrap().to_rgb8()
+}
+
+#[test]
+#[ignore]
+fn f32_render_should_match_f64_render() {
+    log4rs::init_file("src/log4rs.yml", Default::default()).unwrap();
+    let f64_binary = PathBuf::from(env!("CARGO_BIN_EXE_my-ray-tracer"));
+    let f32_binary = build_f32_binary();
+
+    for scene in ["scene3.test", "scene8-sdf.test"] {
+        let f64_image = render(&f64_binary, scene, &env::temp_dir().join("precision_f64.png"));
+        let f32_image = render(&f32_binary, scene, &env::temp_dir().join("precision_f32.png"));
+        assert_eq!(f64_image.dimensions(), f32_image.dimensions());
+
+        let different = f64_image
+            .pixels()
+            .zip(f32_image.pixels())
+            .filter(|(a, b)| (0..3).any(|c| (a[c] as i32 - b[c] as i32).abs() > PIXEL_TOLERANCE))
+            .count();
+        let share = different as f64 / (f64_image.width() * f64_image.height()) as f64;
+        info!("{}: {} pixels differ ({:.4}%)", scene, different, share * 100.0);
+        assert!(share <= MAX_DIFFERENT_PIXELS);
+    }
+}